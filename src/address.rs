@@ -0,0 +1,182 @@
+use std::fmt;
+use std::ops::Deref;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use sha3::{Digest, Keccak256};
+
+use crate::error::MyError::{self, InvalidAddressError};
+
+/// A 20-byte EVM address, held lowercase: that is how it is stored and
+/// looked up, whatever case it was sent in.
+///
+/// Mixed-case input must carry a valid EIP-55 checksum; all-lowercase and
+/// all-uppercase input has none to check.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Address(String);
+
+impl Address {
+  pub fn parse(value: &str) -> Result<Self, MyError> {
+    let invalid = |reason: &str| InvalidAddressError(format!("{:?} {}", value, reason));
+    let hex = value
+      .strip_prefix("0x")
+      .ok_or_else(|| invalid("does not start with 0x"))?;
+    if hex.len() != 40 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+      return Err(invalid("is not 20 hex-encoded bytes"));
+    }
+    let address = Address(format!("0x{}", hex.to_ascii_lowercase()));
+    let mixed_case =
+      hex.bytes().any(|b| b.is_ascii_lowercase()) && hex.bytes().any(|b| b.is_ascii_uppercase());
+    if mixed_case && address.checksummed() != value {
+      return Err(invalid("has an invalid EIP-55 checksum"));
+    }
+    Ok(address)
+  }
+
+  pub fn from_bytes(bytes: [u8; 20]) -> Self {
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    Address(format!("0x{}", hex))
+  }
+
+  /// The lowercase form, used for storage and lookups.
+  pub fn as_str(&self) -> &str {
+    &self.0
+  }
+
+  /// The EIP-55 form: a hex letter is uppercase when the matching nibble of
+  /// the Keccak-256 hash of the lowercase hex is 8 or more.
+  pub fn checksummed(&self) -> String {
+    let hex = &self.0[2..];
+    let hash = Keccak256::digest(hex.as_bytes());
+    let letters: String = hex
+      .chars()
+      .enumerate()
+      .map(|(i, c)| {
+        let nibble = (hash[i / 2] >> (if i % 2 == 0 { 4 } else { 0 })) & 0x0f;
+        if nibble >= 8 {
+          c.to_ascii_uppercase()
+        } else {
+          c
+        }
+      })
+      .collect();
+    format!("0x{}", letters)
+  }
+
+  /// `id` lowercased if it is a valid address, as given otherwise, so ids
+  /// stored before addresses were checked can still be looked up.
+  pub fn normalize(id: &str) -> String {
+    Address::parse(id)
+      .map(|address| address.0)
+      .unwrap_or_else(|_| id.to_string())
+  }
+}
+
+impl fmt::Display for Address {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(&self.0)
+  }
+}
+
+impl AsRef<str> for Address {
+  fn as_ref(&self) -> &str {
+    &self.0
+  }
+}
+
+impl Deref for Address {
+  type Target = str;
+
+  fn deref(&self) -> &str {
+    &self.0
+  }
+}
+
+impl PartialEq<Address> for String {
+  fn eq(&self, other: &Address) -> bool {
+    *self == other.0
+  }
+}
+
+impl Serialize for Address {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&self.0)
+  }
+}
+
+impl<'de> Deserialize<'de> for Address {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    Address::parse(&value).map_err(serde::de::Error::custom)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// The examples of the EIP-55 specification.
+  const CHECKSUMMED: [&str; 4] = [
+    "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+    "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+    "0xdbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB",
+    "0xD1220A0cf47c7B9Be7A2E6BA89F429762e7b9aDb",
+  ];
+
+  #[test]
+  fn accepts_valid_checksums() {
+    for value in CHECKSUMMED {
+      let address = Address::parse(value).unwrap();
+      assert_eq!(address.as_str(), value.to_ascii_lowercase());
+      assert_eq!(address.checksummed(), value);
+    }
+  }
+
+  #[test]
+  fn rejects_mixed_case_with_a_bad_checksum() {
+    for value in CHECKSUMMED {
+      // Flipping the case of one letter breaks the checksum.
+      let i = value.rfind(|c: char| c.is_ascii_alphabetic()).unwrap();
+      let mut flipped = value.to_string();
+      let letter = &value[i..i + 1];
+      let swapped = if letter == letter.to_ascii_lowercase() {
+        letter.to_ascii_uppercase()
+      } else {
+        letter.to_ascii_lowercase()
+      };
+      flipped.replace_range(i..i + 1, &swapped);
+      let error = Address::parse(&flipped).unwrap_err().to_string();
+      assert!(error.contains("EIP-55"), "{}: {}", flipped, error);
+    }
+  }
+
+  #[test]
+  fn accepts_single_case_without_checksum() {
+    let value = CHECKSUMMED[0];
+    let lower = format!("0x{}", value[2..].to_ascii_lowercase());
+    let upper = format!("0x{}", value[2..].to_ascii_uppercase());
+    assert_eq!(Address::parse(&lower).unwrap().as_str(), lower);
+    assert_eq!(Address::parse(&upper).unwrap().as_str(), lower);
+  }
+
+  #[test]
+  fn rejects_malformed_addresses() {
+    let value = CHECKSUMMED[0];
+    for malformed in [
+      &value[2..],
+      &value[..41],
+      &format!("{}00", value),
+      "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeg",
+    ] {
+      assert!(Address::parse(malformed).is_err(), "{}", malformed);
+    }
+  }
+
+  #[test]
+  fn normalizes_only_valid_addresses() {
+    assert_eq!(
+      Address::normalize(CHECKSUMMED[1]),
+      CHECKSUMMED[1].to_ascii_lowercase()
+    );
+    assert_eq!(Address::normalize("legacy-id"), "legacy-id");
+  }
+}
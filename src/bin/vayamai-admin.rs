@@ -0,0 +1,168 @@
+use std::path::PathBuf;
+
+use chrono::NaiveDate;
+use clap::{Parser, Subcommand};
+use dotenv::dotenv;
+use vayamai_axum_mongodb::db::DB;
+use vayamai_axum_mongodb::dump::{self, ExportFilter, OnConflict};
+use vayamai_axum_mongodb::error::MyError;
+use vayamai_axum_mongodb::json_schema;
+use vayamai_axum_mongodb::seed::{self, SeedOptions};
+
+/// Maintenance tasks against the database configured in `.env`.
+#[derive(Parser)]
+struct Cli {
+  #[command(subcommand)]
+  command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+  /// Dump every collection to `<out>/<collection>.ndjson`.
+  Export {
+    #[arg(long)]
+    out: PathBuf,
+    /// Only tasks starting on or after this day (YYYY-MM-DD).
+    #[arg(long)]
+    since: Option<NaiveDate>,
+    /// Only tasks starting on or before this day (YYYY-MM-DD).
+    #[arg(long)]
+    until: Option<NaiveDate>,
+    /// Only what this client or freelancer takes part in.
+    #[arg(long)]
+    user: Option<String>,
+  },
+  /// Load a dump written by `export`, then verify references.
+  Import {
+    /// Directory holding the `<collection>.ndjson` files.
+    #[arg(long)]
+    from: PathBuf,
+    #[arg(long, value_enum, default_value_t = OnConflict::Fail)]
+    on_conflict: OnConflict,
+    /// Don't check references once imported.
+    #[arg(long)]
+    skip_verify: bool,
+  },
+  /// Report references to documents that don't exist.
+  Verify,
+  /// Report stored documents their collection's validator would reject.
+  Validate,
+  /// Recompute the clients' `tasks_ids` and the tasks' `proposals_id`.
+  Repair,
+  /// Fill an empty database with reproducible development fixtures.
+  Seed {
+    #[arg(long, default_value_t = 5)]
+    clients: usize,
+    #[arg(long, default_value_t = 10)]
+    freelancers: usize,
+    #[arg(long, default_value_t = 20)]
+    tasks: usize,
+    /// Same seed, same documents.
+    #[arg(long, default_value_t = 42)]
+    seed: u64,
+  },
+}
+
+#[tokio::main]
+async fn main() -> Result<(), MyError> {
+  dotenv().ok();
+  let cli = Cli::parse();
+  let db = DB::init().await?;
+
+  match cli.command {
+    Command::Export {
+      out,
+      since,
+      until,
+      user,
+    } => {
+      let filter = ExportFilter {
+        since,
+        until,
+        user_id: user,
+      };
+      for (collection, count) in dump::export(&db, &out, &filter).await? {
+        println!("{}: {} exported", collection, count);
+      }
+    }
+    Command::Import {
+      from,
+      on_conflict,
+      skip_verify,
+    } => {
+      for report in dump::import(&db, &from, on_conflict).await? {
+        println!(
+          "{}: {} inserted, {} replaced, {} skipped",
+          report.collection, report.inserted, report.replaced, report.skipped
+        );
+      }
+      if !skip_verify {
+        verify(&db).await?;
+      }
+    }
+    Command::Verify => verify(&db).await?,
+    Command::Validate => {
+      let invalid = json_schema::invalid_documents(&db).await?;
+      if invalid.is_empty() {
+        println!("✅ All documents match their collection schema");
+        return Ok(());
+      }
+      for document in &invalid {
+        println!(
+          "❌ {} {}: {}",
+          document.collection, document.id, document.reason
+        );
+      }
+      println!("{} invalid documents", invalid.len());
+      std::process::exit(1);
+    }
+    Command::Repair => {
+      let (clients, tasks) = db.repair_back_references().await?;
+      println!(
+        "✅ Repaired {} clients' tasks_ids and {} tasks' proposals_id",
+        clients, tasks
+      );
+    }
+    Command::Seed {
+      clients,
+      freelancers,
+      tasks,
+      seed,
+    } => {
+      let options = SeedOptions {
+        clients,
+        freelancers,
+        tasks,
+        seed,
+      };
+      let report = seed::seed(&db, &options).await?;
+      println!(
+        "✅ Seeded {} skills, {} clients, {} freelancers, {} tasks, {} proposals, {} milestones, {} deals, {} reviews",
+        report.skills,
+        report.clients,
+        report.freelancers,
+        report.tasks,
+        report.proposals,
+        report.milestones,
+        report.deals,
+        report.reviews
+      );
+      println!("Every user's password is \"{}\"", seed::PASSWORD);
+    }
+  }
+
+  Ok(())
+}
+
+async fn verify(db: &DB) -> Result<(), MyError> {
+  let broken = dump::verify(db).await?;
+  if broken.is_empty() {
+    println!("✅ All references resolve");
+    return Ok(());
+  }
+  for reference in &broken {
+    println!("❌ {}", reference);
+  }
+  println!("{} broken references", broken.len());
+  std::process::exit(1);
+}
@@ -0,0 +1,287 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::db::Result;
+use crate::response::{
+  CacheStatsResponse, FreelancerListResponse, FreelancerResponse, ReputationResponse,
+  SingleTaskDetailResponse, TaskListResponse,
+};
+
+#[derive(Debug)]
+struct Entry<V> {
+  value: V,
+  inserted_at: Instant,
+}
+
+#[derive(Debug)]
+struct Entries<V> {
+  map: HashMap<String, Entry<V>>,
+  /// Bumped by every invalidation, so a load that started before one doesn't
+  /// store what it read.
+  generation: u64,
+}
+
+/// An in-process read-through cache: entries live for `ttl` and, once
+/// `capacity` is reached, the oldest one makes room for the next.
+#[derive(Debug)]
+pub struct Cache<V> {
+  name: &'static str,
+  ttl: Duration,
+  capacity: usize,
+  entries: Mutex<Entries<V>>,
+  hits: AtomicU64,
+  misses: AtomicU64,
+}
+
+impl<V: Clone> Cache<V> {
+  pub fn new(name: &'static str, ttl: Duration, capacity: usize) -> Self {
+    Self {
+      name,
+      ttl,
+      capacity,
+      entries: Mutex::new(Entries {
+        map: HashMap::new(),
+        generation: 0,
+      }),
+      hits: AtomicU64::new(0),
+      misses: AtomicU64::new(0),
+    }
+  }
+
+  fn lock(&self) -> std::sync::MutexGuard<'_, Entries<V>> {
+    // Nothing is left half-updated while the lock is held, so a panic
+    // elsewhere doesn't make the entries unusable.
+    self
+      .entries
+      .lock()
+      .unwrap_or_else(|poisoned| poisoned.into_inner())
+  }
+
+  /// The cached value for `key`, or what `load` returns, cached unless it
+  /// failed or an invalidation happened meanwhile.
+  pub async fn get_or_load<F, Fut>(&self, key: &str, load: F) -> Result<V>
+  where
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<V>>,
+  {
+    let generation = {
+      let entries = self.lock();
+      if let Some(entry) = entries.map.get(key) {
+        if entry.inserted_at.elapsed() < self.ttl {
+          self.hits.fetch_add(1, Ordering::Relaxed);
+          return Ok(entry.value.clone());
+        }
+      }
+      entries.generation
+    };
+    self.misses.fetch_add(1, Ordering::Relaxed);
+
+    let value = load().await?;
+
+    let mut entries = self.lock();
+    if entries.generation == generation && self.capacity > 0 {
+      if entries.map.len() >= self.capacity && !entries.map.contains_key(key) {
+        self.evict(&mut entries);
+      }
+      entries.map.insert(
+        key.to_string(),
+        Entry {
+          value: value.clone(),
+          inserted_at: Instant::now(),
+        },
+      );
+    }
+    Ok(value)
+  }
+
+  /// Drops the expired entries, or the oldest one if none has expired.
+  fn evict(&self, entries: &mut Entries<V>) {
+    let ttl = self.ttl;
+    entries
+      .map
+      .retain(|_, entry| entry.inserted_at.elapsed() < ttl);
+    if entries.map.len() < self.capacity {
+      return;
+    }
+    let oldest = entries
+      .map
+      .iter()
+      .min_by_key(|(_, entry)| entry.inserted_at)
+      .map(|(key, _)| key.clone());
+    if let Some(key) = oldest {
+      entries.map.remove(&key);
+    }
+  }
+
+  pub fn invalidate(&self, key: &str) {
+    let mut entries = self.lock();
+    entries.map.remove(key);
+    entries.generation += 1;
+  }
+
+  pub fn invalidate_all(&self) {
+    let mut entries = self.lock();
+    entries.map.clear();
+    entries.generation += 1;
+  }
+
+  pub fn stats(&self) -> CacheStatsResponse {
+    CacheStatsResponse {
+      name: self.name,
+      hits: self.hits.load(Ordering::Relaxed),
+      misses: self.misses.load(Ordering::Relaxed),
+      entries: self.lock().map.len(),
+      capacity: self.capacity,
+      ttl_secs: self.ttl.as_secs(),
+    }
+  }
+}
+
+/// The caches in front of `DB`'s hot reads. Each server instance has its
+/// own, so another instance's writes show up once entries expire.
+#[derive(Debug)]
+pub struct Caches {
+  /// Task lists, under `all` or `skill:<skill>`, then the status and sort
+  /// asked for and, unless an admin asks, the viewer.
+  pub tasks: Cache<TaskListResponse>,
+  /// Task details, under the task id and the relations expanded and, unless
+  /// an admin asks, the viewer.
+  pub task_details: Cache<SingleTaskDetailResponse>,
+  /// The freelancer list, under `all`.
+  pub freelancers: Cache<FreelancerListResponse>,
+  /// Freelancer profiles, by id.
+  pub freelancer_profiles: Cache<FreelancerResponse>,
+  /// Review aggregates, by freelancer id.
+  pub reputations: Cache<ReputationResponse>,
+}
+
+impl Caches {
+  /// Sized by `CACHE_TTL_SECS` (30 by default) and `CACHE_CAPACITY`, the
+  /// entries per cache (1000 by default, 0 disables caching).
+  pub fn from_env() -> Self {
+    let ttl = std::env::var("CACHE_TTL_SECS")
+      .ok()
+      .and_then(|secs| secs.parse().ok())
+      .map(Duration::from_secs)
+      .unwrap_or(Duration::from_secs(30));
+    let capacity = std::env::var("CACHE_CAPACITY")
+      .ok()
+      .and_then(|capacity| capacity.parse().ok())
+      .unwrap_or(1000);
+
+    Self {
+      tasks: Cache::new("tasks", ttl, capacity),
+      task_details: Cache::new("task_details", ttl, capacity),
+      freelancers: Cache::new("freelancers", ttl, capacity),
+      freelancer_profiles: Cache::new("freelancer_profiles", ttl, capacity),
+      reputations: Cache::new("reputations", ttl, capacity),
+    }
+  }
+
+  /// Drops the task lists and details, after a task changed.
+  pub fn invalidate_tasks(&self) {
+    self.tasks.invalidate_all();
+    self.task_details.invalidate_all();
+  }
+
+  pub fn invalidate_all(&self) {
+    self.invalidate_tasks();
+    self.freelancers.invalidate_all();
+    self.freelancer_profiles.invalidate_all();
+    self.reputations.invalidate_all();
+  }
+
+  pub fn stats(&self) -> Vec<CacheStatsResponse> {
+    vec![
+      self.tasks.stats(),
+      self.task_details.stats(),
+      self.freelancers.stats(),
+      self.freelancer_profiles.stats(),
+      self.reputations.stats(),
+    ]
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn cache(ttl: Duration, capacity: usize) -> Cache<u32> {
+    Cache::new("test", ttl, capacity)
+  }
+
+  async fn load(cache: &Cache<u32>, key: &str, value: u32) -> u32 {
+    cache
+      .get_or_load(key, || async move { Ok(value) })
+      .await
+      .unwrap()
+  }
+
+  #[tokio::test]
+  async fn serves_what_it_loaded_until_invalidated() {
+    let cache = cache(Duration::from_secs(60), 10);
+    assert_eq!(load(&cache, "a", 1).await, 1);
+    assert_eq!(load(&cache, "a", 2).await, 1);
+    cache.invalidate("a");
+    assert_eq!(load(&cache, "a", 3).await, 3);
+    let stats = cache.stats();
+    assert_eq!((stats.hits, stats.misses, stats.entries), (1, 2, 1));
+  }
+
+  #[tokio::test]
+  async fn drops_a_load_overtaken_by_an_invalidation() {
+    let cache = cache(Duration::from_secs(60), 10);
+    let value = cache
+      .get_or_load("a", || async {
+        cache.invalidate_all();
+        Ok(1)
+      })
+      .await
+      .unwrap();
+    assert_eq!(value, 1);
+    assert_eq!(cache.stats().entries, 0);
+    assert_eq!(load(&cache, "a", 2).await, 2);
+    assert_eq!(load(&cache, "a", 3).await, 2);
+  }
+
+  #[tokio::test]
+  async fn reloads_expired_entries() {
+    let cache = cache(Duration::ZERO, 10);
+    assert_eq!(load(&cache, "a", 1).await, 1);
+    assert_eq!(load(&cache, "a", 2).await, 2);
+    assert_eq!(cache.stats().hits, 0);
+  }
+
+  #[tokio::test]
+  async fn evicts_the_oldest_entry_when_full() {
+    let cache = cache(Duration::from_secs(60), 2);
+    load(&cache, "a", 1).await;
+    load(&cache, "b", 2).await;
+    load(&cache, "c", 3).await;
+    assert_eq!(cache.stats().entries, 2);
+    assert_eq!(load(&cache, "b", 0).await, 2);
+    assert_eq!(load(&cache, "c", 0).await, 3);
+    assert_eq!(load(&cache, "a", 4).await, 4);
+  }
+
+  #[tokio::test]
+  async fn evicts_expired_entries_first() {
+    let cache = cache(Duration::from_millis(50), 2);
+    load(&cache, "a", 1).await;
+    load(&cache, "b", 2).await;
+    std::thread::sleep(Duration::from_millis(60));
+    load(&cache, "c", 3).await;
+    assert_eq!(cache.stats().entries, 1);
+  }
+
+  #[tokio::test]
+  async fn stores_nothing_without_capacity() {
+    let cache = cache(Duration::from_secs(60), 0);
+    assert_eq!(load(&cache, "a", 1).await, 1);
+    assert_eq!(load(&cache, "a", 2).await, 2);
+    assert_eq!(cache.stats().entries, 0);
+  }
+}
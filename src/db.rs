@@ -1,42 +1,68 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::address::Address;
+use crate::cache::Caches;
 use crate::error::MyError;
+use crate::lifecycle::{
+  check_move, transition, DealStatus, InvitationStatus, Lifecycle, MilestoneStatus, StatusChange,
+  TaskStatus,
+};
 use crate::model::{
-  ClientModel, DealModel, FreelancerModel, MilestoneModel, ProposalModel, ReviewModel, TaskModel,
+  Attachment, ClientModel, DealModel, FreelancerModel, Invitation, JobModel, MilestoneModel,
+  ProposalModel, ReviewModel, SkillModel, TaskEdit, TaskFields, TaskModel, TaskVisibility,
 };
+use crate::money::{Money, Token};
+use crate::recommend::{rank, History};
 use crate::response::{
-  ClientData, ClientListResponse, DealData, DealListResponse, DealResponse, FreelancerData,
-  FreelancerListResponse, MilestoneData, MilestoneListResponse, MilestoneResponse,
-  PartialDealResponse, ProposalData, ProposalDealData, ProposalDetailedData, ProposalListResponse,
-  ProposalResponse, ReviewData, SingleClientResponse, SingleDealResponse, SingleFreelancerResponse,
+  ClientListResponse, DealData, DealDetailData, DealDetailResponse, DealListResponse, DealResponse,
+  FreelancerListResponse, FreelancerProfileData, FreelancerProfileResponse, MilestoneData,
+  MilestoneListResponse, PartialDealResponse, ProposalData, ProposalDealData, ProposalDetailedData,
+  ProposalListResponse, RecommendedTaskListResponse, RecommendedTaskResponse, ReputationResponse,
+  ReviewData, SingleDealDetailResponse, SingleDealResponse, SingleFreelancerProfileResponse,
   SingleMilestoneResponse, SingleProposalDealResponse, SingleProposalDetailedResponse,
-  SingleProposalResponse, SingleReviewResponse, SingleTaskResponse, SingleUserResponse, TaskData,
-  TaskListResponse, TaskResponse, UserData, UserResponse, UsersListResponse,
+  SingleProposalResponse, SingleReviewResponse, SingleSkillResponse, SingleTaskDetailResponse,
+  SingleTaskResponse, SingleUserResponse, SkillData, SkillListResponse, SkillMergeData,
+  SkillMergeResponse, TaskCancelData, TaskCancelResponse, TaskData, TaskDetailData,
+  TaskDetailResponse, TaskListResponse, TaskResponse, UserData,
 };
+use crate::schema::{sort_order, MAX_INVITATIONS};
 use crate::schema::{
-  CreateClientSchema, CreateFreelancerSchema, CreateMilestoneSchema, CreateProposalSchema,
-  CreateReviewSchema, CreateTaskSchema, LoginUserSchema,
+  CancelTaskSchema, CreateClientSchema, CreateFreelancerSchema, CreateMilestoneSchema,
+  CreateProposalSchema, CreateReviewSchema, CreateSkillSchema, CreateTaskSchema,
+  InviteFreelancersSchema, LoginUserSchema, MergeSkillsSchema, UpdateTaskSchema,
 };
+use crate::skill::{key_pattern, prefix_pattern, skill_key, Taxonomy};
 use crate::utils::{
-  build_client_document, build_deal_document, build_freelancer_document, build_milestone_document,
-  build_milestones_document, build_proposal_document, build_review_document, build_task_document,
-  doc_to_client_response, doc_to_deal_response, doc_to_detailed_proposal_response,
-  doc_to_freelancer_response, doc_to_milestone_response, doc_to_proposal_and_deal_response,
-  doc_to_proposal_response, doc_to_review_response, doc_to_task_response, doc_to_user_response,
-  docs_to_deal_response,
+  active_filter, build_client_document, build_deal_document, build_freelancer_document,
+  build_milestones_document, build_proposal_document, build_review_document, build_skill_document,
+  build_task_document, doc_to_client_response, doc_to_deal_response,
+  doc_to_detailed_proposal_response, doc_to_freelancer_response, doc_to_milestone_response,
+  doc_to_proposal_and_deal_response, doc_to_proposal_response, doc_to_review_response,
+  doc_to_skill_response, doc_to_task_response, doc_to_user_response, docs_to_deal_response,
+  embedded, from_document, lookup, to_document, with_version,
 };
-use crate::web;
-use crate::{error::MyError::*, model::UserModel, schema::CreateUserSchema};
+use crate::{error::MyError::*, model::UserModel};
 
+use axum::body::Bytes;
 use futures::StreamExt;
-use jsonwebtoken::{encode, EncodingKey, Header};
-use mongodb::bson::{doc, Bson, Document};
-use mongodb::options::{FindOneAndUpdateOptions, IndexOptions, ReturnDocument};
-use mongodb::{options::ClientOptions, Client, Collection, IndexModel};
+use mongodb::bson::{doc, to_bson, Bson, DateTime, Document, Regex};
+use mongodb::options::{FindOneAndUpdateOptions, FindOptions, IndexOptions, ReturnDocument};
+use mongodb::{options::ClientOptions, Client, Collection, Database, IndexModel};
 
+use crate::storage::Storage;
+use crate::web::ctx::Ctx;
 use crate::web::token::generate_auth_cookie;
-use tower_cookies::{Cookie, Cookies};
+use crate::web::upload::{Upload, MAX_ATTACHMENTS};
+use rand::Rng;
+use serde::de::DeserializeOwned;
+use tower_cookies::Cookies;
 
 #[derive(Clone, Debug)]
 pub struct DB {
+  /// The connection behind `database`, for sessions.
+  pub mongo_client: Client,
+  pub database: Database,
   pub client_collection_model: Collection<ClientModel>,
   pub client_collection: Collection<Document>,
   pub tasks_collection_model: Collection<TaskModel>,
@@ -51,6 +77,13 @@ pub struct DB {
   pub deals_collection: Collection<Document>,
   pub milestones_collection_model: Collection<MilestoneModel>,
   pub milestones_collection: Collection<Document>,
+  pub skills_collection_model: Collection<SkillModel>,
+  pub skills_collection: Collection<Document>,
+  pub jobs_collection_model: Collection<JobModel>,
+  pub jobs_collection: Collection<Document>,
+  pub resume_tokens_collection: Collection<Document>,
+  pub migrations_collection: Collection<Document>,
+  pub caches: Arc<Caches>,
 }
 
 pub type Result<T> = std::result::Result<T, MyError>;
@@ -74,6 +107,14 @@ impl DB {
       std::env::var("MONGODB_DEALS_COLLECTION").expect("MONGODB_DEALS_COLLECTION must be set.");
     let milestones_collection_name = std::env::var("MONGODB_MILESTONES_COLLECTION")
       .expect("MONGODB_MILESTONES_COLLECTION must be set.");
+    let skills_collection_name =
+      std::env::var("MONGODB_SKILLS_COLLECTION").expect("MONGODB_SKILLS_COLLECTION must be set.");
+    let jobs_collection_name =
+      std::env::var("MONGODB_JOBS_COLLECTION").expect("MONGODB_JOBS_COLLECTION must be set.");
+    let resume_tokens_collection_name = std::env::var("MONGODB_RESUME_TOKENS_COLLECTION")
+      .expect("MONGODB_RESUME_TOKENS_COLLECTION must be set.");
+    let migrations_collection_name = std::env::var("MONGODB_MIGRATIONS_COLLECTION")
+      .expect("MONGODB_MIGRATIONS_COLLECTION must be set.");
 
     let mut client_options = ClientOptions::parse(mongodb_uri).await?;
     client_options.app_name = Some(database_name.clone());
@@ -97,10 +138,18 @@ impl DB {
     let milestones_collection_model = database.collection(milestones_collection_name.as_str());
     let milestones_collection =
       database.collection::<Document>(milestones_collection_name.as_str());
-
-    println!("✅ Database connected successfully");
-
-    Ok(Self {
+    let skills_collection_model = database.collection(skills_collection_name.as_str());
+    let skills_collection = database.collection::<Document>(skills_collection_name.as_str());
+    let jobs_collection_model = database.collection(jobs_collection_name.as_str());
+    let jobs_collection = database.collection::<Document>(jobs_collection_name.as_str());
+    let resume_tokens_collection =
+      database.collection::<Document>(resume_tokens_collection_name.as_str());
+    let migrations_collection =
+      database.collection::<Document>(migrations_collection_name.as_str());
+
+    let db = Self {
+      mongo_client: client_side,
+      database,
       client_collection_model,
       client_collection,
       tasks_collection_model,
@@ -115,7 +164,77 @@ impl DB {
       deals_collection,
       milestones_collection_model,
       milestones_collection,
-    })
+      skills_collection_model,
+      skills_collection,
+      jobs_collection_model,
+      jobs_collection,
+      resume_tokens_collection,
+      migrations_collection,
+      caches: Arc::new(Caches::from_env()),
+    };
+
+    // Not fatal: the server starts anyway and `/readyz` reports the outage.
+    match db.ping().await {
+      Ok(_) => println!("✅ Database connected successfully"),
+      Err(e) => println!("❌ Database unreachable: {}", e),
+    }
+
+    Ok(db)
+  }
+
+  /// Round trip to the server, proving it is reachable and answering.
+  pub async fn ping(&self) -> Result<()> {
+    self
+      .database
+      .run_command(doc! {"ping": 1}, None)
+      .await
+      .map_err(MongoQueryError)?;
+    Ok(())
+  }
+
+  /// Indexes `created_at` and `updated_at`, newest first, on the
+  /// collections whose lists can be sorted by them, and skills by the keys
+  /// they are looked up and completed by.
+  pub async fn create_indexes(&self) -> Result<()> {
+    let collections = [
+      &self.tasks_collection,
+      &self.proposals_collection,
+      &self.milestones_collection,
+      &self.deals_collection,
+      &self.review_collection,
+    ];
+    for collection in collections {
+      let indexes = ["created_at", "updated_at"]
+        .into_iter()
+        .map(|field| {
+          IndexModel::builder()
+            .keys(doc! {field: -1, "_id": -1})
+            .build()
+        })
+        .collect::<Vec<_>>();
+      collection
+        .create_indexes(indexes, None)
+        .await
+        .map_err(MongoQueryError)?;
+    }
+    let index = IndexModel::builder()
+      .keys(doc! {"keys": 1})
+      .options(IndexOptions::builder().unique(true).build())
+      .build();
+    self
+      .skills_collection
+      .create_index(index, None)
+      .await
+      .map_err(MongoQueryError)?;
+    let index = IndexModel::builder()
+      .keys(doc! {"status": 1, "run_at": 1})
+      .build();
+    self
+      .jobs_collection
+      .create_index(index, None)
+      .await
+      .map_err(MongoQueryError)?;
+    Ok(())
   }
 
   pub async fn api_login(
@@ -129,29 +248,29 @@ impl DB {
       "client" => match self
         .client_collection_model
         .find_one(
-          doc! {"user_name": body.credentials.user_name.to_owned()},
+          doc! {"user_name": body.credentials.user_name.to_owned(), "deleted_at": null},
           None,
         )
         .await
       {
-        Ok(model) => Some(model.unwrap().user),
-        Err(e) => return Err(MongoQueryError(e)),
+        Ok(model) => model.map(|client| client.user),
+        Err(e) => return Err(MyError::read_error(e)),
       },
       "freelancer" => match self
         .freelancer_collection_model
         .find_one(
-          doc! {"user_name": body.credentials.user_name.to_owned()},
+          doc! {"user_name": body.credentials.user_name.to_owned(), "deleted_at": null},
           None,
         )
         .await
       {
-        Ok(user) => Some(user.unwrap().user),
-        Err(e) => return Err(MongoQueryError(e)),
+        Ok(user) => user.map(|freelancer| freelancer.user),
+        Err(e) => return Err(MyError::read_error(e)),
       },
       _ => return Err(InvalidRoleError),
     };
 
-    return match user_model {
+    match user_model {
       Some(user) => {
         let user = doc_to_user_response(&user, &role.to_owned())?; // TODO role
         if user.password == body.credentials.password {
@@ -164,19 +283,19 @@ impl DB {
         Err(InvalidPasswordError)
       }
       None => Err(NotFoundError(body.credentials.user_name.to_owned())),
-    };
+    }
   }
 
-  pub async fn fetch_clients(&self) -> Result<ClientListResponse> {
+  pub async fn fetch_clients(&self, include_deleted: bool) -> Result<ClientListResponse> {
     let mut cursor = self
       .client_collection_model
-      .find(None, None)
+      .find(active_filter(include_deleted), None)
       .await
       .map_err(MongoQueryError)?;
 
     let mut json_result = Vec::new();
     while let Some(doc) = cursor.next().await {
-      json_result.push(doc_to_client_response(&doc.unwrap())?);
+      json_result.push(doc_to_client_response(&doc.map_err(MyError::read_error)?)?);
     }
 
     Ok(ClientListResponse {
@@ -190,6 +309,17 @@ impl DB {
     let user_body = &body.user;
     let description = body.user.description.to_owned().unwrap_or_default();
     let task_ids = body.task_ids.to_owned().unwrap_or_default();
+    for task_id in &task_ids {
+      let task = self
+        .referenced(&self.tasks_collection_model, "task_ids", task_id)
+        .await?;
+      if task.client_id != user_body.id {
+        return Err(BrokenReferenceError(format!(
+          "task {} belongs to client {}",
+          task_id, task.client_id
+        )));
+      }
+    }
     //let role = "client".to_string();
     let document = build_client_document(user_body, description, task_ids)?;
 
@@ -217,10 +347,7 @@ impl DB {
       }
     };
 
-    let new_id = insert_result
-      .inserted_id
-      .as_str()
-      .expect("issue with new _id");
+    let new_id = inserted_id(&insert_result.inserted_id)?;
 
     let client_model = match self
       .client_collection_model
@@ -229,7 +356,7 @@ impl DB {
     {
       Ok(Some(doc)) => doc,
       Ok(None) => return Err(NotFoundError(new_id.to_string())),
-      Err(e) => return Err(MongoQueryError(e)),
+      Err(e) => return Err(MyError::read_error(e)),
     };
     //let role = "client".to_string();
     let client = doc_to_client_response(&client_model)?;
@@ -240,16 +367,66 @@ impl DB {
     })
   }
 
-  pub async fn fetch_tasks(&self) -> Result<TaskListResponse> {
+  /// Every task listed to the caller, or only those requiring `skill` under
+  /// any of its names, or in `status`, in `sort` order.
+  pub async fn fetch_tasks(
+    &self,
+    ctx: &Ctx,
+    include_deleted: bool,
+    skill: Option<&str>,
+    status: Option<TaskStatus>,
+    sort: Option<&str>,
+  ) -> Result<TaskListResponse> {
+    let skill = match skill {
+      Some(skill) => self.canonical_skills(&[skill.to_string()]).await?.pop(),
+      None => None,
+    };
+    let mut filter = active_filter(include_deleted);
+    let mut key = match skill {
+      Some(skill) => {
+        filter.insert("skills", &skill);
+        format!("skill:{}", skill)
+      }
+      None => "all".to_string(),
+    };
+    if let Some(status) = status {
+      filter.insert("status", to_bson(&status)?);
+      key = format!("{} status:{:?}", key, status);
+    }
+    if let Some(sort) = sort {
+      key = format!("{} sort:{}", key, sort);
+    }
+    if !ctx.is_admin() {
+      filter.extend(visible_tasks(ctx.user_id(), true)?);
+      key = format!("{} viewer:{}", key, ctx.user_id());
+    }
+    let sort = sort_order(sort)?;
+    if include_deleted {
+      return self.load_tasks(filter, sort, ctx).await;
+    }
+    self
+      .caches
+      .tasks
+      .get_or_load(&key, || self.load_tasks(filter, sort, ctx))
+      .await
+  }
+
+  async fn load_tasks(
+    &self,
+    filter: Document,
+    sort: Option<Document>,
+    ctx: &Ctx,
+  ) -> Result<TaskListResponse> {
+    let options = FindOptions::builder().sort(sort).build();
     let mut cursor = self
       .tasks_collection_model
-      .find(None, None)
+      .find(filter, options)
       .await
       .map_err(MongoQueryError)?;
 
     let mut json_result: Vec<TaskResponse> = Vec::new();
     while let Some(doc) = cursor.next().await {
-      json_result.push(doc_to_task_response(&doc.unwrap())?);
+      json_result.push(task_response_for(&doc.map_err(MyError::read_error)?, ctx)?);
     }
 
     Ok(TaskListResponse {
@@ -259,62 +436,353 @@ impl DB {
     })
   }
 
-  pub async fn get_task(&self, skill: &str) -> Result<TaskListResponse> {
-    let mut task = self
-      .tasks_collection_model
-      .find(doc! {"skills": skill}, None)
+  /// A live proposal with its milestones, if its task is one the caller may
+  /// see.
+  pub async fn get_proposal(
+    &self,
+    proposal_id: &str,
+    ctx: &Ctx,
+  ) -> Result<SingleProposalDetailedResponse> {
+    let mut pipeline = vec![doc! {"$match": {"_id": proposal_id, "deleted_at": null}}];
+    pipeline.extend(self.of_visible_task("task_id", ctx)?);
+    pipeline.push(lookup(
+      self.milestones_collection.name(),
+      "_id",
+      "proposal_id",
+      "milestones",
+    ));
+    let document = self
+      .aggregate_one(&self.proposals_collection, pipeline)
+      .await?
+      .ok_or_else(|| NotFoundError(proposal_id.to_string()))?;
+
+    let milestones = embedded::<MilestoneModel>(&document, "milestones")?
+      .iter()
+      .map(doc_to_milestone_response)
+      .collect::<Result<Vec<_>>>()?;
+    let detailed_proposal =
+      doc_to_detailed_proposal_response(&from_document(&document)?, milestones)?;
+    Ok(SingleProposalDetailedResponse {
+      status: "Success",
+      data: ProposalDetailedData { detailed_proposal },
+    })
+  }
+
+  /// A live task with its proposals, the accepted proposals' milestones, its
+  /// deal and the deal's reviews, as picked by `relations`, in one query.
+  /// Invite-only tasks are only found by those who may see them.
+  pub async fn get_task_detail(
+    &self,
+    task_id: &str,
+    relations: &[&str],
+    ctx: &Ctx,
+  ) -> Result<SingleTaskDetailResponse> {
+    let mut key = format!("{} with:{}", task_id, relations.join(","));
+    if !ctx.is_admin() {
+      key = format!("{} viewer:{}", key, ctx.user_id());
+    }
+    self
+      .caches
+      .task_details
+      .get_or_load(&key, || self.load_task_detail(task_id, relations, ctx))
       .await
-      .map_err(MongoQueryError)?;
+  }
 
-    let mut json_result: Vec<TaskResponse> = Vec::new();
-    while let Some(doc) = task.next().await {
-      json_result.push(doc_to_task_response(&doc.unwrap())?);
+  async fn load_task_detail(
+    &self,
+    task_id: &str,
+    relations: &[&str],
+    ctx: &Ctx,
+  ) -> Result<SingleTaskDetailResponse> {
+    let expands = |relation: &str| relations.contains(&relation);
+    let mut filter = doc! {"_id": task_id, "deleted_at": null};
+    if !ctx.is_admin() {
+      filter.extend(visible_tasks(ctx.user_id(), false)?);
+    }
+    let mut pipeline = vec![doc! {"$match": filter}];
+    if expands("proposals") || expands("milestones") {
+      pipeline.push(lookup(
+        self.proposals_collection.name(),
+        "_id",
+        "task_id",
+        "proposals",
+      ));
+    }
+    if expands("milestones") {
+      pipeline.push(doc! {"$addFields": {"accepted_ids": {"$map": {
+        "input": {"$filter": {"input": "$proposals", "cond": "$$this.accepted"}},
+        "in": "$$this._id",
+      }}}});
+      pipeline.push(lookup(
+        self.milestones_collection.name(),
+        "accepted_ids",
+        "proposal_id",
+        "milestones",
+      ));
+    }
+    if expands("deal") || expands("reviews") {
+      pipeline.push(lookup(
+        self.deals_collection.name(),
+        "_id",
+        "task_id",
+        "deals",
+      ));
+    }
+    if expands("reviews") {
+      pipeline.push(lookup(
+        self.review_collection.name(),
+        "deals._id",
+        "deal_id",
+        "reviews",
+      ));
     }
 
-    Ok(TaskListResponse {
+    let document = self
+      .aggregate_one(&self.tasks_collection, pipeline)
+      .await?
+      .ok_or_else(|| NotFoundError(task_id.to_string()))?;
+
+    let task = TaskDetailResponse {
+      task: task_response_for(&from_document(&document)?, ctx)?,
+      proposals: if expands("proposals") {
+        Some(
+          embedded::<ProposalModel>(&document, "proposals")?
+            .iter()
+            .map(doc_to_proposal_response)
+            .collect::<Result<_>>()?,
+        )
+      } else {
+        None
+      },
+      milestones: if expands("milestones") {
+        Some(
+          embedded::<MilestoneModel>(&document, "milestones")?
+            .iter()
+            .map(doc_to_milestone_response)
+            .collect::<Result<_>>()?,
+        )
+      } else {
+        None
+      },
+      deal: if expands("deal") {
+        Some(
+          embedded::<DealModel>(&document, "deals")?
+            .first()
+            .map(doc_to_deal_response)
+            .transpose()?,
+        )
+      } else {
+        None
+      },
+      reviews: if expands("reviews") {
+        Some(
+          embedded::<ReviewModel>(&document, "reviews")?
+            .iter()
+            .map(doc_to_review_response)
+            .collect::<Result<_>>()?,
+        )
+      } else {
+        None
+      },
+    };
+    Ok(SingleTaskDetailResponse {
       status: "Success",
-      results: json_result.len(),
-      tasks: json_result,
+      data: TaskDetailData { task },
     })
   }
 
-  pub async fn get_proposal(&self, proposal_id: &str) -> Result<SingleProposalDetailedResponse> {
-    let proposal = self
-      .proposals_collection_model
-      .find_one(doc! {"_id": proposal_id}, None)
+  /// A live deal with its task, proposal, the proposal's milestones and the
+  /// deal's reviews, as picked by `relations`, in one query. Deals of tasks
+  /// the caller may not see aren't found.
+  pub async fn get_deal_detail(
+    &self,
+    deal_id: &str,
+    relations: &[&str],
+    ctx: &Ctx,
+  ) -> Result<SingleDealDetailResponse> {
+    let expands = |relation: &str| relations.contains(&relation);
+    let mut pipeline = vec![doc! {"$match": {"_id": deal_id, "deleted_at": null}}];
+    pipeline.extend(self.of_visible_task("task_id", ctx)?);
+    if expands("task") {
+      pipeline.push(lookup(
+        self.tasks_collection.name(),
+        "task_id",
+        "_id",
+        "task",
+      ));
+    }
+    if expands("proposal") {
+      pipeline.push(lookup(
+        self.proposals_collection.name(),
+        "proposal_id",
+        "_id",
+        "proposal",
+      ));
+    }
+    if expands("milestones") {
+      pipeline.push(lookup(
+        self.milestones_collection.name(),
+        "proposal_id",
+        "proposal_id",
+        "milestones",
+      ));
+    }
+    if expands("reviews") {
+      pipeline.push(lookup(
+        self.review_collection.name(),
+        "_id",
+        "deal_id",
+        "reviews",
+      ));
+    }
+
+    let document = self
+      .aggregate_one(&self.deals_collection, pipeline)
+      .await?
+      .ok_or_else(|| NotFoundError(deal_id.to_string()))?;
+
+    let deal = DealDetailResponse {
+      deal: doc_to_deal_response(&from_document(&document)?)?,
+      task: if expands("task") {
+        Some(
+          embedded::<TaskModel>(&document, "task")?
+            .first()
+            .map(|task| task_response_for(task, ctx))
+            .transpose()?,
+        )
+      } else {
+        None
+      },
+      proposal: if expands("proposal") {
+        Some(
+          embedded::<ProposalModel>(&document, "proposal")?
+            .first()
+            .map(doc_to_proposal_response)
+            .transpose()?,
+        )
+      } else {
+        None
+      },
+      milestones: if expands("milestones") {
+        Some(
+          embedded::<MilestoneModel>(&document, "milestones")?
+            .iter()
+            .map(doc_to_milestone_response)
+            .collect::<Result<_>>()?,
+        )
+      } else {
+        None
+      },
+      reviews: if expands("reviews") {
+        Some(
+          embedded::<ReviewModel>(&document, "reviews")?
+            .iter()
+            .map(doc_to_review_response)
+            .collect::<Result<_>>()?,
+        )
+      } else {
+        None
+      },
+    };
+    Ok(SingleDealDetailResponse {
+      status: "Success",
+      data: DealDetailData { deal },
+    })
+  }
+
+  /// The first document an aggregation yields, if any.
+  async fn aggregate_one(
+    &self,
+    collection: &Collection<Document>,
+    pipeline: Vec<Document>,
+  ) -> Result<Option<Document>> {
+    let mut cursor = collection
+      .aggregate(pipeline, None)
       .await
       .map_err(MongoQueryError)?;
+    match cursor.next().await {
+      Some(document) => Ok(Some(document.map_err(MongoQueryError)?)),
+      None => Ok(None),
+    }
+  }
 
-    let mut cursor = self
-      .milestones_collection_model
-      .find(doc! {"proposal_id": proposal_id}, None)
+  async fn aggregate_all<T: DeserializeOwned>(
+    &self,
+    collection: &Collection<Document>,
+    pipeline: Vec<Document>,
+  ) -> Result<Vec<T>> {
+    let mut cursor = collection
+      .aggregate(pipeline, None)
       .await
       .map_err(MongoQueryError)?;
-
-    let mut milestones = Vec::new();
-    while let Some(doc) = cursor.next().await {
-      milestones.push(doc_to_milestone_response(&doc.unwrap())?);
+    let mut models = Vec::new();
+    while let Some(document) = cursor.next().await {
+      models.push(from_document(&document.map_err(MongoQueryError)?)?);
     }
+    Ok(models)
+  }
 
-    match proposal {
-      Some(doc) => {
-        let detailed_proposal = doc_to_detailed_proposal_response(&doc, milestones)?;
-        Ok(SingleProposalDetailedResponse {
-          status: "Success",
-          data: ProposalDetailedData { detailed_proposal },
-        })
-      }
-      None => Err(NotFoundError(proposal_id.to_string())),
+  /// Stages keeping the documents whose `task_field` names a live task the
+  /// caller may see, any live task for admins.
+  fn of_visible_task(&self, task_field: &str, ctx: &Ctx) -> Result<Vec<Document>> {
+    let mut live = doc! {"deleted_at": null};
+    if !ctx.is_admin() {
+      live.extend(visible_tasks(ctx.user_id(), false)?);
     }
+    let visible = vec![doc! {"$match": live}];
+    Ok(linked(
+      self.tasks_collection.name(),
+      task_field,
+      "task",
+      visible,
+    ))
+  }
+
+  /// Stages keeping the milestones of live proposals on tasks the caller may
+  /// see, as `of_visible_task` tells.
+  fn of_visible_proposal(&self, ctx: &Ctx) -> Result<Vec<Document>> {
+    let mut visible = vec![doc! {"$match": {"deleted_at": null}}];
+    visible.extend(self.of_visible_task("task_id", ctx)?);
+    Ok(linked(
+      self.proposals_collection.name(),
+      "proposal_id",
+      "proposal",
+      visible,
+    ))
   }
-  pub async fn create_task(&self, body: &CreateTaskSchema) -> Result<SingleTaskResponse> {
+
+  pub async fn create_task(
+    &self,
+    body: &CreateTaskSchema,
+    ctx: &Ctx,
+  ) -> Result<SingleTaskResponse> {
+    if body.deadline < body.start_time {
+      return Err(InvalidScheduleError(format!(
+        "deadline {} is before start_time {}",
+        body.deadline, body.start_time
+      )));
+    }
+    if !ctx.is_admin() && ctx.user_id() != body.client_id.as_str() {
+      return Err(ForbiddenError(format!(
+        "{} can't post a task as {}",
+        ctx.user_id(),
+        body.client_id
+      )));
+    }
+    self
+      .referenced(&self.client_collection_model, "client_id", &body.client_id)
+      .await?;
     let _id = self
       .tasks_collection
       .count_documents(None, None)
       .await
       .map_err(MongoQueryError)?
       + 1;
-    let document: Document = build_task_document(body, _id.to_string())?;
+    let body = &CreateTaskSchema {
+      skills: self.canonical_skills(&body.skills).await?,
+      ..body.clone()
+    };
+    let document: Document = build_task_document(body, _id.to_string(), ctx.user_id())?;
 
     let options = IndexOptions::builder().unique(true).build();
     let index = IndexModel::builder()
@@ -340,10 +808,16 @@ impl DB {
       }
     };
 
-    let new_id = insert_result
-      .inserted_id
-      .as_str()
-      .expect("issue with new _id");
+    let new_id = inserted_id(&insert_result.inserted_id)?;
+    self
+      .add_back_reference(
+        &self.client_collection,
+        &body.client_id,
+        "tasks_ids",
+        new_id,
+      )
+      .await?;
+    self.caches.invalidate_tasks();
 
     let task_model = match self
       .tasks_collection_model
@@ -352,10 +826,10 @@ impl DB {
     {
       Ok(Some(doc)) => doc,
       Ok(None) => return Err(NotFoundError(new_id.to_string())),
-      Err(e) => return Err(MongoQueryError(e)),
+      Err(e) => return Err(MyError::read_error(e)),
     };
 
-    let task = doc_to_task_response(&task_model)?;
+    let task = task_response_for(&task_model, ctx)?;
 
     Ok(SingleTaskResponse {
       status: "Success",
@@ -363,241 +837,809 @@ impl DB {
     })
   }
 
-  pub async fn fetch_freelancers(&self) -> Result<FreelancerListResponse> {
-    let mut cursor = self
-      .freelancer_collection_model
-      .find(None, None)
-      .await
-      .map_err(MongoQueryError)?;
+  /// Applies the owner's changes to a live task, recording the fields they
+  /// changed in its `edits`. Once a proposal is accepted only the fields in
+  /// `EDITABLE_WHEN_ACCEPTED` may change.
+  pub async fn update_task(
+    &self,
+    id: &str,
+    body: &UpdateTaskSchema,
+    ctx: &Ctx,
+    expected_version: Option<i64>,
+  ) -> Result<SingleTaskResponse> {
+    let task = self.owned_task(id, ctx, expected_version).await?;
+    if matches!(task.status, TaskStatus::Completed | TaskStatus::Cancelled) {
+      return Err(ConflictError(format!("task {} is {:?}", id, task.status)));
+    }
+    let skills = match &body.skills {
+      Some(skills) => Some(self.canonical_skills(skills).await?),
+      None => None,
+    };
 
-    let mut json_result = Vec::new();
-    while let Some(doc) = cursor.next().await {
-      json_result.push(doc_to_freelancer_response(&doc.unwrap())?);
+    let mut before = TaskFields::default();
+    let mut after = TaskFields::default();
+    let mut set = doc! {};
+    if let Some(title) = body.title.as_ref().filter(|title| **title != task.title) {
+      before.title = Some(task.title.to_owned());
+      after.title = Some(title.to_owned());
+      set.insert("title", title);
+    }
+    if let Some(description) = body
+      .description
+      .as_ref()
+      .filter(|description| **description != task.description)
+    {
+      before.description = Some(task.description.to_owned());
+      after.description = Some(description.to_owned());
+      set.insert("description", description);
+    }
+    let start_time = body.start_time.map(DateTime::from_chrono);
+    if let Some(start_time) = start_time.filter(|start_time| *start_time != task.start_time) {
+      before.start_time = Some(task.start_time);
+      after.start_time = Some(start_time);
+      set.insert("start_time", start_time);
+    }
+    let deadline = body.deadline.map(DateTime::from_chrono);
+    if let Some(deadline) = deadline.filter(|deadline| *deadline != task.deadline) {
+      before.deadline = Some(task.deadline);
+      after.deadline = Some(deadline);
+      set.insert("deadline", deadline);
+    }
+    if let Some(skills) = skills.filter(|skills| *skills != task.skills) {
+      before.skills = Some(task.skills.to_owned());
+      set.insert("skills", &skills);
+      after.skills = Some(skills);
+    }
+    if let Some(bounty) = body.bounty.filter(|bounty| *bounty != task.bounty) {
+      before.bounty = Some(task.bounty);
+      after.bounty = Some(bounty);
+      set.insert("bounty", to_bson(&bounty)?);
+    }
+    if let Some(visibility) = body
+      .visibility
+      .filter(|visibility| *visibility != task.visibility)
+    {
+      before.visibility = Some(task.visibility);
+      after.visibility = Some(visibility);
+      set.insert("visibility", to_bson(&visibility)?);
+    }
+    if set.is_empty() {
+      return Ok(SingleTaskResponse {
+        status: "Success",
+        data: TaskData {
+          task: task_response_for(&task, ctx)?,
+        },
+      });
     }
 
-    Ok(FreelancerListResponse {
+    let start_time = after.start_time.unwrap_or(task.start_time);
+    let deadline = after.deadline.unwrap_or(task.deadline);
+    if deadline < start_time {
+      return Err(InvalidScheduleError(format!(
+        "deadline {} is before start_time {}",
+        deadline.to_chrono(),
+        start_time.to_chrono()
+      )));
+    }
+    let locked: Vec<&str> = set
+      .keys()
+      .map(String::as_str)
+      .filter(|field| !EDITABLE_WHEN_ACCEPTED.contains(field))
+      .collect();
+    if !locked.is_empty() && task.status == TaskStatus::InProgress {
+      return Err(ConflictError(format!(
+        "{} can't change once a proposal is accepted, only {}",
+        locked.join(", "),
+        EDITABLE_WHEN_ACCEPTED.join(", ")
+      )));
+    }
+    if let Some(bounty) = after
+      .bounty
+      .filter(|bounty| bounty.token() != task.bounty.token())
+    {
+      // Proposals are priced in the bounty's token.
+      let proposals = self
+        .proposals_collection
+        .count_documents(doc! {"task_id": id, "deleted_at": null}, None)
+        .await
+        .map_err(MongoQueryError)?;
+      if proposals > 0 {
+        return Err(ConflictError(format!(
+          "the bounty can't move from {} to {} once proposals are made",
+          task.bounty.token(),
+          bounty.token()
+        )));
+      }
+    }
+
+    let now = DateTime::now();
+    set.insert("updated_at", now);
+    let edit = TaskEdit {
+      at: now,
+      by: ctx.user_id().to_string(),
+      before,
+      after,
+    };
+    let mut push = doc! {"edits": to_bson(&edit)?};
+    // Moving the deadline of an expired task later opens it again.
+    if task.status == TaskStatus::Expired && deadline > now {
+      let change = StatusChange {
+        from: TaskStatus::Expired,
+        to: TaskStatus::Open,
+        at: now,
+        by: ctx.user_id().to_string(),
+      };
+      set.insert("status", to_bson(&change.to)?);
+      push.insert("history", to_bson(&change)?);
+    }
+    let update = doc! {
+      "$set": set,
+      "$push": push,
+      "$inc": {"version": 1},
+    };
+    let task = self.update_read_task(id, task.version, update).await?;
+    self.caches.invalidate_tasks();
+    Ok(SingleTaskResponse {
       status: "Success",
-      results: json_result.len(),
-      users: json_result,
+      data: TaskData {
+        task: task_response_for(&task, ctx)?,
+      },
     })
   }
 
-  pub async fn add_freelancer(&self, body: &CreateFreelancerSchema) -> Result<SingleUserResponse> {
-    let user_body = &body.user;
-    let description = body.user.description.to_owned().unwrap_or_default();
-    //let role = "freelancer";
-    let skills = body.skills.to_owned().unwrap_or_default();
-    let document = build_freelancer_document(user_body, description, skills)?;
-
-    let options = IndexOptions::builder().unique(true).build();
-    let index = IndexModel::builder()
-      .keys(doc! {"user_name": 1})
-      .options(options)
-      .build();
-
-    match self
-      .freelancer_collection_model
-      .create_index(index, None)
-      .await
-    {
-      Ok(_) => {}
-      Err(e) => return Err(MongoQueryError(e)),
-    };
-
-    let insert_result = match self.freelancer_collection.insert_one(&document, None).await {
-      Ok(result) => result,
-      Err(e) => {
-        if e
-          .to_string()
-          .contains("E11000 duplicate key error collection")
-        {
-          return Err(MongoDuplicateError(e));
-        }
-        return Err(MongoQueryError(e));
-      }
+  /// Withdraws an open or expired task, closing its pending proposals. Their
+  /// freelancers see them closed on the change feed.
+  pub async fn cancel_task(
+    &self,
+    id: &str,
+    body: &CancelTaskSchema,
+    ctx: &Ctx,
+    expected_version: Option<i64>,
+  ) -> Result<TaskCancelResponse> {
+    let task = self.owned_task(id, ctx, expected_version).await?;
+    if task.status == TaskStatus::InProgress {
+      return Err(ConflictError(format!(
+        "task {} has an accepted proposal, cancel its deal instead",
+        id
+      )));
+    }
+    check_move(task.status, TaskStatus::Cancelled)?;
+
+    let now = DateTime::now();
+    let change = StatusChange {
+      from: task.status,
+      to: TaskStatus::Cancelled,
+      at: now,
+      by: ctx.user_id().to_string(),
     };
-
-    let new_id = insert_result
-      .inserted_id
-      .as_str()
-      .expect("issue with new _id");
-
-    let user_model = match self
-      .freelancer_collection_model
-      .find_one(doc! {"_id": new_id}, None)
-      .await
-    {
-      Ok(Some(doc)) => doc,
-      Ok(None) => return Err(NotFoundError(new_id.to_string())),
-      Err(e) => return Err(MongoQueryError(e)),
+    let update = doc! {
+      "$set": {
+        "status": to_bson(&change.to)?,
+        "cancelled_at": now,
+        "cancelled_by": ctx.user_id(),
+        "cancel_reason": body.reason.as_deref(),
+        "updated_at": now,
+      },
+      "$push": {"history": to_bson(&change)?},
+      "$inc": {"version": 1},
     };
-    let freelancer = doc_to_freelancer_response(&user_model)?;
+    let task = self.update_read_task(id, task.version, update).await?;
+    let closed = self.close_pending_proposals(id, "task cancelled").await?;
+    self.caches.invalidate_tasks();
 
-    Ok(SingleUserResponse {
+    Ok(TaskCancelResponse {
       status: "Success",
-      data: UserData {
-        user: freelancer.user,
+      data: TaskCancelData {
+        task: task_response_for(&task, ctx)?,
+        closed_proposals: closed,
       },
     })
   }
 
-  pub async fn add_review(&self, body: &CreateReviewSchema) -> Result<SingleReviewResponse> {
-    let _id = self
-      .review_collection
-      .count_documents(None, None)
-      .await
-      .map_err(MongoQueryError)?
-      + 1;
-    let document: Document = build_review_document(body, _id.to_string())?;
-    let options = IndexOptions::builder().unique(true).build();
-    let index = IndexModel::builder()
-      .keys(doc! {"review": 1})
-      .options(options)
-      .build();
+  /// Stores `uploads` and attaches them to a task the caller posted, at most
+  /// `MAX_ATTACHMENTS` in all.
+  pub async fn add_task_attachments(
+    &self,
+    id: &str,
+    uploads: Vec<Upload>,
+    storage: &dyn Storage,
+    ctx: &Ctx,
+    expected_version: Option<i64>,
+  ) -> Result<SingleTaskResponse> {
+    let task = self.owned_task(id, ctx, expected_version).await?;
+    if matches!(task.status, TaskStatus::Completed | TaskStatus::Cancelled) {
+      return Err(ConflictError(format!("task {} is {:?}", id, task.status)));
+    }
+    check_attachment_count(task.attachments.len(), uploads.len())?;
 
-    match self.review_collection_model.create_index(index, None).await {
-      Ok(_) => {}
-      Err(e) => return Err(MongoQueryError(e)),
+    let attachments = store_uploads(storage, &format!("tasks/{}", id), uploads, ctx).await?;
+    let update = doc! {
+      "$push": {"attachments": {"$each": to_bson(&attachments)?}},
+      "$set": {"updated_at": DateTime::now()},
+      "$inc": {"version": 1},
     };
-
-    let insert_result = match self.review_collection.insert_one(&document, None).await {
-      Ok(result) => result,
+    let task = match self.update_read_task(id, task.version, update).await {
+      Ok(task) => task,
       Err(e) => {
-        if e
-          .to_string()
-          .contains("E11000 duplicate key error collection")
-        {
-          return Err(MongoDuplicateError(e));
-        }
-        return Err(MongoQueryError(e));
+        discard(storage, &attachments).await;
+        return Err(e);
       }
     };
+    self.caches.invalidate_tasks();
+    Ok(SingleTaskResponse {
+      status: "Success",
+      data: TaskData {
+        task: task_response_for(&task, ctx)?,
+      },
+    })
+  }
 
-    let new_id = insert_result
-      .inserted_id
-      .as_str()
-      .expect("issue with new _id");
-
-    let review_model = match self
-      .review_collection_model
-      .find_one(doc! {"_id": new_id}, None)
+  /// An attachment of a live task, with its content.
+  pub async fn task_attachment(
+    &self,
+    id: &str,
+    attachment_id: &str,
+    storage: &dyn Storage,
+    ctx: &Ctx,
+  ) -> Result<(Attachment, Bytes)> {
+    let mut filter = doc! {"_id": id, "deleted_at": null};
+    if !ctx.is_admin() {
+      filter.extend(visible_tasks(ctx.user_id(), false)?);
+    }
+    let task = self
+      .tasks_collection_model
+      .find_one(filter, None)
       .await
-    {
-      Ok(Some(doc)) => doc,
-      Ok(None) => return Err(NotFoundError(new_id.to_string())),
-      Err(e) => return Err(MongoQueryError(e)),
-    };
-
-    let review = doc_to_review_response(&review_model)?;
+      .map_err(MyError::read_error)?
+      .ok_or_else(|| NotFoundError(id.to_string()))?;
+    let attachment = task
+      .attachments
+      .into_iter()
+      .find(|attachment| attachment.id == attachment_id)
+      .ok_or_else(|| NotFoundError(attachment_id.to_string()))?;
+    let bytes = storage.get(&attachment.key).await?;
+    Ok((attachment, bytes))
+  }
 
-    Ok(SingleReviewResponse {
+  /// Removes an attachment from a task the caller posted, and its file.
+  pub async fn delete_task_attachment(
+    &self,
+    id: &str,
+    attachment_id: &str,
+    storage: &dyn Storage,
+    ctx: &Ctx,
+    expected_version: Option<i64>,
+  ) -> Result<SingleTaskResponse> {
+    let task = self.owned_task(id, ctx, expected_version).await?;
+    let attachment = task
+      .attachments
+      .iter()
+      .find(|attachment| attachment.id == attachment_id)
+      .cloned()
+      .ok_or_else(|| NotFoundError(attachment_id.to_string()))?;
+    let update = doc! {
+      "$pull": {"attachments": {"id": attachment_id}},
+      "$set": {"updated_at": DateTime::now()},
+      "$inc": {"version": 1},
+    };
+    let task = self.update_read_task(id, task.version, update).await?;
+    storage.delete(&attachment.key).await?;
+    self.caches.invalidate_tasks();
+    Ok(SingleTaskResponse {
       status: "Success",
-      data: ReviewData { review },
+      data: TaskData {
+        task: task_response_for(&task, ctx)?,
+      },
     })
   }
 
-  pub async fn fetch_proposals(&self) -> Result<ProposalListResponse> {
-    let mut cursor = self
-      .proposals_collection_model
-      .find(None, None)
-      .await
-      .map_err(MongoQueryError)?;
+  /// Invites freelancers to bid on an open task the caller posted. Those
+  /// invited already are left as they are, unless they declined: they are
+  /// asked again.
+  pub async fn invite_freelancers(
+    &self,
+    id: &str,
+    body: &InviteFreelancersSchema,
+    ctx: &Ctx,
+    expected_version: Option<i64>,
+  ) -> Result<SingleTaskResponse> {
+    let task = self.owned_task(id, ctx, expected_version).await?;
+    if task.status != TaskStatus::Open {
+      return Err(ConflictError(format!(
+        "task {} is {:?}, not open to proposals",
+        id, task.status
+      )));
+    }
+    let now = DateTime::now();
+    let mut invitations = task.invitations.to_owned();
+    for freelancer_id in &body.freelancer_ids {
+      self
+        .referenced(
+          &self.freelancer_collection_model,
+          "freelancer_ids",
+          freelancer_id,
+        )
+        .await?;
+      let invitation = Invitation {
+        freelancer_id: freelancer_id.to_string(),
+        status: InvitationStatus::Pending,
+        invited_by: ctx.user_id().to_string(),
+        invited_at: now,
+        answered_at: None,
+      };
+      match invitations
+        .iter_mut()
+        .find(|invited| invited.freelancer_id == invitation.freelancer_id)
+      {
+        Some(invited) if invited.status == InvitationStatus::Declined => *invited = invitation,
+        Some(_) => {}
+        None => invitations.push(invitation),
+      }
+    }
+    if invitations.len() > MAX_INVITATIONS {
+      return Err(ConflictError(format!(
+        "a task has at most {} invitations, {} would have {}",
+        MAX_INVITATIONS,
+        id,
+        invitations.len()
+      )));
+    }
+    self.set_invitations(&task, invitations, ctx).await
+  }
 
-    let mut json_result: Vec<ProposalResponse> = Vec::new();
-    while let Some(doc) = cursor.next().await {
-      json_result.push(doc_to_proposal_response(&doc.unwrap())?);
+  /// Withdraws the invitation of `freelancer_id` to a task the caller posted.
+  /// Proposals they made already stay.
+  pub async fn revoke_invitation(
+    &self,
+    id: &str,
+    freelancer_id: &str,
+    ctx: &Ctx,
+    expected_version: Option<i64>,
+  ) -> Result<SingleTaskResponse> {
+    let task = self.owned_task(id, ctx, expected_version).await?;
+    let freelancer_id = Address::normalize(freelancer_id);
+    let mut invitations = task.invitations.to_owned();
+    invitations.retain(|invitation| invitation.freelancer_id != freelancer_id);
+    if invitations.len() == task.invitations.len() {
+      return Err(NotFoundError(freelancer_id));
     }
+    self.set_invitations(&task, invitations, ctx).await
+  }
 
-    Ok(ProposalListResponse {
+  /// Accepts or declines the caller's pending invitation to an open task.
+  pub async fn answer_invitation(
+    &self,
+    id: &str,
+    to: InvitationStatus,
+    ctx: &Ctx,
+  ) -> Result<SingleTaskResponse> {
+    let freelancer_id = ctx.user_id();
+    let task = self
+      .tasks_collection_model
+      .find_one(
+        doc! {"_id": id, "deleted_at": null, "invitations.freelancer_id": &freelancer_id},
+        None,
+      )
+      .await
+      .map_err(MyError::read_error)?
+      .ok_or_else(|| NotFoundError(id.to_string()))?;
+    if task.status != TaskStatus::Open {
+      return Err(ConflictError(format!(
+        "task {} is {:?}, not open to proposals",
+        id, task.status
+      )));
+    }
+    let mut invitations = task.invitations.to_owned();
+    for invitation in invitations
+      .iter_mut()
+      .filter(|invitation| invitation.freelancer_id == freelancer_id)
+    {
+      invitation.status = transition(invitation.status, to, false, true, false)?;
+      invitation.answered_at = Some(DateTime::now());
+    }
+    self.set_invitations(&task, invitations, ctx).await
+  }
+
+  /// Replaces the invitations of `task`, as long as it is still at the
+  /// version it was read at.
+  async fn set_invitations(
+    &self,
+    task: &TaskModel,
+    invitations: Vec<Invitation>,
+    ctx: &Ctx,
+  ) -> Result<SingleTaskResponse> {
+    let update = doc! {
+      "$set": {"invitations": to_bson(&invitations)?, "updated_at": DateTime::now()},
+      "$inc": {"version": 1},
+    };
+    let task = self
+      .update_read_task(&task.id, task.version, update)
+      .await?;
+    self.caches.invalidate_tasks();
+    Ok(SingleTaskResponse {
       status: "Success",
-      results: json_result.len(),
-      proposals: json_result,
+      data: TaskData {
+        task: task_response_for(&task, ctx)?,
+      },
     })
   }
 
-  pub async fn fetch_milestones(&self) -> Result<MilestoneListResponse> {
+  /// The live task `id`, when the caller posted it (or is an admin) and it is
+  /// at `expected_version`, if given.
+  async fn owned_task(
+    &self,
+    id: &str,
+    ctx: &Ctx,
+    expected_version: Option<i64>,
+  ) -> Result<TaskModel> {
+    let mut filter = owned_by("client_id", ctx);
+    filter.insert("_id", id);
+    filter.insert("deleted_at", Bson::Null);
+    let task = self
+      .tasks_collection_model
+      .find_one(filter, None)
+      .await
+      .map_err(MyError::read_error)?
+      .ok_or_else(|| NotFoundError(id.to_string()))?;
+    if let Some(expected) = expected_version.filter(|expected| *expected != task.version) {
+      return Err(PreconditionFailedError(format!(
+        "{} has changed since version {}",
+        id, expected
+      )));
+    }
+    Ok(task)
+  }
+
+  /// Applies `update` to task `id` if it is still at the `version` it was
+  /// read at, returning it updated.
+  async fn update_read_task(&self, id: &str, version: i64, update: Document) -> Result<TaskModel> {
+    let options = FindOneAndUpdateOptions::builder()
+      .return_document(ReturnDocument::After)
+      .build();
+    self
+      .tasks_collection_model
+      .find_one_and_update(
+        doc! {"_id": id, "version": version, "deleted_at": null},
+        update,
+        options,
+      )
+      .await
+      .map_err(MyError::read_error)?
+      .ok_or_else(|| ConflictError(format!("task {} changed meanwhile, retry", id)))
+  }
+
+  /// Closes the pending proposals on task `task_id` for `reason`, returning
+  /// how many were closed.
+  async fn close_pending_proposals(&self, task_id: &str, reason: &str) -> Result<u64> {
+    let now = DateTime::now();
+    let closed = self
+      .proposals_collection
+      .update_many(
+        doc! {"task_id": task_id, "accepted": false, "closed_at": null, "deleted_at": null},
+        doc! {
+          "$set": {"closed_at": now, "closed_reason": reason, "updated_at": now},
+          "$inc": {"version": 1},
+        },
+        None,
+      )
+      .await
+      .map_err(MongoQueryError)?;
+    self.caches.task_details.invalidate_all();
+    Ok(closed.modified_count)
+  }
+
+  /// Moves task `id` from `from` to `to` for `by`, recording the move in its
+  /// history. Returns whether it moved: it doesn't when it left `from`
+  /// meanwhile.
+  async fn move_task(&self, id: &str, from: TaskStatus, to: TaskStatus, by: &str) -> Result<bool> {
+    check_move(from, to)?;
+    let change = StatusChange {
+      from,
+      to,
+      at: DateTime::now(),
+      by: by.to_string(),
+    };
+    let moved = self
+      .tasks_collection
+      .update_one(
+        doc! {"_id": id, "status": to_bson(&from)?, "deleted_at": null},
+        doc! {
+          "$set": {"status": to_bson(&to)?, "updated_at": change.at},
+          "$push": {"history": to_bson(&change)?},
+          "$inc": {"version": 1},
+        },
+        None,
+      )
+      .await
+      .map_err(MongoQueryError)?;
+    if moved.modified_count > 0 {
+      self.caches.invalidate_tasks();
+    }
+    Ok(moved.modified_count > 0)
+  }
+
+  /// Takes back the last `move_task` of task `id` from `from` to `to`, for
+  /// when what it went along with couldn't be written or is gone. Whether the
+  /// task was still in `to`.
+  async fn undo_move_task(&self, id: &str, from: TaskStatus, to: TaskStatus) -> Result<bool> {
+    let undone = self
+      .tasks_collection
+      .update_one(
+        doc! {"_id": id, "status": to_bson(&to)?},
+        doc! {
+          "$set": {"status": to_bson(&from)?, "updated_at": DateTime::now()},
+          "$pop": {"history": 1},
+          "$inc": {"version": 1},
+        },
+        None,
+      )
+      .await
+      .map_err(MongoQueryError)?;
+    self.caches.invalidate_tasks();
+    Ok(undone.modified_count > 0)
+  }
+
+  /// Expires the open tasks whose deadline is past, closing their pending
+  /// proposals. Returns how many tasks expired.
+  pub async fn expire_tasks(&self) -> Result<u64> {
+    let filter = doc! {
+      "status": to_bson(&TaskStatus::Open)?,
+      "deadline": {"$lt": DateTime::now()},
+      "deleted_at": null,
+    };
     let mut cursor = self
-      .milestones_collection_model
-      .find(None, None)
+      .tasks_collection_model
+      .find(filter, None)
+      .await
+      .map_err(MongoQueryError)?;
+    let mut expired = 0;
+    while let Some(task) = cursor.next().await {
+      let task = task.map_err(MyError::read_error)?;
+      if self
+        .move_task(
+          &task.id,
+          TaskStatus::Open,
+          TaskStatus::Expired,
+          EXPIRY_ACTOR,
+        )
+        .await?
+      {
+        self
+          .close_pending_proposals(&task.id, "task expired")
+          .await?;
+        expired += 1;
+      }
+    }
+    Ok(expired)
+  }
+
+  pub async fn fetch_freelancers(&self, include_deleted: bool) -> Result<FreelancerListResponse> {
+    if include_deleted {
+      return self.load_freelancers(active_filter(true)).await;
+    }
+    self
+      .caches
+      .freelancers
+      .get_or_load("all", || self.load_freelancers(active_filter(false)))
+      .await
+  }
+
+  async fn load_freelancers(&self, filter: Document) -> Result<FreelancerListResponse> {
+    let mut cursor = self
+      .freelancer_collection_model
+      .find(filter, None)
       .await
       .map_err(MongoQueryError)?;
 
     let mut json_result = Vec::new();
     while let Some(doc) = cursor.next().await {
-      json_result.push(doc_to_milestone_response(&doc.unwrap())?);
+      json_result.push(doc_to_freelancer_response(
+        &doc.map_err(MyError::read_error)?,
+      )?);
     }
 
-    Ok(MilestoneListResponse {
+    Ok(FreelancerListResponse {
       status: "Success",
       results: json_result.len(),
-      milestones: json_result,
+      users: json_result,
     })
   }
-  pub async fn submit_proposal(
-    &self,
-    body: &CreateProposalSchema,
-  ) -> Result<SingleProposalResponse> {
-    let _id = self
-      .proposals_collection
-      .count_documents(None, None)
-      .await
-      .map_err(MongoQueryError)?
-      + 1;
-    let document = build_proposal_document(body, _id.to_string())?;
 
-    let insert_result = match self.proposals_collection.insert_one(&document, None).await {
-      Ok(result) => result,
-      Err(e) => {
-        if e
-          .to_string()
-          .contains("E11000 duplicate key error collection")
+  /// A live freelancer's profile along with the aggregate of the reviews
+  /// they received.
+  pub async fn get_freelancer(&self, id: &str) -> Result<SingleFreelancerProfileResponse> {
+    let id = &Address::normalize(id);
+    let freelancer = self
+      .caches
+      .freelancer_profiles
+      .get_or_load(id, || async {
+        match self
+          .freelancer_collection_model
+          .find_one(doc! {"_id": id, "deleted_at": null}, None)
+          .await
+          .map_err(MyError::read_error)?
         {
-          return Err(MongoDuplicateError(e));
+          Some(freelancer) => doc_to_freelancer_response(&freelancer),
+          None => Err(NotFoundError(id.to_string())),
         }
-        return Err(MongoQueryError(e));
+      })
+      .await?;
+    let reputation = self
+      .caches
+      .reputations
+      .get_or_load(id, || self.reputation(id))
+      .await?;
+
+    Ok(SingleFreelancerProfileResponse {
+      status: "Success",
+      data: FreelancerProfileData {
+        freelancer: FreelancerProfileResponse {
+          freelancer,
+          reputation,
+        },
+      },
+    })
+  }
+
+  async fn reputation(&self, freelancer_id: &str) -> Result<ReputationResponse> {
+    let pipeline = vec![
+      doc! {"$match": {"freelancer_id": freelancer_id, "deleted_at": null}},
+      doc! {"$group": {"_id": null, "reviews": {"$sum": 1}, "average_stars": {"$avg": "$stars"}}},
+    ];
+    let mut cursor = self
+      .review_collection
+      .aggregate(pipeline, None)
+      .await
+      .map_err(MongoQueryError)?;
+
+    match cursor.next().await {
+      Some(group) => {
+        let group = group.map_err(MongoQueryError)?;
+        Ok(ReputationResponse {
+          reviews: match group.get("reviews") {
+            Some(Bson::Int32(reviews)) => *reviews as u64,
+            Some(Bson::Int64(reviews)) => *reviews as u64,
+            _ => 0,
+          },
+          average_stars: group.get_f64("average_stars").ok(),
+        })
       }
-    };
+      None => Ok(ReputationResponse {
+        reviews: 0,
+        average_stars: None,
+      }),
+    }
+  }
 
-    let new_id = insert_result
-      .inserted_id
-      .as_str()
-      .expect("issue with new _id");
-    let proposal_model = match self
-      .proposals_collection_model
-      .find_one(doc! {"_id": new_id}, None)
+  /// The open tasks listed to the calling freelancer they haven't bid on yet,
+  /// ranked by how well they suit their skills, the bounty, the time left and
+  /// the deals they completed before.
+  pub async fn recommended_tasks(
+    &self,
+    ctx: &Ctx,
+    limit: Option<usize>,
+  ) -> Result<RecommendedTaskListResponse> {
+    let id = ctx.user_id();
+    let freelancer = self
+      .freelancer_collection_model
+      .find_one(doc! {"_id": id, "deleted_at": null}, None)
       .await
-    {
-      Ok(Some(doc)) => doc,
-      Ok(None) => return Err(NotFoundError(new_id.to_string())),
-      Err(e) => return Err(MongoQueryError(e)),
+      .map_err(MyError::read_error)?
+      .ok_or_else(|| ForbiddenError("task recommendations are for freelancers".to_string()))?;
+
+    let proposed: Vec<String> = self
+      .find_all(
+        &self.proposals_collection_model,
+        doc! {"freelancer_id": id, "deleted_at": null},
+      )
+      .await?
+      .into_iter()
+      .map(|proposal| proposal.task_id)
+      .collect();
+    let now = DateTime::now();
+    let mut filter = doc! {
+      "status": to_bson(&TaskStatus::Open)?,
+      "deadline": {"$gt": now},
+      "deleted_at": null,
+      "_id": {"$nin": proposed},
     };
+    filter.extend(visible_tasks(id, true)?);
+    let tasks = self.find_all(&self.tasks_collection_model, filter).await?;
+
+    let completed = self
+      .find_all(
+        &self.deals_collection_model,
+        doc! {
+          "freelancer_id": id,
+          "status": to_bson(&DealStatus::Completed)?,
+          "deleted_at": null,
+        },
+      )
+      .await?;
+    let mut history = History::default();
+    for deal in &completed {
+      *history
+        .deals_by_client
+        .entry(deal.client_id.to_owned())
+        .or_default() += 1;
+    }
+    let delivered: Vec<&str> = completed.iter().map(|deal| deal.task_id.as_str()).collect();
+    for task in self
+      .find_all(
+        &self.tasks_collection_model,
+        doc! {"_id": {"$in": delivered}},
+      )
+      .await?
+    {
+      history
+        .proven_skills
+        .extend(task.skills.iter().map(|skill| skill_key(skill)));
+    }
 
-    let proposal = doc_to_proposal_response(&proposal_model)?;
-
-    Ok(SingleProposalResponse {
+    let skills = freelancer.skills.unwrap_or_default();
+    let recommendations = rank(tasks, &skills, &history, now)
+      .into_iter()
+      .take(
+        limit
+          .unwrap_or(DEFAULT_RECOMMENDATIONS)
+          .clamp(1, MAX_RECOMMENDATIONS),
+      )
+      .map(|recommendation| {
+        Ok(RecommendedTaskResponse {
+          task: task_response_for(&recommendation.task, ctx)?,
+          score: recommendation.score,
+          reasons: recommendation.reasons,
+        })
+      })
+      .collect::<Result<Vec<_>>>()?;
+    Ok(RecommendedTaskListResponse {
       status: "Success",
-      data: ProposalData { proposal },
+      results: recommendations.len(),
+      recommendations,
     })
   }
 
-  pub async fn add_milestones(
-    &self,
-    body: &Vec<CreateMilestoneSchema>,
-  ) -> Result<SingleProposalResponse> {
-    let mil_id = self
-      .milestones_collection
-      .count_documents(None, None)
+  async fn find_all<T>(&self, collection: &Collection<T>, filter: Document) -> Result<Vec<T>>
+  where
+    T: DeserializeOwned + Unpin + Send + Sync,
+  {
+    let mut cursor = collection
+      .find(filter, None)
       .await
       .map_err(MongoQueryError)?;
-    let proposal_price = body.iter().fold(0.0, |acc, x| acc + x.price);
-    let document = build_milestones_document(body, mil_id)?;
+    let mut models = Vec::new();
+    while let Some(model) = cursor.next().await {
+      models.push(model.map_err(MyError::read_error)?);
+    }
+    Ok(models)
+  }
 
-    let insert_result = match self
-      .milestones_collection
-      .insert_many(&document, None)
+  pub async fn add_freelancer(&self, body: &CreateFreelancerSchema) -> Result<SingleUserResponse> {
+    let user_body = &body.user;
+    let description = body.user.description.to_owned().unwrap_or_default();
+    //let role = "freelancer";
+    let skills = self
+      .canonical_skills(&body.skills.to_owned().unwrap_or_default())
+      .await?;
+    let document = build_freelancer_document(user_body, description, skills)?;
+
+    let options = IndexOptions::builder().unique(true).build();
+    let index = IndexModel::builder()
+      .keys(doc! {"user_name": 1})
+      .options(options)
+      .build();
+
+    match self
+      .freelancer_collection_model
+      .create_index(index, None)
       .await
     {
-      Ok(res) => res,
+      Ok(_) => {}
+      Err(e) => return Err(MongoQueryError(e)),
+    };
+
+    let insert_result = match self.freelancer_collection.insert_one(&document, None).await {
+      Ok(result) => result,
       Err(e) => {
         if e
           .to_string()
@@ -609,207 +1651,2237 @@ impl DB {
       }
     };
 
-    let new_ids = insert_result
-      .inserted_ids
-      .iter()
-      .map(|id| id.1.as_str().expect("issue with new _id"))
-      .collect::<Vec<&str>>();
-
-    let proposal_id = body[0].proposal_id.clone();
-    let filter = doc! {"_id": &proposal_id};
-    let update = doc! {"$set": {"milestones_id": new_ids, "proposal_price": proposal_price}};
-    let options = FindOneAndUpdateOptions::builder()
-      .return_document(ReturnDocument::After)
-      .build();
+    let new_id = inserted_id(&insert_result.inserted_id)?;
+    self.caches.freelancers.invalidate_all();
 
-    if let Some(doc) = self
-      .proposals_collection_model
-      .find_one_and_update(filter, update, options)
+    let user_model = match self
+      .freelancer_collection_model
+      .find_one(doc! {"_id": new_id}, None)
       .await
-      .map_err(MongoQueryError)?
     {
-      let proposal = doc_to_proposal_response(&doc)?;
-      let proposal_response = SingleProposalResponse {
-        status: "Success",
-        data: ProposalData { proposal },
-      };
-      Ok(proposal_response)
-    } else {
-      Err(NotFoundError(proposal_id.to_string()))
-    }
+      Ok(Some(doc)) => doc,
+      Ok(None) => return Err(NotFoundError(new_id.to_string())),
+      Err(e) => return Err(MyError::read_error(e)),
+    };
+    let freelancer = doc_to_freelancer_response(&user_model)?;
+
+    Ok(SingleUserResponse {
+      status: "Success",
+      data: UserData {
+        user: freelancer.user,
+      },
+    })
   }
-  pub async fn approve_proposal(&self, proposal_id: &String) -> Result<SingleProposalDealResponse> {
-    let filter = doc! {"_id": proposal_id};
-    let update = doc! {"$set": {"accepted": true}};
 
-    let options = FindOneAndUpdateOptions::builder()
-      .return_document(ReturnDocument::After)
+  /// Skills whose name or an alias starts like `q`, by name; all of them
+  /// without `q`.
+  pub async fn search_skills(
+    &self,
+    q: Option<&str>,
+    limit: Option<i64>,
+  ) -> Result<SkillListResponse> {
+    let filter = match q.map(skill_key).filter(|prefix| !prefix.is_empty()) {
+      Some(prefix) => doc! {"keys": {"$regex": prefix_pattern(&prefix)}},
+      None => doc! {},
+    };
+    let options = FindOptions::builder()
+      .sort(doc! {"name": 1})
+      .limit(limit.map(|limit| limit.clamp(1, MAX_SKILL_RESULTS)))
       .build();
+    let skills = self.load_skills(filter, options).await?;
 
-    if let Some(doc) = self
-      .proposals_collection_model
-      .find_one_and_update(filter, update, options)
-      .await
-      .map_err(MongoQueryError)?
-    {
-      let (proposal, partial_deal) = doc_to_proposal_and_deal_response(&doc)?;
-      let deal = self.add_deal(&partial_deal).await?;
-      let proposal_response = SingleProposalDealResponse {
-        status: "Success",
-        data: ProposalDealData { proposal, deal },
-      };
-      Ok(proposal_response)
-    } else {
-      Err(NotFoundError(proposal_id.to_string()))
-    }
+    let json_result = skills
+      .iter()
+      .map(doc_to_skill_response)
+      .collect::<Result<Vec<_>>>()?;
+    Ok(SkillListResponse {
+      status: "Success",
+      results: json_result.len(),
+      skills: json_result,
+    })
   }
 
-  pub async fn add_deal(&self, partial_deal: &PartialDealResponse) -> Result<DealResponse> {
-    let _id = self
-      .deals_collection
-      .count_documents(None, None)
-      .await
-      .map_err(MongoQueryError)?
-      + 1;
-    let _id = _id.to_string();
-
-    let document = build_deal_document(_id.to_string(), partial_deal)?;
+  /// Adds a skill to the taxonomy, then renames the matching skills of
+  /// existing tasks and freelancers after it.
+  pub async fn create_skill(
+    &self,
+    body: &CreateSkillSchema,
+    ctx: &Ctx,
+  ) -> Result<SingleSkillResponse> {
+    let document = build_skill_document(body, ctx.user_id())?;
+    let keys: Vec<&str> = document
+      .get_array("keys")?
+      .iter()
+      .filter_map(Bson::as_str)
+      .collect();
+    let taken = self
+      .load_skills(doc! {"keys": {"$in": &keys}}, None)
+      .await?;
+    if let Some(skill) = taken.first() {
+      let key = keys
+        .iter()
+        .find(|key| skill.keys.iter().any(|taken| taken == *key))
+        .unwrap_or(&keys[0]);
+      return Err(ConflictError(format!(
+        "{} already names skill {}",
+        key, skill.name
+      )));
+    }
 
-    let insert_result = match self.deals_collection.insert_one(&document, None).await {
+    let insert_result = match self.skills_collection.insert_one(&document, None).await {
       Ok(result) => result,
       Err(e) => {
         if e
           .to_string()
           .contains("E11000 duplicate key error collection")
         {
-          return Err(MongoDuplicateError(e));
+          return Err(ConflictError(format!("skill {} was just added", body.name)));
         }
         return Err(MongoQueryError(e));
       }
     };
 
-    let new_id = insert_result
-      .inserted_id
-      .as_str()
-      .expect("issue with new _id");
+    let new_id = inserted_id(&insert_result.inserted_id)?;
+    let keys: Vec<String> = keys.into_iter().map(str::to_owned).collect();
+    self.normalize_listed_skills(&keys).await?;
 
-    let deal_model = match self
-      .deals_collection_model
+    let skill_model = match self
+      .skills_collection_model
       .find_one(doc! {"_id": new_id}, None)
       .await
     {
       Ok(Some(doc)) => doc,
       Ok(None) => return Err(NotFoundError(new_id.to_string())),
-      Err(e) => return Err(MongoQueryError(e)),
+      Err(e) => return Err(MyError::read_error(e)),
     };
-    docs_to_deal_response(&deal_model, partial_deal)
-  }
-
-  pub async fn fetch_deals(&self) -> Result<DealListResponse> {
-    let mut cursor = self
-      .deals_collection_model
-      .find(None, None)
-      .await
-      .map_err(MongoQueryError)?;
 
-    let mut json_result: Vec<DealResponse> = Vec::new();
-    while let Some(doc) = cursor.next().await {
-      json_result.push(doc_to_deal_response(&doc.unwrap())?);
-    }
-
-    Ok(DealListResponse {
+    Ok(SingleSkillResponse {
       status: "Success",
-      results: json_result.len(),
-      deals: json_result,
+      data: SkillData {
+        skill: doc_to_skill_response(&skill_model)?,
+      },
     })
   }
 
-  pub async fn update_deal(
+  /// Folds the skills named in `from` into the one named `into`: their names
+  /// and aliases become aliases of `into`, they are removed, and every task
+  /// and freelancer listing them lists `into` instead.
+  pub async fn merge_skills(
     &self,
-    deal_id: &String,
-    transaction_id: &String,
-  ) -> Result<SingleDealResponse> {
-    let filter = doc! {"_id": deal_id};
-    let update = doc! {"$set": {"address": transaction_id}};
-
-    let options = FindOneAndUpdateOptions::builder()
-      .return_document(ReturnDocument::After)
-      .build();
+    body: &MergeSkillsSchema,
+    ctx: &Ctx,
+  ) -> Result<SkillMergeResponse> {
+    let into = self.find_skill(&body.into).await?;
+    let mut aliases = into.aliases.clone();
+    let mut keys = into.keys.clone();
+    let mut merged: Vec<SkillModel> = Vec::new();
+    for name in &body.from {
+      let skill = self.find_skill(name).await?;
+      if skill.id == into.id {
+        return Err(ConflictError(format!(
+          "cannot merge {} into itself",
+          into.name
+        )));
+      }
+      if merged.iter().any(|seen| seen.id == skill.id) {
+        continue;
+      }
+      for alias in std::iter::once(&skill.name).chain(&skill.aliases) {
+        let key = skill_key(alias);
+        if !keys.contains(&key) {
+          keys.push(key);
+          aliases.push(alias.to_owned());
+        }
+      }
+      merged.push(skill);
+    }
 
-    if let Some(doc) = self
-      .deals_collection_model
-      .find_one_and_update(filter, update, options)
+    // Keys are unique across skills, so `into` can only take over those of
+    // the merged skills once they are gone: both writes commit together.
+    let ids: Vec<&str> = merged.iter().map(|skill| skill.id.as_str()).collect();
+    let names: Vec<&str> = merged.iter().map(|skill| skill.name.as_str()).collect();
+    let now = DateTime::now();
+    let mut session = self
+      .mongo_client
+      .start_session(None)
       .await
-      .map_err(MongoQueryError)?
-    {
-      let deal = doc_to_deal_response(&doc)?;
-      let proposal_response = SingleDealResponse {
-        status: "Success",
-        data: DealData { deal },
-      };
-      Ok(proposal_response)
-    } else {
-      Err(NotFoundError(deal_id.to_string()))
+      .map_err(MongoQueryError)?;
+    session
+      .start_transaction(None)
+      .await
+      .map_err(MongoQueryError)?;
+    let deleted = self
+      .skills_collection
+      .delete_many_with_session(doc! {"_id": {"$in": &ids}}, None, &mut session)
+      .await
+      .map_err(MongoQueryError)?;
+    if deleted.deleted_count != ids.len() as u64 {
+      return Err(ConflictError(format!(
+        "[{}] changed during the merge, try again",
+        names.join(", ")
+      )));
     }
-  }
-  pub async fn submit_milestone(
-    &self,
-    proposal_id: &String,
-    milestone_id: &String,
-    link: &String,
-  ) -> Result<SingleMilestoneResponse> {
-    let filter = doc! {"proposal_id": proposal_id, "_id": milestone_id};
-    let update = doc! {"$set": {"link": link}};
-
+    let update = doc! {
+      "$set": {"aliases": &aliases, "keys": &keys, "updated_at": now},
+      "$push": {"merges": {"skills": &names, "by": ctx.user_id(), "at": now}},
+      "$inc": {"version": 1},
+    };
     let options = FindOneAndUpdateOptions::builder()
       .return_document(ReturnDocument::After)
       .build();
+    let skill = self
+      .skills_collection_model
+      .find_one_and_update_with_session(doc! {"_id": &into.id}, update, options, &mut session)
+      .await
+      .map_err(MyError::read_error)?
+      .ok_or_else(|| NotFoundError(into.name.to_owned()))?;
+    session
+      .commit_transaction()
+      .await
+      .map_err(MongoQueryError)?;
 
-    if let Some(doc) = self
-      .milestones_collection_model
-      .find_one_and_update(filter, update, options)
+    let merged_keys: Vec<String> = keys
+      .into_iter()
+      .filter(|key| !into.keys.contains(key))
+      .collect();
+    let (tasks, freelancers) = self.normalize_listed_skills(&merged_keys).await?;
+    Ok(SkillMergeResponse {
+      status: "Success",
+      data: SkillMergeData {
+        skill: doc_to_skill_response(&skill)?,
+        merged: merged.into_iter().map(|skill| skill.name).collect(),
+        tasks,
+        freelancers,
+      },
+    })
+  }
+
+  /// The skill `name` is the name or an alias of.
+  async fn find_skill(&self, name: &str) -> Result<SkillModel> {
+    self
+      .skills_collection_model
+      .find_one(doc! {"keys": skill_key(name)}, None)
+      .await
+      .map_err(MyError::read_error)?
+      .ok_or_else(|| NotFoundError(format!("skill {}", name)))
+  }
+
+  async fn load_skills(
+    &self,
+    filter: Document,
+    options: impl Into<Option<FindOptions>>,
+  ) -> Result<Vec<SkillModel>> {
+    let mut cursor = self
+      .skills_collection_model
+      .find(filter, options)
+      .await
+      .map_err(MongoQueryError)?;
+    let mut skills = Vec::new();
+    while let Some(skill) = cursor.next().await {
+      skills.push(skill.map_err(MyError::read_error)?);
+    }
+    Ok(skills)
+  }
+
+  /// `skills` under their canonical names, without duplicates. Skills the
+  /// taxonomy doesn't know are kept as sent.
+  async fn canonical_skills(&self, skills: &[String]) -> Result<Vec<String>> {
+    let keys: Vec<String> = skills.iter().map(|skill| skill_key(skill)).collect();
+    let known = self.load_skills(doc! {"keys": {"$in": keys}}, None).await?;
+    Ok(Taxonomy::new(&known).canonical_all(skills))
+  }
+
+  /// Renames the skills of the tasks and freelancers listing one under any of
+  /// `keys` after the taxonomy, returning how many tasks and freelancers
+  /// changed.
+  async fn normalize_listed_skills(&self, keys: &[String]) -> Result<(u64, u64)> {
+    let taxonomy = Taxonomy::new(&self.load_skills(doc! {"keys": {"$in": keys}}, None).await?);
+    let patterns: Vec<Regex> = keys
+      .iter()
+      .map(|key| Regex {
+        pattern: key_pattern(key),
+        options: "i".to_string(),
+      })
+      .collect();
+    let filter = doc! {"skills": {"$in": patterns}};
+    let tasks = self
+      .normalize_skills(&self.tasks_collection, &filter, &taxonomy)
+      .await?;
+    let freelancers = self
+      .normalize_skills(&self.freelancer_collection, &filter, &taxonomy)
+      .await?;
+    self.caches.invalidate_all();
+    Ok((tasks, freelancers))
+  }
+
+  async fn normalize_skills(
+    &self,
+    collection: &Collection<Document>,
+    filter: &Document,
+    taxonomy: &Taxonomy,
+  ) -> Result<u64> {
+    let mut normalized = 0;
+    let mut cursor = collection
+      .find(filter.clone(), None)
+      .await
+      .map_err(MongoQueryError)?;
+    while let Some(document) = cursor.next().await {
+      let document = document.map_err(MongoQueryError)?;
+      let skills: Vec<String> = document
+        .get_array("skills")?
+        .iter()
+        .filter_map(Bson::as_str)
+        .map(str::to_owned)
+        .collect();
+      let canonical = taxonomy.canonical_all(&skills);
+      if canonical == skills {
+        continue;
+      }
+      let update = doc! {
+        "$set": {"skills": canonical, "updated_at": DateTime::now()},
+        "$inc": {"version": 1},
+      };
+      collection
+        .update_one(doc! {"_id": document.get("_id").cloned()}, update, None)
+        .await
+        .map_err(MongoQueryError)?;
+      normalized += 1;
+    }
+    Ok(normalized)
+  }
+
+  pub async fn add_review(
+    &self,
+    body: &CreateReviewSchema,
+    ctx: &Ctx,
+  ) -> Result<SingleReviewResponse> {
+    let deal = self
+      .referenced(&self.deals_collection_model, "deal_id", &body.deal_id)
+      .await?;
+    if deal.client_id != body.client_id {
+      return Err(BrokenReferenceError(format!(
+        "client_id {} is not the client of deal {}",
+        body.client_id, deal.id
+      )));
+    }
+    if deal.freelancer_id != body.freelancer_id {
+      return Err(BrokenReferenceError(format!(
+        "freelancer_id {} is not the freelancer of deal {}",
+        body.freelancer_id, deal.id
+      )));
+    }
+    if !ctx.is_admin() && ctx.user_id() != deal.client_id {
+      return Err(ForbiddenError(format!(
+        "only the client of deal {} can review it",
+        deal.id
+      )));
+    }
+    if deal.status != DealStatus::Completed {
+      return Err(InvalidTransitionError(format!(
+        "deal {} is {:?}, only completed deals can be reviewed",
+        deal.id, deal.status
+      )));
+    }
+    let reviewed = self
+      .review_collection
+      .count_documents(doc! {"deal_id": &deal.id, "deleted_at": null}, None)
+      .await
+      .map_err(MongoQueryError)?;
+    if reviewed > 0 {
+      return Err(ConflictError(format!(
+        "deal {} is already reviewed",
+        deal.id
+      )));
+    }
+    let _id = self
+      .review_collection
+      .count_documents(None, None)
       .await
       .map_err(MongoQueryError)?
+      + 1;
+    let document: Document = build_review_document(body, _id.to_string(), ctx.user_id())?;
+    let options = IndexOptions::builder().unique(true).build();
+    let index = IndexModel::builder()
+      .keys(doc! {"review": 1})
+      .options(options)
+      .build();
+
+    match self.review_collection_model.create_index(index, None).await {
+      Ok(_) => {}
+      Err(e) => return Err(MongoQueryError(e)),
+    };
+
+    let insert_result = match self.review_collection.insert_one(&document, None).await {
+      Ok(result) => result,
+      Err(e) => {
+        if e
+          .to_string()
+          .contains("E11000 duplicate key error collection")
+        {
+          return Err(MongoDuplicateError(e));
+        }
+        return Err(MongoQueryError(e));
+      }
+    };
+
+    let new_id = inserted_id(&insert_result.inserted_id)?;
+    self.caches.reputations.invalidate(&body.freelancer_id);
+
+    let review_model = match self
+      .review_collection_model
+      .find_one(doc! {"_id": new_id}, None)
+      .await
     {
-      let milestone = doc_to_milestone_response(&doc)?;
-      let proposal_response = SingleMilestoneResponse {
-        status: "Success",
-        data: MilestoneData { milestone },
-      };
-      Ok(proposal_response)
-    } else {
-      Err(NotFoundError(proposal_id.to_string()))
+      Ok(Some(doc)) => doc,
+      Ok(None) => return Err(NotFoundError(new_id.to_string())),
+      Err(e) => return Err(MyError::read_error(e)),
+    };
+
+    self.caches.task_details.invalidate_all();
+    let review = doc_to_review_response(&review_model)?;
+
+    Ok(SingleReviewResponse {
+      status: "Success",
+      data: ReviewData { review },
+    })
+  }
+
+  /// The proposals on live tasks the caller may see, in `sort` order.
+  pub async fn fetch_proposals(
+    &self,
+    ctx: &Ctx,
+    include_deleted: bool,
+    sort: Option<&str>,
+  ) -> Result<ProposalListResponse> {
+    let mut pipeline = vec![doc! {"$match": active_filter(include_deleted)}];
+    if let Some(sort) = sort_order(sort)? {
+      pipeline.push(doc! {"$sort": sort});
+    }
+    // Only admins include deleted documents, those of deleted parents too.
+    if !include_deleted {
+      pipeline.extend(self.of_visible_task("task_id", ctx)?);
+    }
+    let json_result = self
+      .aggregate_all::<ProposalModel>(&self.proposals_collection, pipeline)
+      .await?
+      .iter()
+      .map(doc_to_proposal_response)
+      .collect::<Result<Vec<_>>>()?;
+
+    Ok(ProposalListResponse {
+      status: "Success",
+      results: json_result.len(),
+      proposals: json_result,
+    })
+  }
+
+  /// Flags the milestones not approved yet that are due within
+  /// `MILESTONE_REMINDER_HOURS` (24 by default) with `reminded_at`, which
+  /// their parties receive on the change feed. Each is reminded once.
+  pub async fn remind_milestones(&self) -> Result<u64> {
+    let hours: i64 = std::env::var("MILESTONE_REMINDER_HOURS")
+      .ok()
+      .and_then(|hours| hours.parse().ok())
+      .unwrap_or(24);
+    let now = DateTime::now();
+    let due = DateTime::from_millis(now.timestamp_millis() + hours * 3_600_000);
+    let pending = [MilestoneStatus::Initialized, MilestoneStatus::Submitted]
+      .iter()
+      .map(to_bson)
+      .collect::<std::result::Result<Vec<_>, _>>()?;
+    let reminded = self
+      .milestones_collection
+      .update_many(
+        doc! {
+          "status": {"$in": pending},
+          "deadline": {"$lte": due},
+          "reminded_at": null,
+          "deleted_at": null,
+        },
+        doc! {
+          "$set": {"reminded_at": now, "updated_at": now},
+          "$inc": {"version": 1},
+        },
+        None,
+      )
+      .await
+      .map_err(MongoQueryError)?;
+    self.caches.task_details.invalidate_all();
+    Ok(reminded.modified_count)
+  }
+
+  /// The milestones of live proposals on tasks the caller may see, in `sort`
+  /// order.
+  pub async fn fetch_milestones(
+    &self,
+    ctx: &Ctx,
+    include_deleted: bool,
+    sort: Option<&str>,
+  ) -> Result<MilestoneListResponse> {
+    let mut pipeline = vec![doc! {"$match": active_filter(include_deleted)}];
+    if let Some(sort) = sort_order(sort)? {
+      pipeline.push(doc! {"$sort": sort});
+    }
+    // Only admins include deleted documents, those of deleted parents too.
+    if !include_deleted {
+      pipeline.extend(self.of_visible_proposal(ctx)?);
+    }
+    let json_result = self
+      .aggregate_all::<MilestoneModel>(&self.milestones_collection, pipeline)
+      .await?
+      .iter()
+      .map(doc_to_milestone_response)
+      .collect::<Result<Vec<_>>>()?;
+
+    Ok(MilestoneListResponse {
+      status: "Success",
+      results: json_result.len(),
+      milestones: json_result,
+    })
+  }
+  pub async fn submit_proposal(
+    &self,
+    body: &CreateProposalSchema,
+    ctx: &Ctx,
+  ) -> Result<SingleProposalResponse> {
+    let task = self
+      .check_proposal_parties(&body.task_id, &body.client_id, &body.freelancer_id)
+      .await?;
+    if task.status != TaskStatus::Open {
+      return Err(ConflictError(format!(
+        "task {} is {:?}, not open to proposals",
+        body.task_id, task.status
+      )));
     }
+    let caller = ctx.user_id();
+    if !ctx.is_admin() && caller != body.freelancer_id.as_str() {
+      return Err(ForbiddenError(format!(
+        "{} can't submit a proposal as {}",
+        caller, body.freelancer_id
+      )));
+    }
+    if task.visibility == TaskVisibility::InviteOnly
+      && !ctx.is_admin()
+      && !is_invited(&task, caller)
+    {
+      return Err(ForbiddenError(format!(
+        "task {} is invite-only and {} isn't invited",
+        body.task_id, caller
+      )));
+    }
+    let _id = self
+      .proposals_collection
+      .count_documents(None, None)
+      .await
+      .map_err(MongoQueryError)?
+      + 1;
+    // Priced in the task's token once milestones are added.
+    let price = Money::zero(task.bounty.token());
+    let document = build_proposal_document(body, _id.to_string(), &price, ctx.user_id())?;
+
+    let insert_result = match self.proposals_collection.insert_one(&document, None).await {
+      Ok(result) => result,
+      Err(e) => {
+        if e
+          .to_string()
+          .contains("E11000 duplicate key error collection")
+        {
+          return Err(MongoDuplicateError(e));
+        }
+        return Err(MongoQueryError(e));
+      }
+    };
+
+    let new_id = inserted_id(&insert_result.inserted_id)?;
+    self
+      .add_back_reference(
+        &self.tasks_collection,
+        &body.task_id,
+        "proposals_id",
+        new_id,
+      )
+      .await?;
+    self.caches.invalidate_tasks();
+    let proposal_model = match self
+      .proposals_collection_model
+      .find_one(doc! {"_id": new_id}, None)
+      .await
+    {
+      Ok(Some(doc)) => doc,
+      Ok(None) => return Err(NotFoundError(new_id.to_string())),
+      Err(e) => return Err(MyError::read_error(e)),
+    };
+
+    let proposal = doc_to_proposal_response(&proposal_model)?;
+
+    Ok(SingleProposalResponse {
+      status: "Success",
+      data: ProposalData { proposal },
+    })
   }
 
-  pub async fn submit_milestones(
+  pub async fn add_milestones(
     &self,
-    deal_id: &String,
-    milestone_id: &String,
-    link: &String,
-  ) -> Result<SingleDealResponse> {
-    let filter = doc! {"_id": deal_id};
-    //let query = format!("id: "{}"", milestone_id);
-    let update = doc! {"$set": {"milestones": milestone_id, "link": link}};
+    body: &[CreateMilestoneSchema],
+    ctx: &Ctx,
+  ) -> Result<SingleProposalResponse> {
+    let proposal_id = match body.first() {
+      Some(milestone) => milestone.proposal_id.clone(),
+      None => return Err(EmptyInputError("milestones".to_string())),
+    };
+    if let Some(milestone) = body.iter().find(|m| m.proposal_id != proposal_id) {
+      return Err(BrokenReferenceError(format!(
+        "proposal_id {} differs from the first milestone's {}",
+        milestone.proposal_id, proposal_id
+      )));
+    }
+    let proposal = self
+      .referenced(
+        &self.proposals_collection_model,
+        "proposal_id",
+        &proposal_id,
+      )
+      .await?;
+    if !ctx.is_admin() && ctx.user_id() != proposal.freelancer_id {
+      return Err(ForbiddenError(format!(
+        "only the freelancer of proposal {} can add milestones to it",
+        proposal_id
+      )));
+    }
+    if proposal.accepted {
+      return Err(ConflictError(format!(
+        "proposal {} is accepted, its milestones are agreed",
+        proposal_id
+      )));
+    }
+    if proposal.closed_at.is_some() {
+      return Err(ConflictError(format!(
+        "proposal {} is closed: {}",
+        proposal_id,
+        proposal.closed_reason.unwrap_or_default()
+      )));
+    }
+    let task = self
+      .referenced(&self.tasks_collection_model, "task_id", &proposal.task_id)
+      .await?;
+    if !sees_task(&task, ctx) {
+      return Err(NotFoundError(proposal_id.to_string()));
+    }
+    let start_time = task.start_time.to_chrono();
+    if let Some(milestone) = body.iter().find(|m| m.deadline < start_time) {
+      return Err(InvalidScheduleError(format!(
+        "milestone deadline {} is before the task's start_time {}",
+        milestone.deadline, start_time
+      )));
+    }
+    let mil_id = self
+      .milestones_collection
+      .count_documents(None, None)
+      .await
+      .map_err(MongoQueryError)?;
+    let token = task.bounty.token();
+    if let Some(milestone) = body.iter().find(|m| m.price.token() != token) {
+      return Err(InvalidAmountError(format!(
+        "milestone price {} is not in {}, the task's bounty token",
+        milestone.price, token
+      )));
+    }
+    // An overflowing batch is turned down before anything is written.
+    body
+      .iter()
+      .try_fold(Money::zero(token), |total, milestone| {
+        total.checked_add(milestone.price)
+      })?;
+    let document = build_milestones_document(body, mil_id, ctx.user_id())?;
+
+    let insert_result = match self
+      .milestones_collection
+      .insert_many(&document, None)
+      .await
+    {
+      Ok(res) => res,
+      Err(e) => {
+        if e
+          .to_string()
+          .contains("E11000 duplicate key error collection")
+        {
+          return Err(MongoDuplicateError(e));
+        }
+        return Err(MongoQueryError(e));
+      }
+    };
 
+    let new_ids = insert_result
+      .inserted_ids
+      .values()
+      .map(inserted_id)
+      .collect::<Result<Vec<&str>>>()?;
+
+    // Priced from every live milestone, those added before included.
+    let proposal_price = self.milestones_price(&proposal_id, token).await?;
+    let filter = doc! {
+      "_id": &proposal_id,
+      "deleted_at": null,
+      "accepted": {"$ne": true},
+      "closed_at": null,
+    };
+    let update = vec![doc! {"$set": {
+      "milestones_id": {"$concatArrays": [
+        {"$ifNull": ["$milestones_id", []]},
+        {"$literal": &new_ids},
+      ]},
+      "proposal_price": {"$literal": to_document(&proposal_price)?},
+      "updated_at": DateTime::now(),
+      "version": {"$add": [{"$ifNull": ["$version", 0]}, 1]},
+    }}];
     let options = FindOneAndUpdateOptions::builder()
       .return_document(ReturnDocument::After)
       .build();
 
     if let Some(doc) = self
-      .deals_collection_model
+      .proposals_collection_model
       .find_one_and_update(filter, update, options)
       .await
       .map_err(MongoQueryError)?
     {
-      let deal = doc_to_deal_response(&doc)?;
-      let proposal_response = SingleDealResponse {
+      self.caches.task_details.invalidate_all();
+      let proposal = doc_to_proposal_response(&doc)?;
+      let proposal_response = SingleProposalResponse {
         status: "Success",
-        data: DealData { deal },
+        data: ProposalData { proposal },
       };
       Ok(proposal_response)
     } else {
-      Err(NotFoundError(deal_id.to_string()))
+      // Accepted, closed or deleted meanwhile: the milestones go with it.
+      self
+        .milestones_collection
+        .delete_many(doc! {"_id": {"$in": &new_ids}}, None)
+        .await
+        .map_err(MongoQueryError)?;
+      Err(ConflictError(format!(
+        "proposal {} changed meanwhile, retry",
+        proposal_id
+      )))
     }
   }
+
+  /// The sum of the prices of the live milestones of proposal `id`.
+  async fn milestones_price(&self, id: &str, token: Token) -> Result<Money> {
+    self
+      .find_all(
+        &self.milestones_collection_model,
+        doc! {"proposal_id": id, "deleted_at": null},
+      )
+      .await?
+      .iter()
+      .try_fold(Money::zero(token), |total, milestone| {
+        total.checked_add(milestone.price)
+      })
+  }
+
+  /// Applies `update` to the milestone ids of proposal `id` and prices it
+  /// again from its live milestones.
+  async fn reprice_proposal(&self, id: &str, update: Document) -> Result<()> {
+    let proposal = self
+      .proposals_collection_model
+      .find_one(doc! {"_id": id}, None)
+      .await
+      .map_err(MyError::read_error)?
+      .ok_or_else(|| NotFoundError(id.to_string()))?;
+    let price = self
+      .milestones_price(id, proposal.proposal_price.token())
+      .await?;
+    let mut update = update;
+    update.insert(
+      "$set",
+      doc! {"proposal_price": to_document(&price)?, "updated_at": DateTime::now()},
+    );
+    update.insert("$inc", doc! {"version": 1});
+    self
+      .proposals_collection
+      .update_one(doc! {"_id": id}, update, None)
+      .await
+      .map_err(MongoQueryError)?;
+    self.caches.invalidate_tasks();
+    Ok(())
+  }
+
+  async fn set_accepted(&self, proposal_id: &str, accepted: bool) -> Result<()> {
+    self
+      .proposals_collection
+      .update_one(
+        doc! {"_id": proposal_id},
+        doc! {
+          "$set": {"accepted": accepted, "updated_at": DateTime::now()},
+          "$inc": {"version": 1},
+        },
+        None,
+      )
+      .await
+      .map_err(MongoQueryError)?;
+    self.caches.invalidate_tasks();
+    Ok(())
+  }
+  pub async fn approve_proposal(
+    &self,
+    proposal_id: &String,
+    ctx: &Ctx,
+    expected_version: Option<i64>,
+  ) -> Result<SingleProposalDealResponse> {
+    let filter = doc! {"_id": proposal_id, "deleted_at": null};
+    let proposal = self
+      .proposals_collection_model
+      .find_one(filter.clone(), None)
+      .await
+      .map_err(MyError::read_error)?
+      .ok_or_else(|| NotFoundError(proposal_id.to_string()))?;
+    if proposal.closed_at.is_some() {
+      return Err(ConflictError(format!(
+        "proposal {} is closed: {}",
+        proposal_id,
+        proposal.closed_reason.unwrap_or_default()
+      )));
+    }
+    let task = self
+      .check_proposal_parties(
+        &proposal.task_id,
+        &proposal.client_id,
+        &proposal.freelancer_id,
+      )
+      .await?;
+    if !ctx.is_admin() && ctx.user_id() != task.client_id {
+      return Err(ForbiddenError(format!(
+        "only the client of task {} can approve its proposals",
+        task.id
+      )));
+    }
+
+    // Taking the task first makes it the lock: of two proposals approved at
+    // once, only one moves it out of Open.
+    if !self
+      .move_task(
+        &task.id,
+        TaskStatus::Open,
+        TaskStatus::InProgress,
+        ctx.user_id(),
+      )
+      .await?
+    {
+      return Err(ConflictError(format!(
+        "task {} is no longer open, proposals can't be approved",
+        task.id
+      )));
+    }
+    let update = doc! {
+      "$set": {"accepted": true, "updated_at": DateTime::now()},
+      "$inc": {"version": 1},
+    };
+
+    let options = FindOneAndUpdateOptions::builder()
+      .return_document(ReturnDocument::After)
+      .build();
+
+    let approved = self
+      .proposals_collection_model
+      .find_one_and_update(with_version(&filter, expected_version), update, options)
+      .await
+      .map_err(MongoQueryError);
+    let doc = match approved {
+      Ok(Some(doc)) => doc,
+      Ok(None) => {
+        self
+          .undo_move_task(&task.id, TaskStatus::Open, TaskStatus::InProgress)
+          .await?;
+        return Err(
+          self
+            .missing_or_stale(
+              &self.proposals_collection,
+              filter,
+              proposal_id,
+              expected_version,
+            )
+            .await,
+        );
+      }
+      Err(e) => {
+        self
+          .undo_move_task(&task.id, TaskStatus::Open, TaskStatus::InProgress)
+          .await?;
+        return Err(e);
+      }
+    };
+    let dealt = match doc_to_proposal_and_deal_response(&doc) {
+      Ok((proposal, partial_deal)) => self
+        .add_deal(&partial_deal, ctx)
+        .await
+        .map(|deal| (proposal, deal)),
+      Err(e) => Err(e),
+    };
+    match dealt {
+      Ok((proposal, deal)) => Ok(SingleProposalDealResponse {
+        status: "Success",
+        data: ProposalDealData { proposal, deal },
+      }),
+      Err(e) => {
+        // Without its deal the approval is taken back, so it can be retried.
+        self
+          .deals_collection
+          .delete_many(doc! {"proposal_id": proposal_id}, None)
+          .await
+          .map_err(MongoQueryError)?;
+        self
+          .proposals_collection
+          .update_one(
+            doc! {"_id": proposal_id, "accepted": true},
+            doc! {
+              "$set": {"accepted": false, "updated_at": DateTime::now()},
+              "$inc": {"version": 1},
+            },
+            None,
+          )
+          .await
+          .map_err(MongoQueryError)?;
+        self
+          .undo_move_task(&task.id, TaskStatus::Open, TaskStatus::InProgress)
+          .await?;
+        Err(e)
+      }
+    }
+  }
+
+  pub async fn add_deal(
+    &self,
+    partial_deal: &PartialDealResponse,
+    ctx: &Ctx,
+  ) -> Result<DealResponse> {
+    let _id = self
+      .deals_collection
+      .count_documents(None, None)
+      .await
+      .map_err(MongoQueryError)?
+      + 1;
+    let _id = _id.to_string();
+
+    let document = build_deal_document(_id.to_string(), partial_deal, ctx.user_id())?;
+
+    let insert_result = match self.deals_collection.insert_one(&document, None).await {
+      Ok(result) => result,
+      Err(e) => {
+        if e
+          .to_string()
+          .contains("E11000 duplicate key error collection")
+        {
+          return Err(MongoDuplicateError(e));
+        }
+        return Err(MongoQueryError(e));
+      }
+    };
+
+    let new_id = inserted_id(&insert_result.inserted_id)?;
+
+    let deal_model = match self
+      .deals_collection_model
+      .find_one(doc! {"_id": new_id}, None)
+      .await
+    {
+      Ok(Some(doc)) => doc,
+      Ok(None) => return Err(NotFoundError(new_id.to_string())),
+      Err(e) => return Err(MyError::read_error(e)),
+    };
+    self.caches.task_details.invalidate_all();
+    docs_to_deal_response(&deal_model, partial_deal)
+  }
+
+  /// The deals on live tasks the caller may see, in `sort` order.
+  pub async fn fetch_deals(
+    &self,
+    ctx: &Ctx,
+    include_deleted: bool,
+    sort: Option<&str>,
+  ) -> Result<DealListResponse> {
+    let mut pipeline = vec![doc! {"$match": active_filter(include_deleted)}];
+    if let Some(sort) = sort_order(sort)? {
+      pipeline.push(doc! {"$sort": sort});
+    }
+    // Only admins include deleted documents, those of deleted parents too.
+    if !include_deleted {
+      pipeline.extend(self.of_visible_task("task_id", ctx)?);
+    }
+    let json_result = self
+      .aggregate_all::<DealModel>(&self.deals_collection, pipeline)
+      .await?
+      .iter()
+      .map(doc_to_deal_response)
+      .collect::<Result<Vec<_>>>()?;
+
+    Ok(DealListResponse {
+      status: "Success",
+      results: json_result.len(),
+      deals: json_result,
+    })
+  }
+
+  /// Sets the address the deal is paid through. Only the deal's client may,
+  /// and only on a task they still see.
+  pub async fn update_deal(
+    &self,
+    deal_id: &String,
+    address: &Address,
+    ctx: &Ctx,
+    expected_version: Option<i64>,
+  ) -> Result<SingleDealResponse> {
+    let mut filter = doc! {"_id": deal_id, "deleted_at": null};
+    let mut pipeline = vec![doc! {"$match": filter.clone()}];
+    pipeline.extend(self.of_visible_task("task_id", ctx)?);
+    let deal = self
+      .aggregate_all::<DealModel>(&self.deals_collection, pipeline)
+      .await?
+      .pop()
+      .ok_or_else(|| NotFoundError(deal_id.to_string()))?;
+    if !ctx.is_admin() && ctx.user_id() != deal.client_id {
+      return Err(ForbiddenError(format!(
+        "only the client of deal {} can set its address",
+        deal_id
+      )));
+    }
+    filter.extend(owned_by("client_id", ctx));
+    let update = doc! {
+      "$set": {"address": address.as_str(), "updated_at": DateTime::now()},
+      "$inc": {"version": 1},
+    };
+
+    let options = FindOneAndUpdateOptions::builder()
+      .return_document(ReturnDocument::After)
+      .build();
+
+    if let Some(doc) = self
+      .deals_collection_model
+      .find_one_and_update(with_version(&filter, expected_version), update, options)
+      .await
+      .map_err(MongoQueryError)?
+    {
+      self.caches.task_details.invalidate_all();
+      let deal = doc_to_deal_response(&doc)?;
+      let proposal_response = SingleDealResponse {
+        status: "Success",
+        data: DealData { deal },
+      };
+      Ok(proposal_response)
+    } else {
+      Err(
+        self
+          .missing_or_stale(&self.deals_collection, filter, deal_id, expected_version)
+          .await,
+      )
+    }
+  }
+
+  pub async fn submit_milestones(
+    &self,
+    deal_id: &String,
+    milestone_id: &String,
+    link: &String,
+  ) -> Result<SingleDealResponse> {
+    let filter = doc! {"_id": deal_id};
+    //let query = format!("id: "{}"", milestone_id);
+    let update = doc! {"$set": {"milestones": milestone_id, "link": link}};
+
+    let options = FindOneAndUpdateOptions::builder()
+      .return_document(ReturnDocument::After)
+      .build();
+
+    if let Some(doc) = self
+      .deals_collection_model
+      .find_one_and_update(filter, update, options)
+      .await
+      .map_err(MongoQueryError)?
+    {
+      let deal = doc_to_deal_response(&doc)?;
+      let proposal_response = SingleDealResponse {
+        status: "Success",
+        data: DealData { deal },
+      };
+      Ok(proposal_response)
+    } else {
+      Err(NotFoundError(deal_id.to_string()))
+    }
+  }
+  /// Moves a milestone to `to`, setting its `link` when one is given. The
+  /// caller must be the proposal's client or freelancer, as `to` requires.
+  pub async fn transition_milestone(
+    &self,
+    proposal_id: &str,
+    milestone_id: &str,
+    to: MilestoneStatus,
+    link: Option<&str>,
+    ctx: &Ctx,
+    expected_version: Option<i64>,
+  ) -> Result<SingleMilestoneResponse> {
+    let filter = doc! {"proposal_id": proposal_id, "_id": milestone_id, "deleted_at": null};
+    let (proposal, milestone) = self.proposal_milestone(proposal_id, milestone_id).await?;
+    transition(
+      milestone.status,
+      to,
+      ctx.user_id() == proposal.client_id,
+      ctx.user_id() == proposal.freelancer_id,
+      ctx.is_admin(),
+    )?;
+
+    let mut set = doc! {"status": to_bson(&to)?};
+    if let Some(link) = link {
+      set.insert("link", link);
+    }
+    let doc = self
+      .apply_transition(
+        &self.milestones_collection_model,
+        filter,
+        milestone_id,
+        (milestone.status, milestone.version),
+        to,
+        set,
+        ctx,
+        expected_version,
+      )
+      .await?;
+    Ok(SingleMilestoneResponse {
+      status: "Success",
+      data: MilestoneData {
+        milestone: doc_to_milestone_response(&doc)?,
+      },
+    })
+  }
+
+  /// Stores `uploads` as deliverables of a milestone not submitted yet, for
+  /// the proposal's freelancer. At most `MAX_ATTACHMENTS` in all.
+  pub async fn add_deliverables(
+    &self,
+    proposal_id: &str,
+    milestone_id: &str,
+    uploads: Vec<Upload>,
+    storage: &dyn Storage,
+    ctx: &Ctx,
+    expected_version: Option<i64>,
+  ) -> Result<SingleMilestoneResponse> {
+    let milestone = self
+      .deliverable_milestone(proposal_id, milestone_id, ctx, expected_version)
+      .await?;
+    check_attachment_count(milestone.deliverables.len(), uploads.len())?;
+
+    let prefix = format!("milestones/{}/{}", proposal_id, milestone_id);
+    let deliverables = store_uploads(storage, &prefix, uploads, ctx).await?;
+    let update = doc! {
+      "$push": {"deliverables": {"$each": to_bson(&deliverables)?}},
+      "$set": {"updated_at": DateTime::now()},
+      "$inc": {"version": 1},
+    };
+    let milestone = match self.update_read_milestone(&milestone, update).await {
+      Ok(milestone) => milestone,
+      Err(e) => {
+        discard(storage, &deliverables).await;
+        return Err(e);
+      }
+    };
+    Ok(SingleMilestoneResponse {
+      status: "Success",
+      data: MilestoneData {
+        milestone: doc_to_milestone_response(&milestone)?,
+      },
+    })
+  }
+
+  /// A deliverable with its content, for the parties of the proposal's deal
+  /// and the freelancer who uploaded it.
+  pub async fn deliverable(
+    &self,
+    proposal_id: &str,
+    milestone_id: &str,
+    attachment_id: &str,
+    storage: &dyn Storage,
+    ctx: &Ctx,
+  ) -> Result<(Attachment, Bytes)> {
+    let (_, milestone) = self.proposal_milestone(proposal_id, milestone_id).await?;
+    let deliverable = milestone
+      .deliverables
+      .into_iter()
+      .find(|deliverable| deliverable.id == attachment_id)
+      .ok_or_else(|| NotFoundError(attachment_id.to_string()))?;
+    let deal = self
+      .deals_collection_model
+      .find_one(doc! {"proposal_id": proposal_id, "deleted_at": null}, None)
+      .await
+      .map_err(MyError::read_error)?;
+    let party = deal
+      .is_some_and(|deal| ctx.user_id() == deal.client_id || ctx.user_id() == deal.freelancer_id);
+    if !party && !ctx.is_admin() && ctx.user_id() != deliverable.uploaded_by {
+      return Err(ForbiddenError(
+        "deliverables are only shared with the parties of the deal".to_string(),
+      ));
+    }
+    let bytes = storage.get(&deliverable.key).await?;
+    Ok((deliverable, bytes))
+  }
+
+  /// Removes a deliverable from a milestone not submitted yet, and its file.
+  pub async fn delete_deliverable(
+    &self,
+    proposal_id: &str,
+    milestone_id: &str,
+    attachment_id: &str,
+    storage: &dyn Storage,
+    ctx: &Ctx,
+    expected_version: Option<i64>,
+  ) -> Result<SingleMilestoneResponse> {
+    let milestone = self
+      .deliverable_milestone(proposal_id, milestone_id, ctx, expected_version)
+      .await?;
+    let deliverable = milestone
+      .deliverables
+      .iter()
+      .find(|deliverable| deliverable.id == attachment_id)
+      .cloned()
+      .ok_or_else(|| NotFoundError(attachment_id.to_string()))?;
+    let update = doc! {
+      "$pull": {"deliverables": {"id": attachment_id}},
+      "$set": {"updated_at": DateTime::now()},
+      "$inc": {"version": 1},
+    };
+    let milestone = self.update_read_milestone(&milestone, update).await?;
+    storage.delete(&deliverable.key).await?;
+    Ok(SingleMilestoneResponse {
+      status: "Success",
+      data: MilestoneData {
+        milestone: doc_to_milestone_response(&milestone)?,
+      },
+    })
+  }
+
+  /// The live milestone `milestone_id` of the live proposal `proposal_id`.
+  async fn proposal_milestone(
+    &self,
+    proposal_id: &str,
+    milestone_id: &str,
+  ) -> Result<(ProposalModel, MilestoneModel)> {
+    let proposal = self
+      .proposals_collection_model
+      .find_one(doc! {"_id": proposal_id, "deleted_at": null}, None)
+      .await
+      .map_err(MyError::read_error)?
+      .ok_or_else(|| NotFoundError(proposal_id.to_string()))?;
+    let milestone = self
+      .milestones_collection_model
+      .find_one(
+        doc! {"proposal_id": proposal_id, "_id": milestone_id, "deleted_at": null},
+        None,
+      )
+      .await
+      .map_err(MyError::read_error)?
+      .ok_or_else(|| NotFoundError(milestone_id.to_string()))?;
+    Ok((proposal, milestone))
+  }
+
+  /// A milestone whose deliverables the caller may change: they are the
+  /// proposal's freelancer (or an admin), it isn't submitted yet and it is at
+  /// `expected_version`, if given.
+  async fn deliverable_milestone(
+    &self,
+    proposal_id: &str,
+    milestone_id: &str,
+    ctx: &Ctx,
+    expected_version: Option<i64>,
+  ) -> Result<MilestoneModel> {
+    let (proposal, milestone) = self.proposal_milestone(proposal_id, milestone_id).await?;
+    if ctx.user_id() != proposal.freelancer_id && !ctx.is_admin() {
+      return Err(ForbiddenError(
+        "only the proposal's freelancer delivers its milestones".to_string(),
+      ));
+    }
+    if milestone.status != MilestoneStatus::Initialized {
+      return Err(ConflictError(format!(
+        "milestone {} is {:?}, deliverables change before it is submitted",
+        milestone_id, milestone.status
+      )));
+    }
+    if let Some(expected) = expected_version.filter(|expected| *expected != milestone.version) {
+      return Err(PreconditionFailedError(format!(
+        "{} has changed since version {}",
+        milestone_id, expected
+      )));
+    }
+    Ok(milestone)
+  }
+
+  /// Applies `update` to `milestone` if it is still at the version and in the
+  /// status it was read in, returning it updated.
+  async fn update_read_milestone(
+    &self,
+    milestone: &MilestoneModel,
+    update: Document,
+  ) -> Result<MilestoneModel> {
+    let options = FindOneAndUpdateOptions::builder()
+      .return_document(ReturnDocument::After)
+      .build();
+    let milestone = self
+      .milestones_collection_model
+      .find_one_and_update(
+        doc! {
+          "_id": &milestone.id,
+          "proposal_id": &milestone.proposal_id,
+          "version": milestone.version,
+          "status": to_bson(&milestone.status)?,
+          "deleted_at": null,
+        },
+        update,
+        options,
+      )
+      .await
+      .map_err(MyError::read_error)?
+      .ok_or_else(|| {
+        ConflictError(format!(
+          "milestone {} changed meanwhile, retry",
+          milestone.id
+        ))
+      })?;
+    self.caches.task_details.invalidate_all();
+    Ok(milestone)
+  }
+
+  /// Moves a deal to `to`. The caller must be the deal's client or
+  /// freelancer, as `to` requires.
+  pub async fn transition_deal(
+    &self,
+    deal_id: &str,
+    to: DealStatus,
+    ctx: &Ctx,
+    expected_version: Option<i64>,
+  ) -> Result<SingleDealResponse> {
+    let filter = doc! {"_id": deal_id, "deleted_at": null};
+    let deal = self
+      .deals_collection_model
+      .find_one(filter.clone(), None)
+      .await
+      .map_err(MyError::read_error)?
+      .ok_or_else(|| NotFoundError(deal_id.to_string()))?;
+    transition(
+      deal.status,
+      to,
+      ctx.user_id() == deal.client_id,
+      ctx.user_id() == deal.freelancer_id,
+      ctx.is_admin(),
+    )?;
+
+    // The task ends with its deal, and is moved first so a deal can't end
+    // on a task that was just cancelled on its own.
+    let task_status = match to {
+      DealStatus::Completed => Some(TaskStatus::Completed),
+      DealStatus::Cancelled => Some(TaskStatus::Cancelled),
+      _ => None,
+    };
+    if let Some(task_status) = task_status {
+      if !self
+        .move_task(
+          &deal.task_id,
+          TaskStatus::InProgress,
+          task_status,
+          ctx.user_id(),
+        )
+        .await?
+      {
+        return Err(ConflictError(format!(
+          "task {} is no longer in progress, deal {} can't move to {:?}",
+          deal.task_id, deal_id, to
+        )));
+      }
+    }
+    let moved = self
+      .apply_transition(
+        &self.deals_collection_model,
+        filter,
+        deal_id,
+        (deal.status, deal.version),
+        to,
+        doc! {"status": to_bson(&to)?},
+        ctx,
+        expected_version,
+      )
+      .await;
+    let doc = match moved {
+      Ok(doc) => doc,
+      Err(e) => {
+        if let Some(task_status) = task_status {
+          self
+            .undo_move_task(&deal.task_id, TaskStatus::InProgress, task_status)
+            .await?;
+        }
+        return Err(e);
+      }
+    };
+    Ok(SingleDealResponse {
+      status: "Success",
+      data: DealData {
+        deal: doc_to_deal_response(&doc)?,
+      },
+    })
+  }
+
+  /// Writes a checked move from `current` (status and version as read) to
+  /// `to`, along with `set`, and records it in the document's `history`.
+  /// The write only applies to the version that was read, so a concurrent
+  /// move can't be overwritten.
+  #[allow(clippy::too_many_arguments)]
+  async fn apply_transition<T, S>(
+    &self,
+    collection: &Collection<T>,
+    filter: Document,
+    id: &str,
+    current: (S, i64),
+    to: S,
+    set: Document,
+    ctx: &Ctx,
+    expected_version: Option<i64>,
+  ) -> Result<T>
+  where
+    T: DeserializeOwned + Unpin + Send + Sync,
+    S: Lifecycle,
+  {
+    let (from, version) = current;
+    if let Some(expected) = expected_version.filter(|expected| *expected != version) {
+      return Err(PreconditionFailedError(format!(
+        "{} has changed since version {}",
+        id, expected
+      )));
+    }
+    let change = StatusChange {
+      from,
+      to,
+      at: DateTime::now(),
+      by: ctx.user_id().to_string(),
+    };
+    let mut set = set;
+    set.insert("updated_at", change.at);
+    let update = doc! {
+      "$set": set,
+      "$push": {"history": to_bson(&change)?},
+      "$inc": {"version": 1},
+    };
+    let options = FindOneAndUpdateOptions::builder()
+      .return_document(ReturnDocument::After)
+      .build();
+
+    let moved = collection
+      .find_one_and_update(with_version(&filter, Some(version)), update, options)
+      .await
+      .map_err(MyError::read_error)?
+      .ok_or_else(|| {
+        InvalidTransitionError(format!("{} moved from {:?} meanwhile, retry", id, from))
+      })?;
+    self.caches.task_details.invalidate_all();
+    Ok(moved)
+  }
+
+  /// Marks the document matched by `filter` as deleted by the caller.
+  async fn soft_delete<T>(
+    &self,
+    collection: &Collection<T>,
+    id: &str,
+    filter: Document,
+    ctx: &Ctx,
+  ) -> Result<T>
+  where
+    T: DeserializeOwned + Unpin + Send + Sync,
+  {
+    let mut filter = filter;
+    filter.insert("_id", id);
+    filter.insert("deleted_at", Bson::Null);
+    let update = doc! {
+      "$set": {
+        "deleted_at": DateTime::now(),
+        "deleted_by": ctx.user_id(),
+        "updated_at": DateTime::now(),
+      },
+      "$inc": {"version": 1},
+    };
+    let options = FindOneAndUpdateOptions::builder()
+      .return_document(ReturnDocument::After)
+      .build();
+
+    let deleted = collection
+      .find_one_and_update(filter, update, options)
+      .await
+      .map_err(MongoQueryError)?
+      .ok_or_else(|| NotFoundError(id.to_string()))?;
+    self.caches.task_details.invalidate_all();
+    Ok(deleted)
+  }
+
+  /// Clears the deletion marker of the document `id` matched by `filter`.
+  /// Admin only.
+  async fn restore<T>(
+    &self,
+    collection: &Collection<T>,
+    id: &str,
+    filter: Document,
+    ctx: &Ctx,
+    expected_version: Option<i64>,
+  ) -> Result<T>
+  where
+    T: DeserializeOwned + Unpin + Send + Sync,
+  {
+    if !ctx.is_admin() {
+      return Err(ForbiddenError("only admins can restore".to_string()));
+    }
+    let mut filter = filter;
+    filter.insert("_id", id);
+    filter.insert("deleted_at", doc! {"$ne": null});
+    let update = doc! {
+      "$unset": {"deleted_at": "", "deleted_by": ""},
+      "$set": {"updated_at": DateTime::now()},
+      "$inc": {"version": 1},
+    };
+    let options = FindOneAndUpdateOptions::builder()
+      .return_document(ReturnDocument::After)
+      .build();
+
+    match collection
+      .find_one_and_update(with_version(&filter, expected_version), update, options)
+      .await
+      .map_err(MongoQueryError)?
+    {
+      Some(doc) => {
+        self.caches.task_details.invalidate_all();
+        Ok(doc)
+      }
+      None => Err(
+        self
+          .missing_or_stale(collection, filter, id, expected_version)
+          .await,
+      ),
+    }
+  }
+
+  /// Adds `id` to the `field` list of document `owner_id`. Each list update is
+  /// a single atomic operator, so concurrent writers never lose an entry;
+  /// `repair_back_references` recomputes the lists should a write fail midway.
+  async fn add_back_reference(
+    &self,
+    collection: &Collection<Document>,
+    owner_id: &str,
+    field: &str,
+    id: &str,
+  ) -> Result<()> {
+    let update = doc! {
+      "$addToSet": {field: id},
+      "$set": {"updated_at": DateTime::now()},
+      "$inc": {"version": 1},
+    };
+    collection
+      .update_one(doc! {"_id": owner_id}, update, None)
+      .await
+      .map_err(MongoQueryError)?;
+    Ok(())
+  }
+
+  async fn remove_back_reference(
+    &self,
+    collection: &Collection<Document>,
+    owner_id: &str,
+    field: &str,
+    id: &str,
+  ) -> Result<()> {
+    let update = doc! {
+      "$pull": {field: id},
+      "$set": {"updated_at": DateTime::now()},
+      "$inc": {"version": 1},
+    };
+    collection
+      .update_one(doc! {"_id": owner_id}, update, None)
+      .await
+      .map_err(MongoQueryError)?;
+    Ok(())
+  }
+
+  /// Recomputes every client's `tasks_ids` and every task's `proposals_id`
+  /// from the live tasks and proposals pointing at them, returning how many
+  /// clients and tasks had to be fixed.
+  pub async fn repair_back_references(&self) -> Result<(u64, u64)> {
+    let clients = self
+      .repair_list(
+        &self.client_collection,
+        "tasks_ids",
+        &self.tasks_collection,
+        "client_id",
+      )
+      .await?;
+    let tasks = self
+      .repair_list(
+        &self.tasks_collection,
+        "proposals_id",
+        &self.proposals_collection,
+        "task_id",
+      )
+      .await?;
+    self.caches.invalidate_all();
+    Ok((clients, tasks))
+  }
+
+  /// Sets `field` of every `owners` document to the ids of the live
+  /// `children` whose `parent_field` points at it, when it differs.
+  async fn repair_list(
+    &self,
+    owners: &Collection<Document>,
+    field: &str,
+    children: &Collection<Document>,
+    parent_field: &str,
+  ) -> Result<u64> {
+    let mut expected: HashMap<String, Vec<String>> = HashMap::new();
+    let mut cursor = children
+      .find(doc! {"deleted_at": null}, None)
+      .await
+      .map_err(MongoQueryError)?;
+    while let Some(child) = cursor.next().await {
+      let child = child.map_err(MongoQueryError)?;
+      if let (Ok(id), Ok(parent)) = (child.get_str("_id"), child.get_str(parent_field)) {
+        expected
+          .entry(parent.to_string())
+          .or_default()
+          .push(id.to_string());
+      }
+    }
+
+    let mut repaired = 0;
+    let mut cursor = owners.find(None, None).await.map_err(MongoQueryError)?;
+    while let Some(owner) = cursor.next().await {
+      let owner = owner.map_err(MongoQueryError)?;
+      let Ok(owner_id) = owner.get_str("_id") else {
+        continue;
+      };
+      let ids = expected.remove(owner_id).unwrap_or_default();
+      let current: Vec<&str> = owner
+        .get_array(field)
+        .map(|ids| ids.iter().filter_map(Bson::as_str).collect())
+        .unwrap_or_default();
+      let mut sorted_current = current.clone();
+      sorted_current.sort_unstable();
+      let mut sorted_ids: Vec<&str> = ids.iter().map(String::as_str).collect();
+      sorted_ids.sort_unstable();
+      if sorted_current == sorted_ids && !owner.contains_key("tasks_id") {
+        continue;
+      }
+
+      // `tasks_id` is what clients used to be created with.
+      let update = doc! {
+        "$set": {field: &ids, "updated_at": DateTime::now()},
+        "$unset": {"tasks_id": ""},
+        "$inc": {"version": 1},
+      };
+      owners
+        .update_one(doc! {"_id": owner_id}, update, None)
+        .await
+        .map_err(MongoQueryError)?;
+      repaired += 1;
+    }
+    Ok(repaired)
+  }
+
+  /// Loads the live document `id` points at through `field`, failing with a
+  /// broken reference naming both when there is none.
+  async fn referenced<T>(&self, collection: &Collection<T>, field: &str, id: &str) -> Result<T>
+  where
+    T: DeserializeOwned + Unpin + Send + Sync,
+  {
+    collection
+      .find_one(doc! {"_id": id, "deleted_at": null}, None)
+      .await
+      .map_err(MyError::read_error)?
+      .ok_or_else(|| BrokenReferenceError(format!("{} {} does not exist", field, id)))
+  }
+
+  /// A proposal's task, client and freelancer all exist and the client is the
+  /// one who posted the task, which is returned.
+  async fn check_proposal_parties(
+    &self,
+    task_id: &str,
+    client_id: &str,
+    freelancer_id: &str,
+  ) -> Result<TaskModel> {
+    let task = self
+      .referenced(&self.tasks_collection_model, "task_id", task_id)
+      .await?;
+    self
+      .referenced(&self.client_collection_model, "client_id", client_id)
+      .await?;
+    self
+      .referenced(
+        &self.freelancer_collection_model,
+        "freelancer_id",
+        freelancer_id,
+      )
+      .await?;
+    if task.client_id != client_id {
+      return Err(BrokenReferenceError(format!(
+        "client_id {} did not post task {}",
+        client_id, task_id
+      )));
+    }
+    Ok(task)
+  }
+
+  /// Called once a conditional update matched nothing: tells a missing document
+  /// apart from one whose version moved past the caller's `If-Match`.
+  async fn missing_or_stale<T>(
+    &self,
+    collection: &Collection<T>,
+    filter: Document,
+    id: &str,
+    expected_version: Option<i64>,
+  ) -> MyError {
+    let Some(version) = expected_version else {
+      return NotFoundError(id.to_string());
+    };
+    match collection.count_documents(filter, None).await {
+      Ok(0) => NotFoundError(id.to_string()),
+      Ok(_) => PreconditionFailedError(format!("{} has changed since version {}", id, version)),
+      Err(e) => MongoQueryError(e),
+    }
+  }
+
+  pub async fn delete_client(&self, id: &str, ctx: &Ctx) -> Result<SingleUserResponse> {
+    let id = &Address::normalize(id);
+    let client = self
+      .soft_delete(&self.client_collection_model, id, owned_by("_id", ctx), ctx)
+      .await?;
+    Ok(SingleUserResponse {
+      status: "Success",
+      data: UserData {
+        user: doc_to_client_response(&client)?.user,
+      },
+    })
+  }
+
+  pub async fn restore_client(
+    &self,
+    id: &str,
+    ctx: &Ctx,
+    expected_version: Option<i64>,
+  ) -> Result<SingleUserResponse> {
+    let id = &Address::normalize(id);
+    let client = self
+      .restore(
+        &self.client_collection_model,
+        id,
+        doc! {},
+        ctx,
+        expected_version,
+      )
+      .await?;
+    Ok(SingleUserResponse {
+      status: "Success",
+      data: UserData {
+        user: doc_to_client_response(&client)?.user,
+      },
+    })
+  }
+
+  pub async fn delete_freelancer(&self, id: &str, ctx: &Ctx) -> Result<SingleUserResponse> {
+    let id = &Address::normalize(id);
+    let freelancer = self
+      .soft_delete(
+        &self.freelancer_collection_model,
+        id,
+        owned_by("_id", ctx),
+        ctx,
+      )
+      .await?;
+    self.invalidate_freelancer(id);
+    Ok(SingleUserResponse {
+      status: "Success",
+      data: UserData {
+        user: doc_to_freelancer_response(&freelancer)?.user,
+      },
+    })
+  }
+
+  fn invalidate_freelancer(&self, id: &str) {
+    self.caches.freelancers.invalidate_all();
+    self.caches.freelancer_profiles.invalidate(id);
+  }
+
+  pub async fn restore_freelancer(
+    &self,
+    id: &str,
+    ctx: &Ctx,
+    expected_version: Option<i64>,
+  ) -> Result<SingleUserResponse> {
+    let id = &Address::normalize(id);
+    let freelancer = self
+      .restore(
+        &self.freelancer_collection_model,
+        id,
+        doc! {},
+        ctx,
+        expected_version,
+      )
+      .await?;
+    self.invalidate_freelancer(id);
+    Ok(SingleUserResponse {
+      status: "Success",
+      data: UserData {
+        user: doc_to_freelancer_response(&freelancer)?.user,
+      },
+    })
+  }
+
+  pub async fn delete_task(&self, id: &str, ctx: &Ctx) -> Result<SingleTaskResponse> {
+    let task = self
+      .soft_delete(
+        &self.tasks_collection_model,
+        id,
+        owned_by("client_id", ctx),
+        ctx,
+      )
+      .await?;
+    self
+      .remove_back_reference(&self.client_collection, &task.client_id, "tasks_ids", id)
+      .await?;
+    self.caches.invalidate_tasks();
+    Ok(SingleTaskResponse {
+      status: "Success",
+      data: TaskData {
+        task: task_response_for(&task, ctx)?,
+      },
+    })
+  }
+
+  pub async fn restore_task(
+    &self,
+    id: &str,
+    ctx: &Ctx,
+    expected_version: Option<i64>,
+  ) -> Result<SingleTaskResponse> {
+    let task = self
+      .restore(
+        &self.tasks_collection_model,
+        id,
+        doc! {},
+        ctx,
+        expected_version,
+      )
+      .await?;
+    self
+      .add_back_reference(&self.client_collection, &task.client_id, "tasks_ids", id)
+      .await?;
+    self.caches.invalidate_tasks();
+    Ok(SingleTaskResponse {
+      status: "Success",
+      data: TaskData {
+        task: task_response_for(&task, ctx)?,
+      },
+    })
+  }
+
+  pub async fn delete_proposal(&self, id: &str, ctx: &Ctx) -> Result<SingleProposalResponse> {
+    let proposal = self
+      .soft_delete(
+        &self.proposals_collection_model,
+        id,
+        owned_by("freelancer_id", ctx),
+        ctx,
+      )
+      .await?;
+    self
+      .remove_back_reference(
+        &self.tasks_collection,
+        &proposal.task_id,
+        "proposals_id",
+        id,
+      )
+      .await?;
+    self.caches.invalidate_tasks();
+    Ok(SingleProposalResponse {
+      status: "Success",
+      data: ProposalData {
+        proposal: doc_to_proposal_response(&proposal)?,
+      },
+    })
+  }
+
+  pub async fn restore_proposal(
+    &self,
+    id: &str,
+    ctx: &Ctx,
+    expected_version: Option<i64>,
+  ) -> Result<SingleProposalResponse> {
+    let proposal = self
+      .restore(
+        &self.proposals_collection_model,
+        id,
+        doc! {},
+        ctx,
+        expected_version,
+      )
+      .await?;
+    self
+      .add_back_reference(
+        &self.tasks_collection,
+        &proposal.task_id,
+        "proposals_id",
+        id,
+      )
+      .await?;
+    self.caches.invalidate_tasks();
+    Ok(SingleProposalResponse {
+      status: "Success",
+      data: ProposalData {
+        proposal: doc_to_proposal_response(&proposal)?,
+      },
+    })
+  }
+
+  pub async fn delete_milestone(
+    &self,
+    proposal_id: &str,
+    milestone_id: &str,
+    ctx: &Ctx,
+  ) -> Result<SingleMilestoneResponse> {
+    // Milestones are owned through their proposal's freelancer.
+    let mut filter = owned_by("freelancer_id", ctx);
+    filter.insert("_id", proposal_id);
+    filter.insert("deleted_at", Bson::Null);
+    let owned = self
+      .proposals_collection
+      .count_documents(filter, None)
+      .await
+      .map_err(MongoQueryError)?;
+    if owned == 0 {
+      return Err(NotFoundError(proposal_id.to_string()));
+    }
+
+    let milestone = self
+      .soft_delete(
+        &self.milestones_collection_model,
+        milestone_id,
+        doc! {"proposal_id": proposal_id},
+        ctx,
+      )
+      .await?;
+    self
+      .reprice_proposal(proposal_id, doc! {"$pull": {"milestones_id": milestone_id}})
+      .await?;
+    Ok(SingleMilestoneResponse {
+      status: "Success",
+      data: MilestoneData {
+        milestone: doc_to_milestone_response(&milestone)?,
+      },
+    })
+  }
+
+  pub async fn restore_milestone(
+    &self,
+    proposal_id: &str,
+    milestone_id: &str,
+    ctx: &Ctx,
+    expected_version: Option<i64>,
+  ) -> Result<SingleMilestoneResponse> {
+    let milestone = self
+      .restore(
+        &self.milestones_collection_model,
+        milestone_id,
+        doc! {"proposal_id": proposal_id},
+        ctx,
+        expected_version,
+      )
+      .await?;
+    self
+      .reprice_proposal(
+        proposal_id,
+        doc! {"$addToSet": {"milestones_id": milestone_id}},
+      )
+      .await?;
+    Ok(SingleMilestoneResponse {
+      status: "Success",
+      data: MilestoneData {
+        milestone: doc_to_milestone_response(&milestone)?,
+      },
+    })
+  }
+
+  /// Deletes a deal and, while its task is in progress, takes back the
+  /// approval it came from: the task is open again and the proposal no
+  /// longer accepted.
+  pub async fn delete_deal(&self, id: &str, ctx: &Ctx) -> Result<SingleDealResponse> {
+    let deal = self
+      .soft_delete(
+        &self.deals_collection_model,
+        id,
+        owned_by("client_id", ctx),
+        ctx,
+      )
+      .await?;
+    if self
+      .undo_move_task(&deal.task_id, TaskStatus::Open, TaskStatus::InProgress)
+      .await?
+    {
+      self.set_accepted(&deal.proposal_id, false).await?;
+    }
+    Ok(SingleDealResponse {
+      status: "Success",
+      data: DealData {
+        deal: doc_to_deal_response(&deal)?,
+      },
+    })
+  }
+
+  /// Restores a deal along with the approval its deletion took back, which
+  /// needs its task to still be open.
+  pub async fn restore_deal(
+    &self,
+    id: &str,
+    ctx: &Ctx,
+    expected_version: Option<i64>,
+  ) -> Result<SingleDealResponse> {
+    if !ctx.is_admin() {
+      return Err(ForbiddenError("only admins can restore".to_string()));
+    }
+    let deleted = self
+      .deals_collection_model
+      .find_one(doc! {"_id": id, "deleted_at": {"$ne": null}}, None)
+      .await
+      .map_err(MyError::read_error)?
+      .ok_or_else(|| NotFoundError(id.to_string()))?;
+    let accepted = self
+      .proposals_collection
+      .count_documents(doc! {"_id": &deleted.proposal_id, "accepted": true}, None)
+      .await
+      .map_err(MongoQueryError)?
+      > 0;
+    if !accepted
+      && !self
+        .move_task(
+          &deleted.task_id,
+          TaskStatus::Open,
+          TaskStatus::InProgress,
+          ctx.user_id(),
+        )
+        .await?
+    {
+      return Err(ConflictError(format!(
+        "task {} is no longer open",
+        deleted.task_id
+      )));
+    }
+    let deal = match self
+      .restore(
+        &self.deals_collection_model,
+        id,
+        doc! {},
+        ctx,
+        expected_version,
+      )
+      .await
+    {
+      Ok(deal) => deal,
+      Err(e) => {
+        if !accepted {
+          self
+            .undo_move_task(&deleted.task_id, TaskStatus::Open, TaskStatus::InProgress)
+            .await?;
+        }
+        return Err(e);
+      }
+    };
+    if !accepted {
+      self.set_accepted(&deal.proposal_id, true).await?;
+    }
+    Ok(SingleDealResponse {
+      status: "Success",
+      data: DealData {
+        deal: doc_to_deal_response(&deal)?,
+      },
+    })
+  }
+
+  pub async fn delete_review(&self, id: &str, ctx: &Ctx) -> Result<SingleReviewResponse> {
+    let review = self
+      .soft_delete(
+        &self.review_collection_model,
+        id,
+        owned_by("client_id", ctx),
+        ctx,
+      )
+      .await?;
+    self.caches.reputations.invalidate(&review.freelancer_id);
+    Ok(SingleReviewResponse {
+      status: "Success",
+      data: ReviewData {
+        review: doc_to_review_response(&review)?,
+      },
+    })
+  }
+
+  pub async fn restore_review(
+    &self,
+    id: &str,
+    ctx: &Ctx,
+    expected_version: Option<i64>,
+  ) -> Result<SingleReviewResponse> {
+    let review = self
+      .restore(
+        &self.review_collection_model,
+        id,
+        doc! {},
+        ctx,
+        expected_version,
+      )
+      .await?;
+    self.caches.reputations.invalidate(&review.freelancer_id);
+    Ok(SingleReviewResponse {
+      status: "Success",
+      data: ReviewData {
+        review: doc_to_review_response(&review)?,
+      },
+    })
+  }
+}
+
+/// The task fields its client may still edit once a proposal is accepted.
+const EDITABLE_WHEN_ACCEPTED: [&str; 2] = ["description", "deadline"];
+
+/// At most this many skills are completed at once.
+const MAX_SKILL_RESULTS: i64 = 50;
+
+/// Tasks recommended at once, by default and at most.
+const DEFAULT_RECOMMENDATIONS: usize = 20;
+const MAX_RECOMMENDATIONS: usize = 50;
+
+/// Who expired a task, in its history.
+const EXPIRY_ACTOR: &str = "scheduler";
+
+/// Refuses `sent` more files on top of the `kept` ones past `MAX_ATTACHMENTS`.
+fn check_attachment_count(kept: usize, sent: usize) -> Result<()> {
+  if kept + sent > MAX_ATTACHMENTS {
+    return Err(ConflictError(format!(
+      "at most {} files, {} already attached",
+      MAX_ATTACHMENTS, kept
+    )));
+  }
+  Ok(())
+}
+
+/// Stores `uploads` under `prefix`, each under a fresh id. When one fails the
+/// ones already stored are removed.
+async fn store_uploads(
+  storage: &dyn Storage,
+  prefix: &str,
+  uploads: Vec<Upload>,
+  ctx: &Ctx,
+) -> Result<Vec<Attachment>> {
+  let mut attachments: Vec<Attachment> = Vec::with_capacity(uploads.len());
+  for upload in uploads {
+    let id = format!("{:016x}", rand::thread_rng().gen::<u64>());
+    let key = format!("{}/{}", prefix, id);
+    let size = upload.bytes.len() as i64;
+    if let Err(e) = storage.put(&key, upload.bytes).await {
+      discard(storage, &attachments).await;
+      return Err(e);
+    }
+    attachments.push(Attachment {
+      id,
+      file_name: upload.file_name,
+      content_type: upload.content_type,
+      size,
+      key,
+      uploaded_by: ctx.user_id().to_string(),
+      uploaded_at: DateTime::now(),
+    });
+  }
+  Ok(attachments)
+}
+
+/// Removes the files of attachments that weren't recorded. Best effort: a
+/// file left behind only takes space.
+async fn discard(storage: &dyn Storage, attachments: &[Attachment]) {
+  for attachment in attachments {
+    if let Err(e) = storage.delete(&attachment.key).await {
+      println!("❌ Removing {} failed: {}", attachment.key, e);
+    }
+  }
+}
+
+/// The tasks `user_id` may see: their own, those they are invited to and
+/// haven't declined, and the public ones, unlisted ones too unless `listed`.
+fn visible_tasks(user_id: &str, listed: bool) -> Result<Document> {
+  let shown = if listed {
+    doc! {"visibility": to_bson(&TaskVisibility::Public)?}
+  } else {
+    doc! {"visibility": {"$ne": to_bson(&TaskVisibility::InviteOnly)?}}
+  };
+  let invited = doc! {"invitations": {"$elemMatch": {
+    "freelancer_id": &user_id,
+    "status": {"$ne": to_bson(&InvitationStatus::Declined)?},
+  }}};
+  Ok(doc! {"$or": [shown, {"client_id": &user_id}, invited]})
+}
+
+/// Whether `ctx` may see `task`, as `visible_tasks` tells for a task fetched
+/// by id.
+fn sees_task(task: &TaskModel, ctx: &Ctx) -> bool {
+  ctx.is_admin()
+    || task.visibility != TaskVisibility::InviteOnly
+    || task.client_id == ctx.user_id()
+    || is_invited(task, ctx.user_id())
+}
+
+/// Whether `freelancer_id` is invited to `task` and hasn't declined.
+fn is_invited(task: &TaskModel, freelancer_id: &str) -> bool {
+  task.invitations.iter().any(|invitation| {
+    invitation.freelancer_id == freelancer_id && invitation.status != InvitationStatus::Declined
+  })
+}
+
+/// Stages keeping the documents whose `local` field is the `_id` of a
+/// document of `from` that passes `stages`, looked up as `name`.
+fn linked(from: &str, local: &str, name: &str, mut stages: Vec<Document>) -> Vec<Document> {
+  stages.insert(
+    0,
+    doc! {"$match": {"$expr": {"$eq": ["$_id", format!("$${}", name)]}}},
+  );
+  stages.push(doc! {"$project": {"_id": 1}});
+  vec![
+    doc! {"$lookup": {
+      "from": from,
+      "let": {name: format!("${}", local)},
+      "pipeline": stages,
+      "as": name,
+    }},
+    doc! {"$match": {format!("{}.0", name): {"$exists": true}}},
+    doc! {"$project": {name: 0}},
+  ]
+}
+
+/// `task` as `ctx` sees it: only its client and admins see every invitation,
+/// an invited freelancer sees their own.
+fn task_response_for(task: &TaskModel, ctx: &Ctx) -> Result<TaskResponse> {
+  let mut response = doc_to_task_response(task)?;
+  let user_id = ctx.user_id();
+  if !ctx.is_admin() && user_id != task.client_id {
+    response
+      .invitations
+      .retain(|invitation| invitation.freelancer_id == user_id);
+  }
+  Ok(response)
+}
+
+/// Restricts a filter to documents whose `owner_field` is the caller, admins excepted.
+fn owned_by(owner_field: &str, ctx: &Ctx) -> Document {
+  if ctx.is_admin() {
+    doc! {}
+  } else {
+    doc! {owner_field: ctx.user_id()}
+  }
+}
+
+/// `_id`s generated by the data layer are strings; anything else means the
+/// insert went through a path that doesn't set one.
+fn inserted_id(id: &Bson) -> Result<&str> {
+  id.as_str()
+    .ok_or_else(|| MalformedDocumentError(format!("inserted _id {} is not a string", id)))
 }
@@ -0,0 +1,415 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::NaiveDate;
+use futures::StreamExt;
+use mongodb::bson::{doc, Bson, Document};
+use mongodb::options::FindOptions;
+use mongodb::Collection;
+
+use crate::db::{Result, DB};
+use crate::error::MyError::*;
+use crate::utils::parse_datetime;
+
+/// Logical names of the dumped collections, independent of the configured
+/// collection names. Each one is written to `<dir>/<name>.ndjson`.
+pub const COLLECTIONS: [&str; 8] = [
+  "clients",
+  "freelancers",
+  "tasks",
+  "proposals",
+  "milestones",
+  "deals",
+  "reviews",
+  "skills",
+];
+
+/// Every `(collection, field, target collection)` id reference between the
+/// dumped collections. Fields hold either one id or an array of ids.
+const REFERENCES: [(&str, &str, &str); 15] = [
+  ("clients", "tasks_ids", "tasks"),
+  ("tasks", "client_id", "clients"),
+  ("tasks", "proposals_id", "proposals"),
+  ("proposals", "task_id", "tasks"),
+  ("proposals", "client_id", "clients"),
+  ("proposals", "freelancer_id", "freelancers"),
+  ("proposals", "milestones_id", "milestones"),
+  ("milestones", "proposal_id", "proposals"),
+  ("deals", "task_id", "tasks"),
+  ("deals", "proposal_id", "proposals"),
+  ("deals", "client_id", "clients"),
+  ("deals", "freelancer_id", "freelancers"),
+  ("reviews", "deal_id", "deals"),
+  ("reviews", "client_id", "clients"),
+  ("reviews", "freelancer_id", "freelancers"),
+];
+
+/// Restricts an export to the tasks starting within `since..=until` and/or
+/// the tasks and proposals `user_id` takes part in. Milestones, deals and
+/// reviews follow their proposal, and the users they reference are exported
+/// along with them. The skill taxonomy is always exported whole.
+#[derive(Debug, Default)]
+pub struct ExportFilter {
+  pub since: Option<NaiveDate>,
+  pub until: Option<NaiveDate>,
+  pub user_id: Option<String>,
+}
+
+impl ExportFilter {
+  fn is_empty(&self) -> bool {
+    self.since.is_none() && self.until.is_none() && self.user_id.is_none()
+  }
+
+  fn in_range(&self, task: &Document) -> bool {
+    if self.since.is_none() && self.until.is_none() {
+      return true;
+    }
+    let start_time = match task.get("start_time") {
+      Some(Bson::DateTime(start_time)) => Some(start_time.to_chrono()),
+      // Dumps taken before schedules were migrated to datetimes.
+      Some(Bson::String(start_time)) => parse_datetime(start_time),
+      _ => None,
+    };
+    match start_time.map(|start_time| start_time.date_naive()) {
+      Some(day) => {
+        self.since.is_none_or(|since| day >= since) && self.until.is_none_or(|until| day <= until)
+      }
+      None => false,
+    }
+  }
+}
+
+/// What to do with a dumped document whose `_id` already exists.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum OnConflict {
+  /// Abort before writing anything.
+  Fail,
+  /// Keep the stored document.
+  Skip,
+  /// Replace the stored document with the dumped one.
+  Overwrite,
+}
+
+#[derive(Debug, Default)]
+pub struct ImportReport {
+  pub collection: &'static str,
+  pub inserted: u64,
+  pub skipped: u64,
+  pub replaced: u64,
+}
+
+#[derive(Debug)]
+pub struct BrokenReference {
+  pub collection: &'static str,
+  pub id: String,
+  pub field: &'static str,
+  pub missing: String,
+}
+
+fn collection<'a>(db: &'a DB, name: &str) -> &'a Collection<Document> {
+  match name {
+    "clients" => &db.client_collection,
+    "freelancers" => &db.freelancer_collection,
+    "tasks" => &db.tasks_collection,
+    "proposals" => &db.proposals_collection,
+    "milestones" => &db.milestones_collection,
+    "deals" => &db.deals_collection,
+    "skills" => &db.skills_collection,
+    _ => &db.review_collection,
+  }
+}
+
+fn dump_path(dir: &Path, name: &str) -> PathBuf {
+  dir.join(format!("{}.ndjson", name))
+}
+
+/// The ids held by `field`, whether it is a single id or an array of them.
+fn referenced_ids<'a>(document: &'a Document, field: &str) -> Vec<&'a str> {
+  match document.get(field) {
+    Some(Bson::String(id)) => vec![id.as_str()],
+    Some(Bson::Array(ids)) => ids.iter().filter_map(Bson::as_str).collect(),
+    _ => Vec::new(),
+  }
+}
+
+fn document_id(document: &Document) -> String {
+  match document.get("_id") {
+    Some(Bson::String(id)) => id.to_owned(),
+    Some(id) => id.to_string(),
+    None => String::new(),
+  }
+}
+
+async fn find_all(collection: &Collection<Document>, filter: Document) -> Result<Vec<Document>> {
+  let mut cursor = collection
+    .find(filter, None)
+    .await
+    .map_err(MongoQueryError)?;
+  let mut documents = Vec::new();
+  while let Some(document) = cursor.next().await {
+    documents.push(document.map_err(MongoQueryError)?);
+  }
+  Ok(documents)
+}
+
+async fn find_ids(collection: &Collection<Document>) -> Result<HashSet<String>> {
+  let options = FindOptions::builder().projection(doc! {"_id": 1}).build();
+  let mut cursor = collection
+    .find(None, options)
+    .await
+    .map_err(MongoQueryError)?;
+  let mut ids = HashSet::new();
+  while let Some(document) = cursor.next().await {
+    ids.insert(document_id(&document.map_err(MongoQueryError)?));
+  }
+  Ok(ids)
+}
+
+fn write_documents(dir: &Path, name: &str, documents: &[Document]) -> Result<()> {
+  let mut out = BufWriter::new(File::create(dump_path(dir, name))?);
+  for document in documents {
+    let line = Bson::Document(document.clone()).into_canonical_extjson();
+    writeln!(out, "{}", line)?;
+  }
+  out.flush()?;
+  Ok(())
+}
+
+fn read_documents(dir: &Path, name: &str) -> Result<Option<Vec<Document>>> {
+  let path = dump_path(dir, name);
+  if !path.exists() {
+    return Ok(None);
+  }
+
+  let mut documents = Vec::new();
+  for (number, line) in BufReader::new(File::open(&path)?).lines().enumerate() {
+    let line = line?;
+    if line.trim().is_empty() {
+      continue;
+    }
+    let malformed =
+      |e: String| MalformedDocumentError(format!("{}:{}: {}", path.display(), number + 1, e));
+    let value: serde_json::Value =
+      serde_json::from_str(&line).map_err(|e| malformed(e.to_string()))?;
+    match Bson::try_from(value).map_err(|e| malformed(e.to_string()))? {
+      Bson::Document(document) => documents.push(document),
+      other => return Err(malformed(format!("expected a document, found {}", other))),
+    }
+  }
+  Ok(Some(documents))
+}
+
+/// Writes every collection to `<dir>/<name>.ndjson`, one canonical extended
+/// JSON document per line, and returns how many documents each file holds.
+/// Soft-deleted documents are included.
+pub async fn export(
+  db: &DB,
+  dir: &Path,
+  filter: &ExportFilter,
+) -> Result<Vec<(&'static str, usize)>> {
+  std::fs::create_dir_all(dir)?;
+
+  let mut selected: HashMap<&str, Vec<Document>> = HashMap::new();
+  for name in COLLECTIONS {
+    selected.insert(name, find_all(collection(db, name), doc! {}).await?);
+  }
+
+  if !filter.is_empty() {
+    let user = filter.user_id.as_deref();
+    let takes_part = |document: &Document| {
+      user.is_none_or(|user| {
+        ["client_id", "freelancer_id"]
+          .iter()
+          .any(|field| document.get_str(field) == Ok(user))
+      })
+    };
+
+    // A freelancer takes part in the tasks they bid on, not only the ones
+    // they own, so proposals decide task membership too.
+    let bid_on: HashSet<String> = selected["proposals"]
+      .iter()
+      .filter(|proposal| takes_part(proposal))
+      .filter_map(|proposal| proposal.get_str("task_id").ok().map(str::to_string))
+      .collect();
+    let tasks: Vec<Document> = selected["tasks"]
+      .iter()
+      .filter(|task| filter.in_range(task))
+      .filter(|task| takes_part(task) || bid_on.contains(&document_id(task)))
+      .cloned()
+      .collect();
+    let task_ids: HashSet<String> = tasks.iter().map(document_id).collect();
+
+    let belongs = |document: &Document, field: &str, ids: &HashSet<String>| {
+      document.get_str(field).is_ok_and(|id| ids.contains(id))
+    };
+    let proposals: Vec<Document> = selected["proposals"]
+      .iter()
+      .filter(|proposal| belongs(proposal, "task_id", &task_ids) && takes_part(proposal))
+      .cloned()
+      .collect();
+    let proposal_ids: HashSet<String> = proposals.iter().map(document_id).collect();
+    let milestones: Vec<Document> = selected["milestones"]
+      .iter()
+      .filter(|milestone| belongs(milestone, "proposal_id", &proposal_ids))
+      .cloned()
+      .collect();
+    let deals: Vec<Document> = selected["deals"]
+      .iter()
+      .filter(|deal| belongs(deal, "proposal_id", &proposal_ids))
+      .cloned()
+      .collect();
+    let deal_ids: HashSet<String> = deals.iter().map(document_id).collect();
+    let reviews: Vec<Document> = selected["reviews"]
+      .iter()
+      .filter(|review| belongs(review, "deal_id", &deal_ids))
+      .cloned()
+      .collect();
+
+    let mut user_ids: HashSet<String> = user.map(str::to_string).into_iter().collect();
+    for document in tasks.iter().chain(&proposals).chain(&deals).chain(&reviews) {
+      for field in ["client_id", "freelancer_id"] {
+        if let Ok(id) = document.get_str(field) {
+          user_ids.insert(id.to_string());
+        }
+      }
+    }
+    for name in ["clients", "freelancers"] {
+      let users = selected[name]
+        .iter()
+        .filter(|user| user_ids.contains(&document_id(user)))
+        .cloned()
+        .collect();
+      selected.insert(name, users);
+    }
+
+    selected.insert("tasks", tasks);
+    selected.insert("proposals", proposals);
+    selected.insert("milestones", milestones);
+    selected.insert("deals", deals);
+    selected.insert("reviews", reviews);
+  }
+
+  let mut counts = Vec::new();
+  for name in COLLECTIONS {
+    write_documents(dir, name, &selected[name])?;
+    counts.push((name, selected[name].len()));
+  }
+  Ok(counts)
+}
+
+/// Loads the dump in `dir` into the configured collections. Collections
+/// without a file in the dump are left alone.
+pub async fn import(db: &DB, dir: &Path, on_conflict: OnConflict) -> Result<Vec<ImportReport>> {
+  let mut dumps = Vec::new();
+  for name in COLLECTIONS {
+    if let Some(documents) = read_documents(dir, name)? {
+      dumps.push((name, documents));
+    }
+  }
+
+  if on_conflict == OnConflict::Fail {
+    for (name, documents) in &dumps {
+      let ids: Vec<Bson> = documents
+        .iter()
+        .filter_map(|document| document.get("_id").cloned())
+        .collect();
+      if let Some(existing) = collection(db, name)
+        .find_one(doc! {"_id": {"$in": ids}}, None)
+        .await
+        .map_err(MongoQueryError)?
+      {
+        return Err(PreconditionFailedError(format!(
+          "{} {} already exists",
+          name,
+          document_id(&existing)
+        )));
+      }
+    }
+  }
+
+  let mut reports = Vec::new();
+  for (name, documents) in dumps {
+    let target = collection(db, name);
+    let mut report = ImportReport {
+      collection: name,
+      ..Default::default()
+    };
+    for document in documents {
+      let e = match target.insert_one(&document, None).await {
+        Ok(_) => {
+          report.inserted += 1;
+          continue;
+        }
+        Err(e) => e,
+      };
+      if !e
+        .to_string()
+        .contains("E11000 duplicate key error collection")
+      {
+        return Err(MongoQueryError(e));
+      }
+      match on_conflict {
+        OnConflict::Fail => return Err(MongoDuplicateError(e)),
+        OnConflict::Skip => report.skipped += 1,
+        OnConflict::Overwrite => {
+          let filter = doc! {"_id": document.get("_id").cloned().unwrap_or(Bson::Null)};
+          let result = target
+            .replace_one(filter, &document, None)
+            .await
+            .map_err(MongoQueryError)?;
+          // The clash was on another unique key, not the `_id`.
+          if result.matched_count == 0 {
+            return Err(MongoDuplicateError(e));
+          }
+          report.replaced += 1;
+        }
+      }
+    }
+    reports.push(report);
+  }
+  Ok(reports)
+}
+
+/// Every id reference, across all collections, pointing at a document that
+/// doesn't exist. Soft-deleted targets still count as existing.
+pub async fn verify(db: &DB) -> Result<Vec<BrokenReference>> {
+  let mut ids: HashMap<&str, HashSet<String>> = HashMap::new();
+  for name in COLLECTIONS {
+    ids.insert(name, find_ids(collection(db, name)).await?);
+  }
+
+  let mut broken = Vec::new();
+  for name in COLLECTIONS {
+    let references: Vec<_> = REFERENCES
+      .iter()
+      .filter(|(from, ..)| *from == name)
+      .collect();
+    for document in find_all(collection(db, name), doc! {}).await? {
+      for (_, field, target) in &references {
+        for missing in referenced_ids(&document, field) {
+          if !ids[target].contains(missing) {
+            broken.push(BrokenReference {
+              collection: name,
+              id: document_id(&document),
+              field,
+              missing: missing.to_string(),
+            });
+          }
+        }
+      }
+    }
+  }
+  Ok(broken)
+}
+
+impl std::fmt::Display for BrokenReference {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(
+      f,
+      "{} {}: {} -> {} does not exist",
+      self.collection, self.id, self.field, self.missing
+    )
+  }
+}
@@ -1,12 +1,20 @@
-use axum::{http::StatusCode, Json};
+use std::any::Any;
+use std::collections::BTreeMap;
+
+use axum::{
+  http::StatusCode,
+  response::{IntoResponse, Response},
+  Json,
+};
 use serde::Serialize;
+use validator::{ValidationErrors, ValidationErrorsKind};
 
 #[derive(thiserror::Error, Debug)]
 pub enum MyError {
   #[error("MongoDB error")]
   MongoError(#[from] mongodb::error::Error),
   #[error("duplicate key error: {0}")]
-  MongoErrorKind(mongodb::error::ErrorKind),
+  MongoErrorKind(Box<mongodb::error::ErrorKind>),
   #[error("duplicate key error: {0}")]
   MongoDuplicateError(mongodb::error::Error),
   #[error("error during mongodb query: {0}")]
@@ -25,6 +33,53 @@ pub enum MyError {
   NotFoundError(String),
   #[error("Auth fail: no auth token cookie")]
   AuthFailNoAuthTokenCookie,
+  #[error("forbidden: {0}")]
+  ForbiddenError(String),
+  #[error("precondition failed: {0}")]
+  PreconditionFailedError(String),
+  #[error("malformed document: {0}")]
+  MalformedDocumentError(String),
+  #[error("empty input: {0}")]
+  EmptyInputError(String),
+  #[error("io error: {0}")]
+  IoError(#[from] std::io::Error),
+  #[error("broken reference: {0}")]
+  BrokenReferenceError(String),
+  #[error("invalid query: {0}")]
+  InvalidQueryError(String),
+  #[error("invalid schedule: {0}")]
+  InvalidScheduleError(String),
+  #[error("invalid amount: {0}")]
+  InvalidAmountError(String),
+  #[error("invalid transition: {0}")]
+  InvalidTransitionError(String),
+  #[error("invalid address: {0}")]
+  InvalidAddressError(String),
+  #[error("conflict: {0}")]
+  ConflictError(String),
+  #[error("invalid input: {0}")]
+  InvalidInputError(ValidationErrors),
+  #[error("invalid upload: {0}")]
+  InvalidUploadError(String),
+  #[error("too large: {0}")]
+  PayloadTooLargeError(String),
+  #[error("unsupported media type: {0}")]
+  UnsupportedMediaTypeError(String),
+  #[error("storage error: {0}")]
+  StorageError(String),
+}
+
+impl MyError {
+  /// Reads fail either because the query failed or because a stored document
+  /// doesn't decode into its model; the latter is reported as malformed.
+  pub fn read_error(e: mongodb::error::Error) -> Self {
+    match *e.kind {
+      mongodb::error::ErrorKind::BsonDeserialization(_) => {
+        MyError::MalformedDocumentError(e.to_string())
+      }
+      _ => MyError::MongoQueryError(e),
+    }
+  }
 }
 
 #[derive(Serialize)]
@@ -33,9 +88,50 @@ struct ErrorResponse {
   message: String,
 }
 
-impl Into<(axum::http::StatusCode, Json<serde_json::Value>)> for MyError {
-  fn into(self) -> (axum::http::StatusCode, Json<serde_json::Value>) {
-    let (status, error_response) = match self {
+#[derive(Serialize)]
+struct InvalidInputResponse {
+  status: &'static str,
+  message: String,
+  errors: BTreeMap<String, Vec<String>>,
+}
+
+/// The messages of every failed rule, keyed by the path of the field they
+/// apply to, such as `title` or `milestones[1].price`. Nested schemas are
+/// all `#[serde(flatten)]`ed into their parent, so their fields are keyed by
+/// their own name, as they are sent.
+fn field_errors(errors: &ValidationErrors) -> BTreeMap<String, Vec<String>> {
+  fn collect(prefix: &str, errors: &ValidationErrors, out: &mut BTreeMap<String, Vec<String>>) {
+    for (field, kind) in errors.errors() {
+      let path = if prefix.is_empty() {
+        field.to_string()
+      } else {
+        format!("{}.{}", prefix, field)
+      };
+      match kind {
+        ValidationErrorsKind::Field(errors) => {
+          let messages = errors.iter().map(|error| match &error.message {
+            Some(message) => message.to_string(),
+            None => format!("failed {}", error.code),
+          });
+          out.entry(path).or_default().extend(messages);
+        }
+        ValidationErrorsKind::Struct(errors) => collect(prefix, errors, out),
+        ValidationErrorsKind::List(items) => {
+          for (i, errors) in items {
+            collect(&format!("{}[{}]", path, i), errors, out);
+          }
+        }
+      }
+    }
+  }
+  let mut out = BTreeMap::new();
+  collect("", errors, &mut out);
+  out
+}
+
+impl From<MyError> for (StatusCode, Json<serde_json::Value>) {
+  fn from(err: MyError) -> (StatusCode, Json<serde_json::Value>) {
+    let (status, error_response) = match err {
       MyError::MongoErrorKind(e) => (
         StatusCode::INTERNAL_SERVER_ERROR,
         ErrorResponse {
@@ -113,13 +209,160 @@ impl Into<(axum::http::StatusCode, Json<serde_json::Value>)> for MyError {
           message: "no auth token cookie".to_string(),
         },
       ),
+      MyError::ForbiddenError(reason) => (
+        StatusCode::FORBIDDEN,
+        ErrorResponse {
+          status: "Fail",
+          message: format!("forbidden: {}", reason),
+        },
+      ),
+      MyError::PreconditionFailedError(reason) => (
+        StatusCode::PRECONDITION_FAILED,
+        ErrorResponse {
+          status: "Fail",
+          message: format!("precondition failed: {}", reason),
+        },
+      ),
+      MyError::MalformedDocumentError(details) => (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        ErrorResponse {
+          status: "Error",
+          message: format!("malformed document: {}", details),
+        },
+      ),
+      MyError::EmptyInputError(what) => (
+        StatusCode::BAD_REQUEST,
+        ErrorResponse {
+          status: "Fail",
+          message: format!("{} must not be empty", what),
+        },
+      ),
+      MyError::IoError(e) => (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        ErrorResponse {
+          status: "Error",
+          message: format!("io error: {}", e),
+        },
+      ),
+      MyError::BrokenReferenceError(reference) => (
+        StatusCode::UNPROCESSABLE_ENTITY,
+        ErrorResponse {
+          status: "Fail",
+          message: format!("broken reference: {}", reference),
+        },
+      ),
+      MyError::InvalidQueryError(reason) => (
+        StatusCode::BAD_REQUEST,
+        ErrorResponse {
+          status: "Fail",
+          message: format!("invalid query: {}", reason),
+        },
+      ),
+      MyError::InvalidScheduleError(reason) => (
+        StatusCode::UNPROCESSABLE_ENTITY,
+        ErrorResponse {
+          status: "Fail",
+          message: format!("invalid schedule: {}", reason),
+        },
+      ),
+      MyError::InvalidAmountError(reason) => (
+        StatusCode::UNPROCESSABLE_ENTITY,
+        ErrorResponse {
+          status: "Fail",
+          message: format!("invalid amount: {}", reason),
+        },
+      ),
+      MyError::InvalidTransitionError(reason) => (
+        StatusCode::CONFLICT,
+        ErrorResponse {
+          status: "Fail",
+          message: format!("invalid transition: {}", reason),
+        },
+      ),
+      MyError::InvalidAddressError(reason) => (
+        StatusCode::UNPROCESSABLE_ENTITY,
+        ErrorResponse {
+          status: "Fail",
+          message: format!("invalid address: {}", reason),
+        },
+      ),
+      MyError::ConflictError(reason) => (
+        StatusCode::CONFLICT,
+        ErrorResponse {
+          status: "Fail",
+          message: format!("conflict: {}", reason),
+        },
+      ),
+      MyError::InvalidUploadError(reason) => (
+        StatusCode::BAD_REQUEST,
+        ErrorResponse {
+          status: "Fail",
+          message: format!("invalid upload: {}", reason),
+        },
+      ),
+      MyError::PayloadTooLargeError(reason) => (
+        StatusCode::PAYLOAD_TOO_LARGE,
+        ErrorResponse {
+          status: "Fail",
+          message: format!("too large: {}", reason),
+        },
+      ),
+      MyError::UnsupportedMediaTypeError(reason) => (
+        StatusCode::UNSUPPORTED_MEDIA_TYPE,
+        ErrorResponse {
+          status: "Fail",
+          message: format!("unsupported media type: {}", reason),
+        },
+      ),
+      MyError::StorageError(reason) => (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        ErrorResponse {
+          status: "Error",
+          message: format!("storage error: {}", reason),
+        },
+      ),
+      // The only error with details per field.
+      MyError::InvalidInputError(errors) => {
+        let errors = field_errors(&errors);
+        let fields: Vec<&str> = errors.keys().map(String::as_str).collect();
+        let error_response = InvalidInputResponse {
+          status: "Fail",
+          message: format!("invalid input: {}", fields.join(", ")),
+          errors,
+        };
+        return (
+          StatusCode::UNPROCESSABLE_ENTITY,
+          Json(serde_json::to_value(error_response).unwrap_or_default()),
+        );
+      }
     };
-    (status, Json(serde_json::to_value(error_response).unwrap()))
+    (
+      status,
+      Json(serde_json::to_value(error_response).unwrap_or_default()),
+    )
   }
 }
 
-impl From<MyError> for (StatusCode, ErrorResponse) {
-  fn from(err: MyError) -> (StatusCode, ErrorResponse) {
-    err.into()
+impl IntoResponse for MyError {
+  fn into_response(self) -> Response {
+    let (status, body): (StatusCode, Json<serde_json::Value>) = self.into();
+    (status, body).into_response()
   }
 }
+
+/// Last resort for a panic escaping a handler: answers 500 instead of
+/// dropping the connection.
+pub fn panic_response(err: Box<dyn Any + Send + 'static>) -> Response {
+  let details = err
+    .downcast_ref::<String>()
+    .cloned()
+    .or_else(|| err.downcast_ref::<&str>().map(|s| s.to_string()))
+    .unwrap_or_default();
+  println!("❌ handler panicked: {}", details);
+
+  let error_response = ErrorResponse {
+    status: "Error",
+    message: "internal server error".to_string(),
+  };
+  (StatusCode::INTERNAL_SERVER_ERROR, Json(error_response)).into_response()
+}
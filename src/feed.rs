@@ -0,0 +1,398 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use axum::async_trait;
+use futures::StreamExt;
+use mongodb::bson::{self, doc, DateTime, Document};
+use mongodb::change_stream::event::{OperationType, ResumeToken};
+use mongodb::error::ErrorKind;
+use mongodb::options::{ChangeStreamOptions, FullDocumentType, UpdateOptions};
+use mongodb::Collection;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+use crate::db::{Result, DB};
+use crate::error::MyError::{
+  self, MalformedDocumentError, MongoQueryError, MongoSerializeBsonError,
+};
+use crate::model::{DealModel, MilestoneModel, ProposalModel};
+use crate::utils::{doc_to_deal_response, doc_to_milestone_response, doc_to_proposal_response};
+use crate::web::ctx::Ctx;
+
+/// Events kept for subscribers that fall behind before they start losing some,
+/// and for those reconnecting with `Last-Event-ID`.
+const FEED_CAPACITY: usize = 1024;
+/// Returned by `watch` on a server that isn't part of a replica set.
+const NOT_A_REPLICA_SET: i32 = 40573;
+/// Pause before reopening a change stream that failed.
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// A write on a watched collection, as pushed to `/api/feed` subscribers.
+#[derive(Clone, Debug, Serialize)]
+pub struct ChangeEvent {
+  pub collection: &'static str,
+  pub operation: &'static str,
+  pub id: String,
+  pub data: Option<serde_json::Value>,
+  /// Users allowed to see the event, admins aside.
+  #[serde(skip)]
+  pub audience: Vec<String>,
+  /// The SSE id, `<run>-<sequence>`, set once published.
+  #[serde(skip)]
+  pub event_id: String,
+}
+
+impl ChangeEvent {
+  pub fn visible_to(&self, ctx: &Ctx) -> bool {
+    ctx.is_admin() || self.audience.iter().any(|user| user == ctx.user_id())
+  }
+}
+
+/// Fans the proposals, milestones and deals change streams out to subscribers,
+/// keeping the last `FEED_CAPACITY` events for those who reconnect.
+#[derive(Clone, Debug)]
+pub struct ChangeFeed {
+  sender: broadcast::Sender<ChangeEvent>,
+  /// Tells this server run's event ids from those of previous ones.
+  run: i64,
+  recent: Arc<Mutex<Recent>>,
+}
+
+#[derive(Debug)]
+struct Recent {
+  events: VecDeque<(u64, ChangeEvent)>,
+  next: u64,
+}
+
+impl ChangeFeed {
+  /// Starts one watcher per collection. Each resumes from the last resume
+  /// token it stored, so events written while the server was down are
+  /// published too, for subscribers to pick up with `Last-Event-ID`.
+  pub fn start(db: &DB) -> Self {
+    let feed = Self::new();
+    tokio::spawn(watch(
+      db.clone(),
+      db.proposals_collection_model.clone(),
+      feed.clone(),
+    ));
+    tokio::spawn(watch(
+      db.clone(),
+      db.milestones_collection_model.clone(),
+      feed.clone(),
+    ));
+    tokio::spawn(watch(
+      db.clone(),
+      db.deals_collection_model.clone(),
+      feed.clone(),
+    ));
+    feed
+  }
+
+  fn new() -> Self {
+    let (sender, _) = broadcast::channel(FEED_CAPACITY);
+    Self {
+      sender,
+      run: DateTime::now().timestamp_millis(),
+      recent: Arc::new(Mutex::new(Recent {
+        events: VecDeque::with_capacity(FEED_CAPACITY),
+        next: 0,
+      })),
+    }
+  }
+
+  fn lock(&self) -> std::sync::MutexGuard<'_, Recent> {
+    self
+      .recent
+      .lock()
+      .unwrap_or_else(|poisoned| poisoned.into_inner())
+  }
+
+  /// Numbers `change`, keeps it and sends it to the current subscribers.
+  fn publish(&self, mut change: ChangeEvent) {
+    let mut recent = self.lock();
+    let sequence = recent.next;
+    recent.next += 1;
+    change.event_id = format!("{}-{}", self.run, sequence);
+    if recent.events.len() == FEED_CAPACITY {
+      recent.events.pop_front();
+    }
+    recent.events.push_back((sequence, change.clone()));
+    // Nobody listening is not an error, the event is simply dropped.
+    let _ = self.sender.send(change);
+  }
+
+  /// The kept events that came after `last_event_id`, all of them if it is
+  /// from a previous run or not one of ours, and the events to come.
+  pub fn subscribe(
+    &self,
+    last_event_id: Option<&str>,
+  ) -> (Vec<ChangeEvent>, broadcast::Receiver<ChangeEvent>) {
+    let recent = self.lock();
+    let receiver = self.sender.subscribe();
+    let missed = match last_event_id {
+      None => Vec::new(),
+      Some(last) => {
+        let after = last
+          .split_once('-')
+          .filter(|(run, _)| run.parse::<i64>() == Ok(self.run))
+          .and_then(|(_, sequence)| sequence.parse::<u64>().ok());
+        recent
+          .events
+          .iter()
+          .filter(|(sequence, _)| after.is_none_or(|after| *sequence > after))
+          .map(|(_, change)| change.clone())
+          .collect()
+      }
+    };
+    (missed, receiver)
+  }
+}
+
+/// A model whose collection is streamed on the feed.
+#[async_trait]
+trait Watched: DeserializeOwned + Unpin + Send + Sync + 'static {
+  const NAME: &'static str;
+
+  fn to_json(&self) -> Result<serde_json::Value>;
+
+  async fn audience(&self, db: &DB) -> Result<Vec<String>>;
+}
+
+#[async_trait]
+impl Watched for ProposalModel {
+  const NAME: &'static str = "proposal";
+
+  fn to_json(&self) -> Result<serde_json::Value> {
+    json(doc_to_proposal_response(self)?)
+  }
+
+  async fn audience(&self, _db: &DB) -> Result<Vec<String>> {
+    Ok(vec![
+      self.client_id.to_owned(),
+      self.freelancer_id.to_owned(),
+    ])
+  }
+}
+
+#[async_trait]
+impl Watched for MilestoneModel {
+  const NAME: &'static str = "milestone";
+
+  fn to_json(&self) -> Result<serde_json::Value> {
+    json(doc_to_milestone_response(self)?)
+  }
+
+  /// Milestones are visible to the parties of their proposal.
+  async fn audience(&self, db: &DB) -> Result<Vec<String>> {
+    let proposal = db
+      .proposals_collection_model
+      .find_one(doc! {"_id": &self.proposal_id}, None)
+      .await
+      .map_err(MongoQueryError)?;
+    Ok(
+      proposal
+        .map(|proposal| vec![proposal.client_id, proposal.freelancer_id])
+        .unwrap_or_default(),
+    )
+  }
+}
+
+#[async_trait]
+impl Watched for DealModel {
+  const NAME: &'static str = "deal";
+
+  fn to_json(&self) -> Result<serde_json::Value> {
+    json(doc_to_deal_response(self)?)
+  }
+
+  async fn audience(&self, _db: &DB) -> Result<Vec<String>> {
+    Ok(vec![
+      self.client_id.to_owned(),
+      self.freelancer_id.to_owned(),
+    ])
+  }
+}
+
+/// A response as pushed to subscribers.
+fn json(response: impl Serialize) -> Result<serde_json::Value> {
+  serde_json::to_value(response).map_err(|e| MalformedDocumentError(e.to_string()))
+}
+
+/// Whether `e` says the server is standalone, where change streams don't exist.
+fn needs_replica_set(e: &MyError) -> bool {
+  match e {
+    MongoQueryError(e) => {
+      matches!(*e.kind, ErrorKind::Command(ref command) if command.code == NOT_A_REPLICA_SET)
+    }
+    _ => false,
+  }
+}
+
+/// Keeps a change stream open on `collection` for the lifetime of the server.
+async fn watch<T: Watched>(db: DB, collection: Collection<T>, feed: ChangeFeed) {
+  loop {
+    match stream_changes(&db, &collection, &feed).await {
+      Err(e) if needs_replica_set(&e) => println!(
+        "❌ change feed on {} needs MongoDB to run as a replica set, see the README",
+        T::NAME
+      ),
+      Err(e) => println!("❌ change feed on {} stopped: {}", T::NAME, e),
+      Ok(()) => {}
+    }
+    tokio::time::sleep(RETRY_DELAY).await;
+  }
+}
+
+async fn stream_changes<T: Watched>(
+  db: &DB,
+  collection: &Collection<T>,
+  feed: &ChangeFeed,
+) -> Result<()> {
+  let resume_after = load_resume_token(db, T::NAME).await?;
+  let resuming = resume_after.is_some();
+  let options = ChangeStreamOptions::builder()
+    .full_document(Some(FullDocumentType::UpdateLookup))
+    .resume_after(resume_after)
+    .build();
+
+  let mut stream = match collection.watch(None, options).await {
+    Ok(stream) => stream,
+    Err(e) if resuming => {
+      // The token most likely fell off the oplog: start over from now.
+      println!(
+        "❌ change feed on {} can't resume, events may have been missed: {}",
+        T::NAME,
+        e
+      );
+      clear_resume_token(db, T::NAME).await?;
+      return Err(MongoQueryError(e));
+    }
+    Err(e) => return Err(MongoQueryError(e)),
+  };
+
+  while let Some(event) = stream.next().await {
+    let event = event.map_err(MongoQueryError)?;
+    let operation = match event.operation_type {
+      OperationType::Insert => "insert",
+      OperationType::Update => "update",
+      OperationType::Replace => "replace",
+      OperationType::Delete => "delete",
+      _ => {
+        save_resume_token(db, T::NAME, &event.id).await?;
+        continue;
+      }
+    };
+    let id = event
+      .document_key
+      .as_ref()
+      .and_then(|key| key.get_str("_id").ok())
+      .unwrap_or_default()
+      .to_string();
+    let (data, audience) = match &event.full_document {
+      Some(document) => match document.to_json() {
+        Ok(data) => (Some(data), document.audience(db).await?),
+        Err(e) => {
+          println!("❌ change feed skipped {} {}: {}", T::NAME, id, e);
+          save_resume_token(db, T::NAME, &event.id).await?;
+          continue;
+        }
+      },
+      None => (None, Vec::new()),
+    };
+
+    feed.publish(ChangeEvent {
+      collection: T::NAME,
+      operation,
+      id,
+      data,
+      audience,
+      event_id: String::new(),
+    });
+    save_resume_token(db, T::NAME, &event.id).await?;
+  }
+  Ok(())
+}
+
+async fn load_resume_token(db: &DB, name: &str) -> Result<Option<ResumeToken>> {
+  let stored = db
+    .resume_tokens_collection
+    .find_one(doc! {"_id": name}, None)
+    .await
+    .map_err(MongoQueryError)?;
+  Ok(
+    stored
+      .and_then(|stored: Document| stored.get("token").cloned())
+      .and_then(|token| bson::from_bson(token).ok()),
+  )
+}
+
+async fn save_resume_token(db: &DB, name: &str, token: &ResumeToken) -> Result<()> {
+  let token = bson::to_bson(token).map_err(MongoSerializeBsonError)?;
+  let options = UpdateOptions::builder().upsert(true).build();
+  db.resume_tokens_collection
+    .update_one(
+      doc! {"_id": name},
+      doc! {"$set": {"token": token, "updated_at": DateTime::now()}},
+      options,
+    )
+    .await
+    .map_err(MongoQueryError)?;
+  Ok(())
+}
+
+async fn clear_resume_token(db: &DB, name: &str) -> Result<()> {
+  db.resume_tokens_collection
+    .delete_one(doc! {"_id": name}, None)
+    .await
+    .map_err(MongoQueryError)?;
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn change(id: &str) -> ChangeEvent {
+    ChangeEvent {
+      collection: "deal",
+      operation: "update",
+      id: id.to_string(),
+      data: None,
+      audience: Vec::new(),
+      event_id: String::new(),
+    }
+  }
+
+  fn ids(changes: &[ChangeEvent]) -> Vec<&str> {
+    changes.iter().map(|change| change.id.as_str()).collect()
+  }
+
+  #[tokio::test]
+  async fn replays_what_came_after_the_last_event_id() {
+    let feed = ChangeFeed::new();
+    feed.publish(change("a"));
+    feed.publish(change("b"));
+    feed.publish(change("c"));
+    let (all, _) = feed.subscribe(None);
+    assert!(all.is_empty());
+    let (kept, _) = feed.subscribe(Some("0-0"));
+    assert_eq!(ids(&kept), ["a", "b", "c"]);
+    let (missed, mut receiver) = feed.subscribe(Some(&kept[0].event_id));
+    assert_eq!(ids(&missed), ["b", "c"]);
+    feed.publish(change("d"));
+    assert_eq!(receiver.recv().await.unwrap().id, "d");
+  }
+
+  #[test]
+  fn keeps_the_last_events_only() {
+    let feed = ChangeFeed::new();
+    for i in 0..FEED_CAPACITY + 2 {
+      feed.publish(change(&i.to_string()));
+    }
+    let (kept, _) = feed.subscribe(Some("garbage"));
+    assert_eq!(kept.len(), FEED_CAPACITY);
+    assert_eq!(kept[0].id, "2");
+  }
+}
@@ -1,20 +1,42 @@
 use std::sync::Arc;
 
 use axum::{
-  extract::{Path, State},
-  http::StatusCode,
-  response::IntoResponse,
+  body::Bytes,
+  extract::{Multipart, Path, Query, State},
+  http::{
+    header::{CONTENT_DISPOSITION, CONTENT_TYPE, ETAG, X_CONTENT_TYPE_OPTIONS},
+    HeaderMap, StatusCode,
+  },
+  response::{
+    sse::{Event, KeepAlive, Sse},
+    IntoResponse,
+  },
   Json,
 };
+use futures::{Stream, StreamExt};
+use tokio::sync::broadcast::error::RecvError;
 use tower_cookies::Cookies;
 
-use crate::response::SingleUserResponse;
+use crate::address::Address;
+use crate::feed::ChangeEvent;
+use crate::health::readiness;
+use crate::job;
+use crate::lifecycle::{DealStatus, InvitationStatus, MilestoneStatus};
+use crate::model::Attachment;
+use crate::response::{CacheStatsListResponse, HealthResponse, SingleUserResponse};
+use crate::web::conditional::{etag, IfMatch};
+use crate::web::ctx::Ctx;
 use crate::web::token::generate_auth_cookie;
+use crate::web::upload::{content_disposition, read_uploads};
+use crate::web::validated::ValidatedJson;
 use crate::{
   error::MyError,
   schema::{
-    CreateClientSchema, CreateFreelancerSchema, CreateMilestoneSchema, CreateProposalSchema,
-    CreateReviewSchema, CreateTaskSchema, CreateUserSchema, LoginUserSchema,
+    task_status_filter, CancelTaskSchema, CreateClientSchema, CreateFreelancerSchema,
+    CreateMilestonesSchema, CreateProposalSchema, CreateReviewSchema, CreateSkillSchema,
+    CreateTaskSchema, DetailOptions, InviteFreelancersSchema, JobListOptions, ListOptions,
+    LoginUserSchema, MergeSkillsSchema, RecommendationOptions, SkillSearchOptions, TaskListOptions,
+    TransitionSchema, UpdateTaskSchema,
   },
   AppState,
 };
@@ -26,32 +48,83 @@ fn when_user_added(
   match result {
     Ok(res) => {
       cookies.add(generate_auth_cookie(res.data.user.id.clone(), None));
-      Ok((StatusCode::CREATED, Json(res)))
+      let etag = etag(res.data.user.version);
+      Ok((StatusCode::CREATED, [(ETAG, etag)], Json(res)))
     }
     Err(e) => Err(e.into()),
   }
 }
 
+/// Only admins may list soft-deleted documents.
+fn include_deleted(ctx: &Ctx, include_deleted: Option<bool>) -> Result<bool, MyError> {
+  match include_deleted {
+    Some(true) if !ctx.is_admin() => Err(MyError::ForbiddenError(
+      "include_deleted is reserved to admins".to_string(),
+    )),
+    Some(include) => Ok(include),
+    None => Ok(false),
+  }
+}
+
+/// Fails unless the caller is an admin, naming what is reserved to them.
+fn admin_only(ctx: &Ctx, reserved: &str) -> Result<(), MyError> {
+  match ctx.is_admin() {
+    true => Ok(()),
+    false => Err(MyError::ForbiddenError(format!(
+      "{} reserved to admins",
+      reserved
+    ))),
+  }
+}
+
+/// A stored file, as a download that browsers won't render in place.
+fn download(attachment: &Attachment, bytes: Bytes) -> impl IntoResponse {
+  (
+    [
+      (CONTENT_TYPE, attachment.content_type.to_owned()),
+      (
+        CONTENT_DISPOSITION,
+        content_disposition(&attachment.file_name),
+      ),
+      (X_CONTENT_TYPE_OPTIONS, "nosniff".to_string()),
+    ],
+    bytes,
+  )
+}
+
+/// Liveness: answers as long as the process serves requests.
+pub async fn healthz_handler() -> impl IntoResponse {
+  Json(HealthResponse { status: "Success" })
+}
+
+/// Readiness: MongoDB reachable, migrations applied and configuration valid.
+pub async fn readyz_handler(State(app_state): State<Arc<AppState>>) -> impl IntoResponse {
+  let res = readiness(&app_state.db).await;
+  let status = match res.status {
+    "Success" => StatusCode::OK,
+    _ => StatusCode::SERVICE_UNAVAILABLE,
+  };
+  (status, Json(res))
+}
+
 pub async fn api_login_handler(
   cookies: Cookies,
   State(app_state): State<Arc<AppState>>,
   Json(body): Json<LoginUserSchema>,
 ) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
-  match app_state
-    .db
-    .api_login(cookies, &body)
-    .await
-    .map_err(MyError::from)
-  {
+  match app_state.db.api_login(cookies, &body).await {
     Ok(res) => Ok((StatusCode::OK, Json(res))),
     Err(e) => Err(e.into()),
   }
 }
 
 pub async fn list_clients_handler(
+  ctx: Ctx,
+  Query(options): Query<ListOptions>,
   State(app_state): State<Arc<AppState>>,
 ) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
-  match app_state.db.fetch_clients().await.map_err(MyError::from) {
+  let include_deleted = include_deleted(&ctx, options.include_deleted)?;
+  match app_state.db.fetch_clients(include_deleted).await {
     Ok(res) => Ok(Json(res)),
     Err(e) => Err(e.into()),
   }
@@ -60,192 +133,887 @@ pub async fn list_clients_handler(
 pub async fn add_client_handler(
   cookies: Cookies,
   State(app_state): State<Arc<AppState>>,
-  Json(body): Json<CreateClientSchema>,
+  ValidatedJson(body): ValidatedJson<CreateClientSchema>,
 ) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
-  when_user_added(
-    app_state.db.add_client(&body).await.map_err(MyError::from),
-    cookies,
-  )
+  when_user_added(app_state.db.add_client(&body).await, cookies)
 }
 
 pub async fn list_tasks_handler(
+  ctx: Ctx,
+  Query(options): Query<TaskListOptions>,
   State(app_state): State<Arc<AppState>>,
 ) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
-  match app_state.db.fetch_tasks().await.map_err(MyError::from) {
+  let include_deleted = include_deleted(&ctx, options.include_deleted)?;
+  let sort = options.sort.as_deref();
+  let status = task_status_filter(options.status.as_deref())?;
+  match app_state
+    .db
+    .fetch_tasks(
+      &ctx,
+      include_deleted,
+      options.skill.as_deref(),
+      status,
+      sort,
+    )
+    .await
+  {
     Ok(res) => Ok(Json(res)),
     Err(e) => Err(e.into()),
   }
 }
 
 pub async fn get_task_handler(
-  Path(skill): Path<String>,
+  ctx: Ctx,
+  Path(task_id): Path<String>,
+  Query(options): Query<DetailOptions>,
   State(app_state): State<Arc<AppState>>,
 ) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
-  match app_state.db.get_task(&skill).await.map_err(MyError::from) {
-    Ok(res) => Ok(Json(res)),
+  let relations = options.relations(&["proposals", "milestones", "deal", "reviews"])?;
+  match app_state
+    .db
+    .get_task_detail(&task_id, &relations, &ctx)
+    .await
+  {
+    Ok(res) => Ok(([(ETAG, etag(res.data.task.task.version))], Json(res))),
     Err(e) => Err(e.into()),
   }
 }
 
-pub async fn get_proposal_handler(
-  Path(proposal_id): Path<String>,
+pub async fn get_deal_handler(
+  ctx: Ctx,
+  Path(deal_id): Path<String>,
+  Query(options): Query<DetailOptions>,
   State(app_state): State<Arc<AppState>>,
 ) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+  let relations = options.relations(&["task", "proposal", "milestones", "reviews"])?;
   match app_state
     .db
-    .get_proposal(&proposal_id)
+    .get_deal_detail(&deal_id, &relations, &ctx)
     .await
-    .map_err(MyError::from)
   {
-    Ok(res) => Ok(Json(res)),
+    Ok(res) => Ok(([(ETAG, etag(res.data.deal.deal.version))], Json(res))),
     Err(e) => Err(e.into()),
   }
 }
-pub async fn create_task_handler(
+
+pub async fn get_proposal_handler(
+  ctx: Ctx,
+  Path(proposal_id): Path<String>,
+  State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+  match app_state.db.get_proposal(&proposal_id, &ctx).await {
+    Ok(res) => Ok((
+      [(ETAG, etag(res.data.detailed_proposal.version))],
+      Json(res),
+    )),
+    Err(e) => Err(e.into()),
+  }
+}
+pub async fn get_freelancer_handler(
+  Path(id): Path<String>,
   State(app_state): State<Arc<AppState>>,
-  Json(body): Json<CreateTaskSchema>,
 ) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
-  match app_state.db.create_task(&body).await.map_err(MyError::from) {
-    Ok(res) => Ok((StatusCode::CREATED, Json(res))),
+  match app_state.db.get_freelancer(&id).await {
+    Ok(res) => Ok((
+      [(ETAG, etag(res.data.freelancer.freelancer.user.version))],
+      Json(res),
+    )),
     Err(e) => Err(e.into()),
   }
 }
 
-pub async fn list_freelancers_handler(
+pub async fn cache_stats_handler(
+  ctx: Ctx,
+  State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+  if !ctx.is_admin() {
+    return Err(
+      MyError::ForbiddenError("cache statistics are reserved to admins".to_string()).into(),
+    );
+  }
+  Ok(Json(CacheStatsListResponse {
+    status: "Success",
+    caches: app_state.db.caches.stats(),
+  }))
+}
+
+/// The open tasks suiting the calling freelancer, best first.
+pub async fn recommended_tasks_handler(
+  ctx: Ctx,
+  Query(options): Query<RecommendationOptions>,
+  State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+  match app_state.db.recommended_tasks(&ctx, options.limit).await {
+    Ok(res) => Ok(Json(res)),
+    Err(e) => Err(e.into()),
+  }
+}
+
+/// Autocompletes skills, or lists them all without `q`.
+pub async fn list_skills_handler(
+  Query(options): Query<SkillSearchOptions>,
   State(app_state): State<Arc<AppState>>,
 ) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
   match app_state
     .db
-    .fetch_freelancers()
+    .search_skills(options.q.as_deref(), options.limit)
     .await
-    .map_err(MyError::from)
   {
     Ok(res) => Ok(Json(res)),
     Err(e) => Err(e.into()),
   }
 }
 
-pub async fn add_freelancer_handler(
-  cookies: Cookies,
+pub async fn create_skill_handler(
+  ctx: Ctx,
   State(app_state): State<Arc<AppState>>,
-  Json(body): Json<CreateFreelancerSchema>,
+  ValidatedJson(body): ValidatedJson<CreateSkillSchema>,
 ) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
-  when_user_added(
-    app_state
-      .db
-      .add_freelancer(&body)
-      .await
-      .map_err(MyError::from),
-    cookies,
-  )
+  admin_only(&ctx, "the skill taxonomy is")?;
+  match app_state.db.create_skill(&body, &ctx).await {
+    Ok(res) => Ok((
+      StatusCode::CREATED,
+      [(ETAG, etag(res.data.skill.version))],
+      Json(res),
+    )),
+    Err(e) => Err(e.into()),
+  }
 }
 
-pub async fn add_review_handler(
+pub async fn merge_skills_handler(
+  ctx: Ctx,
   State(app_state): State<Arc<AppState>>,
-  Json(body): Json<CreateReviewSchema>,
+  ValidatedJson(body): ValidatedJson<MergeSkillsSchema>,
 ) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
-  match app_state.db.add_review(&body).await.map_err(MyError::from) {
-    Ok(res) => Ok((StatusCode::CREATED, Json(res))),
+  admin_only(&ctx, "the skill taxonomy is")?;
+  match app_state.db.merge_skills(&body, &ctx).await {
+    Ok(res) => Ok(Json(res)),
     Err(e) => Err(e.into()),
   }
 }
 
-pub async fn list_proposal_handler(
+pub async fn list_jobs_handler(
+  ctx: Ctx,
+  Query(options): Query<JobListOptions>,
   State(app_state): State<Arc<AppState>>,
 ) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
-  match app_state.db.fetch_proposals().await.map_err(MyError::from) {
+  admin_only(&ctx, "jobs are")?;
+  match job::fetch_jobs(&app_state.db, options.status, options.kind, options.limit).await {
     Ok(res) => Ok(Json(res)),
     Err(e) => Err(e.into()),
   }
 }
 
-pub async fn list_milestone_handler(
+pub async fn retry_job_handler(
+  ctx: Ctx,
+  Path(id): Path<String>,
   State(app_state): State<Arc<AppState>>,
 ) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
-  match app_state.db.fetch_milestones().await.map_err(MyError::from) {
+  admin_only(&ctx, "jobs are")?;
+  match job::retry_job(&app_state.db, &id).await {
     Ok(res) => Ok(Json(res)),
     Err(e) => Err(e.into()),
   }
 }
-pub async fn submit_proposal_handler(
+
+pub async fn cancel_job_handler(
+  ctx: Ctx,
+  Path(id): Path<String>,
+  State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+  admin_only(&ctx, "jobs are")?;
+  match job::cancel_job(&app_state.db, &id).await {
+    Ok(res) => Ok(Json(res)),
+    Err(e) => Err(e.into()),
+  }
+}
+
+pub async fn create_task_handler(
+  ctx: Ctx,
+  State(app_state): State<Arc<AppState>>,
+  ValidatedJson(body): ValidatedJson<CreateTaskSchema>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+  match app_state.db.create_task(&body, &ctx).await {
+    Ok(res) => Ok((
+      StatusCode::CREATED,
+      [(ETAG, etag(res.data.task.version))],
+      Json(res),
+    )),
+    Err(e) => Err(e.into()),
+  }
+}
+
+pub async fn list_freelancers_handler(
+  ctx: Ctx,
+  Query(options): Query<ListOptions>,
   State(app_state): State<Arc<AppState>>,
-  Json(body): Json<CreateProposalSchema>,
 ) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+  let include_deleted = include_deleted(&ctx, options.include_deleted)?;
+  match app_state.db.fetch_freelancers(include_deleted).await {
+    Ok(res) => Ok(Json(res)),
+    Err(e) => Err(e.into()),
+  }
+}
+
+pub async fn add_freelancer_handler(
+  cookies: Cookies,
+  State(app_state): State<Arc<AppState>>,
+  ValidatedJson(body): ValidatedJson<CreateFreelancerSchema>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+  when_user_added(app_state.db.add_freelancer(&body).await, cookies)
+}
+
+pub async fn add_review_handler(
+  ctx: Ctx,
+  State(app_state): State<Arc<AppState>>,
+  ValidatedJson(body): ValidatedJson<CreateReviewSchema>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+  match app_state.db.add_review(&body, &ctx).await {
+    Ok(res) => Ok((
+      StatusCode::CREATED,
+      [(ETAG, etag(res.data.review.version))],
+      Json(res),
+    )),
+    Err(e) => Err(e.into()),
+  }
+}
+
+pub async fn list_proposal_handler(
+  ctx: Ctx,
+  Query(options): Query<ListOptions>,
+  State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+  let include_deleted = include_deleted(&ctx, options.include_deleted)?;
+  let sort = options.sort.as_deref();
   match app_state
     .db
-    .submit_proposal(&body)
+    .fetch_proposals(&ctx, include_deleted, sort)
     .await
-    .map_err(MyError::from)
   {
-    Ok(res) => Ok((StatusCode::CREATED, Json(res))),
+    Ok(res) => Ok(Json(res)),
     Err(e) => Err(e.into()),
   }
 }
 
-pub async fn add_milestones_handler(
+pub async fn list_milestone_handler(
+  ctx: Ctx,
+  Query(options): Query<ListOptions>,
   State(app_state): State<Arc<AppState>>,
-  Json(body): Json<Vec<CreateMilestoneSchema>>,
 ) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+  let include_deleted = include_deleted(&ctx, options.include_deleted)?;
+  let sort = options.sort.as_deref();
   match app_state
     .db
-    .add_milestones(&body)
+    .fetch_milestones(&ctx, include_deleted, sort)
     .await
-    .map_err(MyError::from)
   {
-    Ok(res) => Ok((StatusCode::CREATED, Json(res))),
+    Ok(res) => Ok(Json(res)),
+    Err(e) => Err(e.into()),
+  }
+}
+pub async fn submit_proposal_handler(
+  ctx: Ctx,
+  State(app_state): State<Arc<AppState>>,
+  ValidatedJson(body): ValidatedJson<CreateProposalSchema>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+  match app_state.db.submit_proposal(&body, &ctx).await {
+    Ok(res) => Ok((
+      StatusCode::CREATED,
+      [(ETAG, etag(res.data.proposal.version))],
+      Json(res),
+    )),
+    Err(e) => Err(e.into()),
+  }
+}
+
+pub async fn add_milestones_handler(
+  ctx: Ctx,
+  State(app_state): State<Arc<AppState>>,
+  ValidatedJson(CreateMilestonesSchema(body)): ValidatedJson<CreateMilestonesSchema>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+  match app_state.db.add_milestones(&body, &ctx).await {
+    Ok(res) => Ok((
+      StatusCode::CREATED,
+      [(ETAG, etag(res.data.proposal.version))],
+      Json(res),
+    )),
     Err(e) => Err(e.into()),
   }
 }
 pub async fn approve_proposal_handler(
+  ctx: Ctx,
   Path(proposal_id): Path<String>,
+  IfMatch(expected_version): IfMatch,
   State(app_state): State<Arc<AppState>>,
 ) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
   match app_state
     .db
-    .approve_proposal(&proposal_id)
+    .approve_proposal(&proposal_id, &ctx, expected_version)
     .await
-    .map_err(MyError::from)
   {
-    Ok(res) => Ok((StatusCode::CREATED, Json(res))),
+    Ok(res) => Ok((
+      StatusCode::CREATED,
+      [(ETAG, etag(res.data.proposal.version))],
+      Json(res),
+    )),
     Err(e) => Err(e.into()),
   }
 }
 
 pub async fn list_deals_handler(
+  ctx: Ctx,
+  Query(options): Query<ListOptions>,
   State(app_state): State<Arc<AppState>>,
 ) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
-  match app_state.db.fetch_deals().await.map_err(MyError::from) {
+  let include_deleted = include_deleted(&ctx, options.include_deleted)?;
+  let sort = options.sort.as_deref();
+  match app_state.db.fetch_deals(&ctx, include_deleted, sort).await {
     Ok(res) => Ok(Json(res)),
     Err(e) => Err(e.into()),
   }
 }
 
 pub async fn update_deal_handler(
-  Path((deal_id, proposal_id)): Path<(String, String)>,
+  ctx: Ctx,
+  Path((deal_id, address)): Path<(String, String)>,
+  IfMatch(expected_version): IfMatch,
   State(app_state): State<Arc<AppState>>,
 ) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+  let address = Address::parse(&address)?;
   match app_state
     .db
-    .update_deal(&deal_id, &proposal_id)
+    .update_deal(&deal_id, &address, &ctx, expected_version)
     .await
-    .map_err(MyError::from)
   {
-    Ok(res) => Ok((StatusCode::CREATED, Json(res))),
+    Ok(res) => Ok((
+      StatusCode::CREATED,
+      [(ETAG, etag(res.data.deal.version))],
+      Json(res),
+    )),
     Err(e) => Err(e.into()),
   }
 }
 
 pub async fn submit_milestone_handler(
+  ctx: Ctx,
   Path((proposal_id, milestone_id, link)): Path<(String, String, String)>,
+  IfMatch(expected_version): IfMatch,
+  State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+  match app_state
+    .db
+    .transition_milestone(
+      &proposal_id,
+      &milestone_id,
+      MilestoneStatus::Submitted,
+      Some(&link),
+      &ctx,
+      expected_version,
+    )
+    .await
+  {
+    Ok(res) => Ok((
+      StatusCode::CREATED,
+      [(ETAG, etag(res.data.milestone.version))],
+      Json(res),
+    )),
+    Err(e) => Err(e.into()),
+  }
+}
+
+pub async fn add_deliverables_handler(
+  ctx: Ctx,
+  Path((proposal_id, milestone_id)): Path<(String, String)>,
+  IfMatch(expected_version): IfMatch,
+  State(app_state): State<Arc<AppState>>,
+  multipart: Multipart,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+  let uploads = read_uploads(multipart, &app_state.upload_limits).await?;
+  match app_state
+    .db
+    .add_deliverables(
+      &proposal_id,
+      &milestone_id,
+      uploads,
+      app_state.storage.as_ref(),
+      &ctx,
+      expected_version,
+    )
+    .await
+  {
+    Ok(res) => Ok((
+      StatusCode::CREATED,
+      [(ETAG, etag(res.data.milestone.version))],
+      Json(res),
+    )),
+    Err(e) => Err(e.into()),
+  }
+}
+
+pub async fn download_deliverable_handler(
+  ctx: Ctx,
+  Path((proposal_id, milestone_id, attachment_id)): Path<(String, String, String)>,
   State(app_state): State<Arc<AppState>>,
 ) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
   match app_state
     .db
-    .submit_milestone(&proposal_id, &milestone_id, &link)
+    .deliverable(
+      &proposal_id,
+      &milestone_id,
+      &attachment_id,
+      app_state.storage.as_ref(),
+      &ctx,
+    )
     .await
-    .map_err(MyError::from)
   {
-    Ok(res) => Ok((StatusCode::CREATED, Json(res))),
+    Ok((deliverable, bytes)) => Ok(download(&deliverable, bytes)),
     Err(e) => Err(e.into()),
   }
 }
+
+pub async fn delete_deliverable_handler(
+  ctx: Ctx,
+  Path((proposal_id, milestone_id, attachment_id)): Path<(String, String, String)>,
+  IfMatch(expected_version): IfMatch,
+  State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+  match app_state
+    .db
+    .delete_deliverable(
+      &proposal_id,
+      &milestone_id,
+      &attachment_id,
+      app_state.storage.as_ref(),
+      &ctx,
+      expected_version,
+    )
+    .await
+  {
+    Ok(res) => Ok(([(ETAG, etag(res.data.milestone.version))], Json(res))),
+    Err(e) => Err(e.into()),
+  }
+}
+
+pub async fn transition_milestone_handler(
+  ctx: Ctx,
+  Path((proposal_id, milestone_id)): Path<(String, String)>,
+  IfMatch(expected_version): IfMatch,
+  State(app_state): State<Arc<AppState>>,
+  Json(body): Json<TransitionSchema<MilestoneStatus>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+  match app_state
+    .db
+    .transition_milestone(
+      &proposal_id,
+      &milestone_id,
+      body.status,
+      None,
+      &ctx,
+      expected_version,
+    )
+    .await
+  {
+    Ok(res) => Ok(([(ETAG, etag(res.data.milestone.version))], Json(res))),
+    Err(e) => Err(e.into()),
+  }
+}
+
+pub async fn transition_deal_handler(
+  ctx: Ctx,
+  Path(deal_id): Path<String>,
+  IfMatch(expected_version): IfMatch,
+  State(app_state): State<Arc<AppState>>,
+  Json(body): Json<TransitionSchema<DealStatus>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+  match app_state
+    .db
+    .transition_deal(&deal_id, body.status, &ctx, expected_version)
+    .await
+  {
+    Ok(res) => Ok(([(ETAG, etag(res.data.deal.version))], Json(res))),
+    Err(e) => Err(e.into()),
+  }
+}
+
+pub async fn delete_client_handler(
+  ctx: Ctx,
+  Path(id): Path<String>,
+  State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+  match app_state.db.delete_client(&id, &ctx).await {
+    Ok(res) => Ok(([(ETAG, etag(res.data.user.version))], Json(res))),
+    Err(e) => Err(e.into()),
+  }
+}
+
+pub async fn restore_client_handler(
+  ctx: Ctx,
+  Path(id): Path<String>,
+  IfMatch(expected_version): IfMatch,
+  State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+  match app_state
+    .db
+    .restore_client(&id, &ctx, expected_version)
+    .await
+  {
+    Ok(res) => Ok(([(ETAG, etag(res.data.user.version))], Json(res))),
+    Err(e) => Err(e.into()),
+  }
+}
+
+pub async fn delete_freelancer_handler(
+  ctx: Ctx,
+  Path(id): Path<String>,
+  State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+  match app_state.db.delete_freelancer(&id, &ctx).await {
+    Ok(res) => Ok(([(ETAG, etag(res.data.user.version))], Json(res))),
+    Err(e) => Err(e.into()),
+  }
+}
+
+pub async fn restore_freelancer_handler(
+  ctx: Ctx,
+  Path(id): Path<String>,
+  IfMatch(expected_version): IfMatch,
+  State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+  match app_state
+    .db
+    .restore_freelancer(&id, &ctx, expected_version)
+    .await
+  {
+    Ok(res) => Ok(([(ETAG, etag(res.data.user.version))], Json(res))),
+    Err(e) => Err(e.into()),
+  }
+}
+
+pub async fn delete_task_handler(
+  ctx: Ctx,
+  Path(id): Path<String>,
+  State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+  match app_state.db.delete_task(&id, &ctx).await {
+    Ok(res) => Ok(([(ETAG, etag(res.data.task.version))], Json(res))),
+    Err(e) => Err(e.into()),
+  }
+}
+
+pub async fn restore_task_handler(
+  ctx: Ctx,
+  Path(id): Path<String>,
+  IfMatch(expected_version): IfMatch,
+  State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+  match app_state.db.restore_task(&id, &ctx, expected_version).await {
+    Ok(res) => Ok(([(ETAG, etag(res.data.task.version))], Json(res))),
+    Err(e) => Err(e.into()),
+  }
+}
+
+pub async fn update_task_handler(
+  ctx: Ctx,
+  Path(id): Path<String>,
+  IfMatch(expected_version): IfMatch,
+  State(app_state): State<Arc<AppState>>,
+  ValidatedJson(body): ValidatedJson<UpdateTaskSchema>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+  match app_state
+    .db
+    .update_task(&id, &body, &ctx, expected_version)
+    .await
+  {
+    Ok(res) => Ok(([(ETAG, etag(res.data.task.version))], Json(res))),
+    Err(e) => Err(e.into()),
+  }
+}
+
+pub async fn add_task_attachments_handler(
+  ctx: Ctx,
+  Path(id): Path<String>,
+  IfMatch(expected_version): IfMatch,
+  State(app_state): State<Arc<AppState>>,
+  multipart: Multipart,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+  let uploads = read_uploads(multipart, &app_state.upload_limits).await?;
+  match app_state
+    .db
+    .add_task_attachments(
+      &id,
+      uploads,
+      app_state.storage.as_ref(),
+      &ctx,
+      expected_version,
+    )
+    .await
+  {
+    Ok(res) => Ok((
+      StatusCode::CREATED,
+      [(ETAG, etag(res.data.task.version))],
+      Json(res),
+    )),
+    Err(e) => Err(e.into()),
+  }
+}
+
+pub async fn download_task_attachment_handler(
+  ctx: Ctx,
+  Path((id, attachment_id)): Path<(String, String)>,
+  State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+  match app_state
+    .db
+    .task_attachment(&id, &attachment_id, app_state.storage.as_ref(), &ctx)
+    .await
+  {
+    Ok((attachment, bytes)) => Ok(download(&attachment, bytes)),
+    Err(e) => Err(e.into()),
+  }
+}
+
+pub async fn delete_task_attachment_handler(
+  ctx: Ctx,
+  Path((id, attachment_id)): Path<(String, String)>,
+  IfMatch(expected_version): IfMatch,
+  State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+  match app_state
+    .db
+    .delete_task_attachment(
+      &id,
+      &attachment_id,
+      app_state.storage.as_ref(),
+      &ctx,
+      expected_version,
+    )
+    .await
+  {
+    Ok(res) => Ok(([(ETAG, etag(res.data.task.version))], Json(res))),
+    Err(e) => Err(e.into()),
+  }
+}
+
+pub async fn cancel_task_handler(
+  ctx: Ctx,
+  Path(id): Path<String>,
+  IfMatch(expected_version): IfMatch,
+  State(app_state): State<Arc<AppState>>,
+  ValidatedJson(body): ValidatedJson<CancelTaskSchema>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+  match app_state
+    .db
+    .cancel_task(&id, &body, &ctx, expected_version)
+    .await
+  {
+    Ok(res) => Ok(([(ETAG, etag(res.data.task.version))], Json(res))),
+    Err(e) => Err(e.into()),
+  }
+}
+
+pub async fn invite_freelancers_handler(
+  ctx: Ctx,
+  Path(id): Path<String>,
+  IfMatch(expected_version): IfMatch,
+  State(app_state): State<Arc<AppState>>,
+  ValidatedJson(body): ValidatedJson<InviteFreelancersSchema>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+  match app_state
+    .db
+    .invite_freelancers(&id, &body, &ctx, expected_version)
+    .await
+  {
+    Ok(res) => Ok(([(ETAG, etag(res.data.task.version))], Json(res))),
+    Err(e) => Err(e.into()),
+  }
+}
+
+pub async fn revoke_invitation_handler(
+  ctx: Ctx,
+  Path((id, freelancer_id)): Path<(String, String)>,
+  IfMatch(expected_version): IfMatch,
+  State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+  match app_state
+    .db
+    .revoke_invitation(&id, &freelancer_id, &ctx, expected_version)
+    .await
+  {
+    Ok(res) => Ok(([(ETAG, etag(res.data.task.version))], Json(res))),
+    Err(e) => Err(e.into()),
+  }
+}
+
+pub async fn accept_invitation_handler(
+  ctx: Ctx,
+  Path(id): Path<String>,
+  State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+  answer_invitation(&app_state, &id, InvitationStatus::Accepted, &ctx).await
+}
+
+pub async fn decline_invitation_handler(
+  ctx: Ctx,
+  Path(id): Path<String>,
+  State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+  answer_invitation(&app_state, &id, InvitationStatus::Declined, &ctx).await
+}
+
+async fn answer_invitation(
+  app_state: &AppState,
+  id: &str,
+  to: InvitationStatus,
+  ctx: &Ctx,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+  match app_state.db.answer_invitation(id, to, ctx).await {
+    Ok(res) => Ok(([(ETAG, etag(res.data.task.version))], Json(res))),
+    Err(e) => Err(e.into()),
+  }
+}
+
+pub async fn delete_proposal_handler(
+  ctx: Ctx,
+  Path(id): Path<String>,
+  State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+  match app_state.db.delete_proposal(&id, &ctx).await {
+    Ok(res) => Ok(([(ETAG, etag(res.data.proposal.version))], Json(res))),
+    Err(e) => Err(e.into()),
+  }
+}
+
+pub async fn restore_proposal_handler(
+  ctx: Ctx,
+  Path(id): Path<String>,
+  IfMatch(expected_version): IfMatch,
+  State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+  match app_state
+    .db
+    .restore_proposal(&id, &ctx, expected_version)
+    .await
+  {
+    Ok(res) => Ok(([(ETAG, etag(res.data.proposal.version))], Json(res))),
+    Err(e) => Err(e.into()),
+  }
+}
+
+pub async fn delete_deal_handler(
+  ctx: Ctx,
+  Path(id): Path<String>,
+  State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+  match app_state.db.delete_deal(&id, &ctx).await {
+    Ok(res) => Ok(([(ETAG, etag(res.data.deal.version))], Json(res))),
+    Err(e) => Err(e.into()),
+  }
+}
+
+pub async fn restore_deal_handler(
+  ctx: Ctx,
+  Path(id): Path<String>,
+  IfMatch(expected_version): IfMatch,
+  State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+  match app_state.db.restore_deal(&id, &ctx, expected_version).await {
+    Ok(res) => Ok(([(ETAG, etag(res.data.deal.version))], Json(res))),
+    Err(e) => Err(e.into()),
+  }
+}
+
+pub async fn delete_review_handler(
+  ctx: Ctx,
+  Path(id): Path<String>,
+  State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+  match app_state.db.delete_review(&id, &ctx).await {
+    Ok(res) => Ok(([(ETAG, etag(res.data.review.version))], Json(res))),
+    Err(e) => Err(e.into()),
+  }
+}
+
+pub async fn restore_review_handler(
+  ctx: Ctx,
+  Path(id): Path<String>,
+  IfMatch(expected_version): IfMatch,
+  State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+  match app_state
+    .db
+    .restore_review(&id, &ctx, expected_version)
+    .await
+  {
+    Ok(res) => Ok(([(ETAG, etag(res.data.review.version))], Json(res))),
+    Err(e) => Err(e.into()),
+  }
+}
+
+pub async fn delete_milestone_handler(
+  ctx: Ctx,
+  Path((proposal_id, milestone_id)): Path<(String, String)>,
+  State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+  match app_state
+    .db
+    .delete_milestone(&proposal_id, &milestone_id, &ctx)
+    .await
+  {
+    Ok(res) => Ok(([(ETAG, etag(res.data.milestone.version))], Json(res))),
+    Err(e) => Err(e.into()),
+  }
+}
+
+pub async fn restore_milestone_handler(
+  ctx: Ctx,
+  Path((proposal_id, milestone_id)): Path<(String, String)>,
+  IfMatch(expected_version): IfMatch,
+  State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+  match app_state
+    .db
+    .restore_milestone(&proposal_id, &milestone_id, &ctx, expected_version)
+    .await
+  {
+    Ok(res) => Ok(([(ETAG, etag(res.data.milestone.version))], Json(res))),
+    Err(e) => Err(e.into()),
+  }
+}
+
+/// Server-sent events for every proposal, milestone and deal change the
+/// caller is a party to, starting with those missed since `Last-Event-ID`.
+pub async fn change_feed_handler(
+  ctx: Ctx,
+  headers: HeaderMap,
+  State(app_state): State<Arc<AppState>>,
+) -> Sse<impl Stream<Item = Result<Event, serde_json::Error>>> {
+  let last_event_id = headers
+    .get("last-event-id")
+    .and_then(|value| value.to_str().ok());
+  let (missed, receiver) = app_state.feed.subscribe(last_event_id);
+  let missed: Vec<_> = missed
+    .into_iter()
+    .filter(|change| change.visible_to(&ctx))
+    .map(|change| feed_event(&change))
+    .collect();
+  let live = futures::stream::unfold((receiver, ctx), |(mut receiver, ctx)| async move {
+    loop {
+      match receiver.recv().await {
+        Ok(change) if change.visible_to(&ctx) => {
+          return Some((feed_event(&change), (receiver, ctx)));
+        }
+        Ok(_) | Err(RecvError::Lagged(_)) => continue,
+        Err(RecvError::Closed) => return None,
+      }
+    }
+  });
+  let stream = futures::stream::iter(missed).chain(live);
+  Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+fn feed_event(change: &ChangeEvent) -> Result<Event, serde_json::Error> {
+  Event::default()
+    .id(&change.event_id)
+    .event(change.collection)
+    .json_data(change)
+}
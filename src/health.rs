@@ -0,0 +1,108 @@
+use std::future::Future;
+use std::time::Instant;
+
+use crate::db::DB;
+use crate::migration::pending_migrations;
+use crate::response::{DependencyStatus, ReadinessChecks, ReadinessResponse};
+
+/// Env vars naming the collections, which must all differ.
+const COLLECTION_VARS: [&str; 11] = [
+  "MONGODB_CLIENTS_COLLECTION",
+  "MONGODB_TASKS_COLLECTION",
+  "MONGODB_FREELANCERS_COLLECTION",
+  "MONGODB_REVIEW_COLLECTION",
+  "MONGODB_PROPOSALS_COLLECTION",
+  "MONGODB_DEALS_COLLECTION",
+  "MONGODB_MILESTONES_COLLECTION",
+  "MONGODB_SKILLS_COLLECTION",
+  "MONGODB_JOBS_COLLECTION",
+  "MONGODB_RESUME_TOKENS_COLLECTION",
+  "MONGODB_MIGRATIONS_COLLECTION",
+];
+
+pub async fn readiness(db: &DB) -> ReadinessResponse {
+  let mongodb = timed(db.ping()).await;
+  let migrations = timed(async {
+    let pending = pending_migrations(db).await.map_err(|e| e.to_string())?;
+    match pending.is_empty() {
+      true => Ok(()),
+      false => Err(format!(
+        "pending: {}",
+        pending
+          .iter()
+          .map(|migration| migration.name)
+          .collect::<Vec<_>>()
+          .join(", ")
+      )),
+    }
+  })
+  .await;
+  let configuration = timed(async {
+    match configuration_errors() {
+      errors if errors.is_empty() => Ok(()),
+      errors => Err(errors.join("; ")),
+    }
+  })
+  .await;
+
+  let ready = [&mongodb, &migrations, &configuration]
+    .iter()
+    .all(|check| check.status == "up");
+  ReadinessResponse {
+    status: if ready { "Success" } else { "Fail" },
+    checks: ReadinessChecks {
+      mongodb,
+      migrations,
+      configuration,
+    },
+  }
+}
+
+async fn timed<E: ToString>(
+  check: impl Future<Output = std::result::Result<(), E>>,
+) -> DependencyStatus {
+  let start = Instant::now();
+  let result = check.await;
+  let latency_ms = start.elapsed().as_millis();
+  match result {
+    Ok(()) => DependencyStatus {
+      status: "up",
+      latency_ms,
+      error: None,
+    },
+    Err(e) => DependencyStatus {
+      status: "down",
+      latency_ms,
+      error: Some(e.to_string()),
+    },
+  }
+}
+
+fn configuration_errors() -> Vec<String> {
+  let mut errors = Vec::new();
+
+  if std::env::var("AUTH_AUDIENCE")
+    .unwrap_or_default()
+    .is_empty()
+  {
+    errors.push("AUTH_AUDIENCE is not set, every token will be rejected".to_string());
+  }
+
+  let mut names: Vec<(&str, String)> = Vec::new();
+  for var in COLLECTION_VARS {
+    match std::env::var(var) {
+      Ok(name) if name.trim().is_empty() => errors.push(format!("{} is empty", var)),
+      Ok(name) => {
+        if let Some((other, _)) = names.iter().find(|(_, other_name)| *other_name == name) {
+          errors.push(format!(
+            "{} and {} both name collection {}",
+            other, var, name
+          ));
+        }
+        names.push((var, name));
+      }
+      Err(_) => errors.push(format!("{} is not set", var)),
+    }
+  }
+  errors
+}
@@ -0,0 +1,476 @@
+use std::time::Duration;
+
+use futures::StreamExt;
+use mongodb::bson::{doc, to_bson, Bson, DateTime, Document};
+use mongodb::options::{FindOneAndUpdateOptions, FindOptions, ReturnDocument, UpdateOptions};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::db::{Result, DB};
+use crate::error::MyError::{self, ConflictError, MongoQueryError, NotFoundError};
+use crate::model::JobModel;
+use crate::response::{JobData, JobListResponse, SingleJobResponse};
+use crate::utils::doc_to_job_response;
+
+/// How often due jobs are looked for by default.
+const DEFAULT_POLL_SECS: u64 = 5;
+
+/// How long a claimed job is reserved to the instance running it. Past it,
+/// another instance takes the job over.
+const LEASE_SECS: i64 = 300;
+
+/// How often the instance running a job extends its lease, well within
+/// `LEASE_SECS` so a slow run keeps it.
+const LEASE_RENEWAL_SECS: u64 = 60;
+
+/// Runs of a job, the first included, before it is marked `Failed`.
+const MAX_ATTEMPTS: i32 = 5;
+
+/// The delay before the first retry, doubled at every further one up to
+/// `MAX_BACKOFF_SECS`.
+const BASE_BACKOFF_SECS: i64 = 30;
+const MAX_BACKOFF_SECS: i64 = 3600;
+
+/// At most this many jobs are listed at once.
+const MAX_JOB_RESULTS: i64 = 200;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobKind {
+  /// Expires open tasks past their deadline.
+  ExpireTasks,
+  /// Flags milestones due soon to their parties.
+  RemindMilestones,
+}
+
+impl JobKind {
+  pub const ALL: &'static [Self] = &[JobKind::ExpireTasks, JobKind::RemindMilestones];
+
+  /// The `_id` of its recurring job.
+  fn id(self) -> &'static str {
+    match self {
+      JobKind::ExpireTasks => "expire-tasks",
+      JobKind::RemindMilestones => "remind-milestones",
+    }
+  }
+
+  /// Seconds between runs of its recurring job, 0 when disabled.
+  fn every_secs(self) -> u64 {
+    match self {
+      JobKind::ExpireTasks => env_secs("TASK_EXPIRY_INTERVAL_SECS", 60),
+      JobKind::RemindMilestones => env_secs("MILESTONE_REMINDER_INTERVAL_SECS", 3600),
+    }
+  }
+
+  /// Does the work, describing what was done.
+  async fn run(self, db: &DB) -> Result<String> {
+    match self {
+      JobKind::ExpireTasks => Ok(format!("{} task(s) expired", db.expire_tasks().await?)),
+      JobKind::RemindMilestones => Ok(format!(
+        "{} milestone(s) reminded",
+        db.remind_milestones().await?
+      )),
+    }
+  }
+}
+
+/// Pending → Running → Succeeded, or back to Pending for a retry or the next
+/// run of a recurring job. A one-off job out of attempts is Failed for good;
+/// a recurring one waits for its next run. Admins cancel pending or failed
+/// jobs and retry failed or cancelled ones.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobStatus {
+  Pending,
+  Running,
+  Succeeded,
+  Failed,
+  Cancelled,
+}
+
+fn env_secs(var: &str, default: u64) -> u64 {
+  std::env::var(var)
+    .ok()
+    .and_then(|secs| secs.parse().ok())
+    .unwrap_or(default)
+}
+
+fn after(at: DateTime, secs: i64) -> DateTime {
+  DateTime::from_millis(at.timestamp_millis() + secs * 1000)
+}
+
+/// The delay before retrying a job that failed its `attempts`-th run.
+fn backoff_secs(attempts: i32) -> i64 {
+  let doublings = attempts.clamp(1, 16) - 1;
+  (BASE_BACKOFF_SECS << doublings).min(MAX_BACKOFF_SECS)
+}
+
+fn status(status: JobStatus) -> Result<Bson> {
+  Ok(to_bson(&status)?)
+}
+
+/// Schedules the recurring jobs, then runs due jobs every
+/// `JOB_POLL_INTERVAL_SECS` (5 by default). Every instance may run it: a
+/// lease keeps a job to the instance that claimed it.
+pub fn start(db: &DB) {
+  let owner = format!("{:016x}", rand::thread_rng().gen::<u64>());
+  let poll = Duration::from_secs(env_secs("JOB_POLL_INTERVAL_SECS", DEFAULT_POLL_SECS).max(1));
+  tokio::spawn(run(db.clone(), owner, poll));
+}
+
+async fn run(db: DB, owner: String, poll: Duration) {
+  let mut scheduled = false;
+  let mut interval = tokio::time::interval(poll);
+  loop {
+    interval.tick().await;
+    if !scheduled {
+      match schedule_recurring(&db).await {
+        Ok(()) => scheduled = true,
+        Err(e) => {
+          println!("❌ Scheduling recurring jobs failed: {}", e);
+          continue;
+        }
+      }
+    }
+    loop {
+      match claim(&db, &owner).await {
+        Ok(Some(job)) => {
+          if let Err(e) = execute(&db, &owner, job).await {
+            println!("❌ Recording a job run failed: {}", e);
+          }
+        }
+        Ok(None) => break,
+        Err(e) => {
+          println!("❌ Claiming jobs failed: {}", e);
+          break;
+        }
+      }
+    }
+  }
+}
+
+/// Creates the recurring jobs that don't exist yet, keeping their interval
+/// in line with the configuration, and cancels the disabled ones, or has
+/// `execute` cancel them once their run ends. A pending job whose interval
+/// changed, a failed one, and one cancelled for being disabled are scheduled
+/// again from their last run; one an admin cancelled stays so.
+async fn schedule_recurring(db: &DB) -> Result<()> {
+  let now = DateTime::now();
+  for kind in JobKind::ALL {
+    let every = kind.every_secs() as i64;
+    if every == 0 {
+      db.jobs_collection
+        .update_one(
+          doc! {"_id": kind.id(), "status": status(JobStatus::Pending)?},
+          doc! {"$set": {
+            "status": status(JobStatus::Cancelled)?,
+            "disabled": true,
+            "finished_at": now,
+            "updated_at": now,
+          }},
+          None,
+        )
+        .await
+        .map_err(MongoQueryError)?;
+      db.jobs_collection
+        .update_one(
+          doc! {"_id": kind.id(), "status": status(JobStatus::Running)?},
+          doc! {"$set": {"disabled": true, "updated_at": now}},
+          None,
+        )
+        .await
+        .map_err(MongoQueryError)?;
+      continue;
+    }
+    let next_run = doc! {"$cond": [
+      {"$eq": [{"$ifNull": ["$last_run_at", null]}, null]},
+      now,
+      {"$max": [now, {"$add": ["$last_run_at", every * 1000]}]},
+    ]};
+    // A running job is left to `execute`, which schedules its next run.
+    db.jobs_collection
+      .update_one(
+        doc! {
+          "_id": kind.id(),
+          "$or": [
+            {"status": status(JobStatus::Pending)?, "every_secs": {"$ne": every}},
+            {"status": status(JobStatus::Failed)?},
+            {"status": status(JobStatus::Cancelled)?, "disabled": true},
+          ],
+        },
+        vec![doc! {"$set": {
+          "status": status(JobStatus::Pending)?,
+          "run_at": next_run,
+          "every_secs": every,
+          "attempts": 0,
+          "disabled": false,
+          "finished_at": null,
+          "updated_at": now,
+        }}],
+        None,
+      )
+      .await
+      .map_err(MongoQueryError)?;
+    db.jobs_collection
+      .update_one(
+        doc! {"_id": kind.id()},
+        doc! {
+          "$setOnInsert": {
+            "kind": to_bson(kind)?,
+            "status": status(JobStatus::Pending)?,
+            "run_at": now,
+            "attempts": 0,
+            "max_attempts": MAX_ATTEMPTS,
+            "created_at": now,
+          },
+          "$set": {"every_secs": every, "disabled": false},
+        },
+        UpdateOptions::builder().upsert(true).build(),
+      )
+      .await
+      .map_err(MongoQueryError)?;
+  }
+  Ok(())
+}
+
+/// Leases the job due first to `owner`: a pending one whose time has come,
+/// or a running one whose instance let its lease run out.
+async fn claim(db: &DB, owner: &str) -> Result<Option<JobModel>> {
+  let now = DateTime::now();
+  let filter = doc! {"$or": [
+    {"status": status(JobStatus::Pending)?, "run_at": {"$lte": now}},
+    {"status": status(JobStatus::Running)?, "lease_until": {"$lt": now}},
+  ]};
+  let update = doc! {
+    "$set": {
+      "status": status(JobStatus::Running)?,
+      "lease_owner": owner,
+      "lease_until": after(now, LEASE_SECS),
+      "updated_at": now,
+    },
+    "$inc": {"attempts": 1},
+  };
+  let options = FindOneAndUpdateOptions::builder()
+    .sort(doc! {"run_at": 1})
+    .return_document(ReturnDocument::After)
+    .build();
+  db.jobs_collection_model
+    .find_one_and_update(filter, update, options)
+    .await
+    .map_err(MyError::read_error)
+}
+
+/// Runs a claimed job, renewing its lease meanwhile, and records the outcome
+/// unless the lease was taken over anyway.
+async fn execute(db: &DB, owner: &str, job: JobModel) -> Result<()> {
+  let renewal = tokio::spawn(renew_lease(db.clone(), job.id.clone(), owner.to_string()));
+  let result = job.kind.run(db).await;
+  renewal.abort();
+  // The job as it is now: it may have been disabled or given another
+  // interval meanwhile, or taken over.
+  let running = doc! {"_id": &job.id, "status": status(JobStatus::Running)?, "lease_owner": owner};
+  let Some(job) = db
+    .jobs_collection_model
+    .find_one(running.clone(), None)
+    .await
+    .map_err(MyError::read_error)?
+  else {
+    return Ok(());
+  };
+  let now = DateTime::now();
+  let mut set = doc! {
+    "last_run_at": now,
+    "lease_owner": Bson::Null,
+    "lease_until": Bson::Null,
+    "updated_at": now,
+  };
+  let succeeded = result.is_ok();
+  let retried = match result {
+    Ok(summary) => {
+      set.insert("last_result", summary);
+      set.insert("last_error", Bson::Null);
+      false
+    }
+    Err(e) => {
+      println!(
+        "❌ Job {} failed (attempt {} of {}): {}",
+        job.id, job.attempts, job.max_attempts, e
+      );
+      set.insert("last_error", e.to_string());
+      job.attempts < job.max_attempts && !job.disabled
+    }
+  };
+  match job.every_secs {
+    _ if retried => {
+      set.insert("status", status(JobStatus::Pending)?);
+      set.insert("run_at", after(now, backoff_secs(job.attempts)));
+    }
+    Some(_) if job.disabled => {
+      set.insert("status", status(JobStatus::Cancelled)?);
+      set.insert("finished_at", now);
+    }
+    // Out of retries, a recurring job gives up on this run only.
+    Some(every) => {
+      set.insert("status", status(JobStatus::Pending)?);
+      set.insert("run_at", after(now, every));
+      set.insert("attempts", 0);
+    }
+    None if succeeded => {
+      set.insert("status", status(JobStatus::Succeeded)?);
+      set.insert("finished_at", now);
+    }
+    None => {
+      set.insert("status", status(JobStatus::Failed)?);
+      set.insert("finished_at", now);
+    }
+  }
+  db.jobs_collection
+    .update_one(running, doc! {"$set": set}, None)
+    .await
+    .map_err(MongoQueryError)?;
+  Ok(())
+}
+
+/// Extends the lease of job `id` every `LEASE_RENEWAL_SECS` for as long as
+/// `owner` holds it.
+async fn renew_lease(db: DB, id: String, owner: String) {
+  let mut interval = tokio::time::interval(Duration::from_secs(LEASE_RENEWAL_SECS));
+  interval.tick().await;
+  loop {
+    interval.tick().await;
+    let renewed = db
+      .jobs_collection
+      .update_one(
+        doc! {"_id": &id, "lease_owner": &owner},
+        doc! {"$set": {"lease_until": after(DateTime::now(), LEASE_SECS)}},
+        None,
+      )
+      .await;
+    match renewed {
+      Ok(renewed) if renewed.matched_count == 0 => return,
+      Ok(_) => {}
+      Err(e) => println!("❌ Renewing the lease of job {} failed: {}", id, e),
+    }
+  }
+}
+
+/// The jobs in `status` and of `kind`, if given, due first first.
+pub async fn fetch_jobs(
+  db: &DB,
+  job_status: Option<JobStatus>,
+  kind: Option<JobKind>,
+  limit: Option<i64>,
+) -> Result<JobListResponse> {
+  let mut filter = Document::new();
+  if let Some(job_status) = job_status {
+    filter.insert("status", status(job_status)?);
+  }
+  if let Some(kind) = kind {
+    filter.insert("kind", to_bson(&kind)?);
+  }
+  let options = FindOptions::builder()
+    .sort(doc! {"run_at": 1, "_id": 1})
+    .limit(limit.unwrap_or(MAX_JOB_RESULTS).clamp(1, MAX_JOB_RESULTS))
+    .build();
+  let mut cursor = db
+    .jobs_collection_model
+    .find(filter, options)
+    .await
+    .map_err(MongoQueryError)?;
+
+  let mut jobs = Vec::new();
+  while let Some(job) = cursor.next().await {
+    jobs.push(doc_to_job_response(&job.map_err(MyError::read_error)?));
+  }
+  Ok(JobListResponse {
+    status: "Success",
+    results: jobs.len(),
+    jobs,
+  })
+}
+
+/// Schedules a failed or cancelled job to run right away, with its attempts
+/// reset.
+pub async fn retry_job(db: &DB, id: &str) -> Result<SingleJobResponse> {
+  let now = DateTime::now();
+  let update = doc! {"$set": {
+    "status": status(JobStatus::Pending)?,
+    "run_at": now,
+    "attempts": 0,
+    "finished_at": Bson::Null,
+    "updated_at": now,
+  }};
+  move_job(
+    db,
+    id,
+    "retried",
+    &[JobStatus::Failed, JobStatus::Cancelled],
+    update,
+  )
+  .await
+}
+
+/// Stops a pending or failed job from running again.
+pub async fn cancel_job(db: &DB, id: &str) -> Result<SingleJobResponse> {
+  let now = DateTime::now();
+  let update = doc! {"$set": {
+    "status": status(JobStatus::Cancelled)?,
+    "finished_at": now,
+    "updated_at": now,
+  }};
+  move_job(
+    db,
+    id,
+    "cancelled",
+    &[JobStatus::Pending, JobStatus::Failed],
+    update,
+  )
+  .await
+}
+
+/// Applies `update` to job `id` if it is in one of `from`, or says why it
+/// can't be `action`.
+async fn move_job(
+  db: &DB,
+  id: &str,
+  action: &str,
+  from: &[JobStatus],
+  update: Document,
+) -> Result<SingleJobResponse> {
+  let statuses = from
+    .iter()
+    .map(|s| status(*s))
+    .collect::<Result<Vec<_>>>()?;
+  let options = FindOneAndUpdateOptions::builder()
+    .return_document(ReturnDocument::After)
+    .build();
+  if let Some(job) = db
+    .jobs_collection_model
+    .find_one_and_update(
+      doc! {"_id": id, "status": {"$in": statuses}},
+      update,
+      options,
+    )
+    .await
+    .map_err(MyError::read_error)?
+  {
+    return Ok(SingleJobResponse {
+      status: "Success",
+      data: JobData {
+        job: doc_to_job_response(&job),
+      },
+    });
+  }
+  let job = db
+    .jobs_collection_model
+    .find_one(doc! {"_id": id}, None)
+    .await
+    .map_err(MyError::read_error)?
+    .ok_or_else(|| NotFoundError(id.to_string()))?;
+  let allowed: Vec<String> = from.iter().map(|s| format!("{:?}", s)).collect();
+  Err(ConflictError(format!(
+    "job {} is {:?}, only {} jobs can be {}",
+    id,
+    job.status,
+    allowed.join(" or "),
+    action
+  )))
+}
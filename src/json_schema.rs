@@ -0,0 +1,444 @@
+use futures::StreamExt;
+use mongodb::bson::{self, doc, Bson, Document};
+use mongodb::error::ErrorKind;
+use mongodb::Collection;
+use serde::de::DeserializeOwned;
+
+use crate::db::{Result, DB};
+use crate::error::MyError::*;
+use crate::lifecycle::{DealStatus, InvitationStatus, Lifecycle, MilestoneStatus, TaskStatus};
+use crate::model::{
+  ClientModel, DealModel, FreelancerModel, MilestoneModel, ProposalModel, ReviewModel, SkillModel,
+  TaskModel, TaskVisibility,
+};
+use crate::money::Token;
+
+const NAMESPACE_NOT_FOUND: i32 = 26;
+
+/// The `$jsonSchema` validator of the collection a model is stored in. It
+/// mirrors the model's serde representation field by field: `Option` fields
+/// may be missing or null, everything else is required.
+pub trait CollectionSchema {
+  fn json_schema() -> Document;
+}
+
+/// A stored document its collection's validator would reject.
+#[derive(Debug)]
+pub struct InvalidDocument {
+  pub collection: String,
+  pub id: String,
+  pub reason: String,
+}
+
+fn typed(bson_type: &str) -> Document {
+  doc! {"bsonType": bson_type}
+}
+
+fn nullable(bson_type: &str) -> Document {
+  doc! {"bsonType": [bson_type, "null"]}
+}
+
+fn string_array() -> Document {
+  doc! {"bsonType": ["array", "null"], "items": typed("string")}
+}
+
+fn unsigned_16() -> Document {
+  doc! {"bsonType": ["int", "long"], "minimum": 0, "maximum": u16::MAX as i32}
+}
+
+fn money() -> Document {
+  let tokens: Vec<&str> = Token::ALL.iter().map(|token| token.symbol()).collect();
+  doc! {
+    "bsonType": "object",
+    "required": ["amount", "token"],
+    "properties": {
+      "amount": {"bsonType": "string", "pattern": "^[0-9]+(\\.[0-9]+)?$"},
+      "token": {"enum": tokens},
+    },
+  }
+}
+
+fn status<S: Lifecycle>() -> Document {
+  let statuses: Vec<Bson> = S::ALL
+    .iter()
+    .filter_map(|s| bson::to_bson(s).ok())
+    .collect();
+  doc! {"enum": statuses}
+}
+
+fn history<S: Lifecycle>() -> Document {
+  doc! {
+    "bsonType": "array",
+    "items": {
+      "bsonType": "object",
+      "required": ["from", "to", "at", "by"],
+      "properties": {
+        "from": status::<S>(),
+        "to": status::<S>(),
+        "at": typed("date"),
+        "by": typed("string"),
+      },
+    },
+  }
+}
+
+fn attachments() -> Document {
+  doc! {
+    "bsonType": "array",
+    "items": {
+      "bsonType": "object",
+      "required": ["id", "file_name", "content_type", "size", "key", "uploaded_by", "uploaded_at"],
+      "properties": {
+        "id": typed("string"),
+        "file_name": typed("string"),
+        "content_type": typed("string"),
+        "size": {"bsonType": ["int", "long"], "minimum": 0},
+        "key": typed("string"),
+        "uploaded_by": typed("string"),
+        "uploaded_at": typed("date"),
+      },
+    },
+  }
+}
+
+fn object(required: &[&str], mut properties: Document) -> Document {
+  properties.insert("version", doc! {"bsonType": ["int", "long"]});
+  properties.insert("deleted_at", nullable("date"));
+  properties.insert("deleted_by", nullable("string"));
+  properties.insert("created_at", nullable("date"));
+  properties.insert("updated_at", nullable("date"));
+  properties.insert("created_by", nullable("string"));
+  doc! {"bsonType": "object", "required": required, "properties": properties}
+}
+
+fn user_properties() -> Document {
+  doc! {
+    "_id": typed("string"),
+    "user_name": typed("string"),
+    "description": nullable("string"),
+    "password": typed("string"),
+  }
+}
+
+const USER_REQUIRED: [&str; 3] = ["_id", "user_name", "password"];
+
+impl CollectionSchema for ClientModel {
+  fn json_schema() -> Document {
+    let mut properties = user_properties();
+    properties.insert("tasks_ids", string_array());
+    object(&USER_REQUIRED, properties)
+  }
+}
+
+impl CollectionSchema for FreelancerModel {
+  fn json_schema() -> Document {
+    let mut properties = user_properties();
+    properties.insert("skills", string_array());
+    object(&USER_REQUIRED, properties)
+  }
+}
+
+impl CollectionSchema for TaskModel {
+  fn json_schema() -> Document {
+    let visibilities: Vec<Bson> = TaskVisibility::ALL
+      .iter()
+      .filter_map(|visibility| bson::to_bson(visibility).ok())
+      .collect();
+    object(
+      &[
+        "_id",
+        "client_id",
+        "title",
+        "start_time",
+        "deadline",
+        "description",
+        "skills",
+        "bounty",
+        "status",
+        "visibility",
+      ],
+      doc! {
+        "_id": typed("string"),
+        "client_id": typed("string"),
+        "title": typed("string"),
+        "start_time": typed("date"),
+        "deadline": typed("date"),
+        "description": typed("string"),
+        "skills": {"bsonType": "array", "items": typed("string")},
+        "bounty": money(),
+        "proposals_id": string_array(),
+        "cancelled_at": nullable("date"),
+        "cancelled_by": nullable("string"),
+        "cancel_reason": nullable("string"),
+        "edits": {
+          "bsonType": "array",
+          "items": {
+            "bsonType": "object",
+            "required": ["at", "by", "before", "after"],
+            "properties": {
+              "at": typed("date"),
+              "by": typed("string"),
+              "before": typed("object"),
+              "after": typed("object"),
+            },
+          },
+        },
+        "status": status::<TaskStatus>(),
+        "history": history::<TaskStatus>(),
+        "attachments": attachments(),
+        "visibility": {"enum": visibilities},
+        "invitations": {
+          "bsonType": "array",
+          "items": {
+            "bsonType": "object",
+            "required": ["freelancer_id", "status", "invited_by", "invited_at"],
+            "properties": {
+              "freelancer_id": typed("string"),
+              "status": status::<InvitationStatus>(),
+              "invited_by": typed("string"),
+              "invited_at": typed("date"),
+              "answered_at": nullable("date"),
+            },
+          },
+        },
+      },
+    )
+  }
+}
+
+impl CollectionSchema for ProposalModel {
+  fn json_schema() -> Document {
+    object(
+      &[
+        "_id",
+        "client_id",
+        "task_id",
+        "freelancer_id",
+        "proposal_price",
+        "accepted",
+      ],
+      doc! {
+        "_id": typed("string"),
+        "client_id": typed("string"),
+        "task_id": typed("string"),
+        "freelancer_id": typed("string"),
+        "milestones_id": string_array(),
+        "proposal_price": money(),
+        "accepted": typed("bool"),
+        "closed_at": nullable("date"),
+        "closed_reason": nullable("string"),
+      },
+    )
+  }
+}
+
+impl CollectionSchema for MilestoneModel {
+  fn json_schema() -> Document {
+    object(
+      &[
+        "_id",
+        "proposal_id",
+        "description",
+        "deadline",
+        "price",
+        "link",
+        "status",
+      ],
+      doc! {
+        "_id": typed("string"),
+        "proposal_id": typed("string"),
+        "description": typed("string"),
+        "deadline": typed("date"),
+        "price": money(),
+        "link": typed("string"),
+        "status": status::<MilestoneStatus>(),
+        "history": history::<MilestoneStatus>(),
+        "reminded_at": nullable("date"),
+        "deliverables": attachments(),
+      },
+    )
+  }
+}
+
+impl CollectionSchema for DealModel {
+  fn json_schema() -> Document {
+    object(
+      &[
+        "_id",
+        "task_id",
+        "proposal_id",
+        "freelancer_id",
+        "client_id",
+        "price",
+        "status",
+      ],
+      doc! {
+        "_id": typed("string"),
+        "task_id": typed("string"),
+        "proposal_id": typed("string"),
+        "freelancer_id": typed("string"),
+        "client_id": typed("string"),
+        "price": money(),
+        "status": status::<DealStatus>(),
+        "address": nullable("string"),
+        "history": history::<DealStatus>(),
+      },
+    )
+  }
+}
+
+impl CollectionSchema for ReviewModel {
+  fn json_schema() -> Document {
+    object(
+      &[
+        "_id",
+        "freelancer_id",
+        "client_id",
+        "deal_id",
+        "review",
+        "stars",
+      ],
+      doc! {
+        "_id": typed("string"),
+        "freelancer_id": typed("string"),
+        "client_id": typed("string"),
+        "deal_id": typed("string"),
+        "review": typed("string"),
+        "stars": unsigned_16(),
+      },
+    )
+  }
+}
+
+impl CollectionSchema for SkillModel {
+  fn json_schema() -> Document {
+    object(
+      &["_id", "name", "aliases", "keys"],
+      doc! {
+        "_id": typed("string"),
+        "name": typed("string"),
+        "category": nullable("string"),
+        "aliases": {"bsonType": "array", "items": typed("string")},
+        "keys": {"bsonType": "array", "minItems": 1, "items": typed("string")},
+        "merges": {
+          "bsonType": "array",
+          "items": {
+            "bsonType": "object",
+            "required": ["skills", "by", "at"],
+            "properties": {
+              "skills": {"bsonType": "array", "items": typed("string")},
+              "by": typed("string"),
+              "at": typed("date"),
+            },
+          },
+        },
+      },
+    )
+  }
+}
+
+/// Sets `T`'s validator on `collection`, creating the collection if needed.
+/// Documents already stored are left alone: with the `moderate` level only
+/// inserts and updates of valid documents are checked.
+async fn install<T: CollectionSchema>(db: &DB, collection: &Collection<Document>) -> Result<()> {
+  let validator = doc! {"$jsonSchema": T::json_schema()};
+  let modify = doc! {
+    "collMod": collection.name(),
+    "validator": &validator,
+    "validationLevel": "moderate",
+    "validationAction": "error",
+  };
+  match db.database.run_command(modify, None).await {
+    Ok(_) => Ok(()),
+    Err(e) => match *e.kind {
+      ErrorKind::Command(ref command) if command.code == NAMESPACE_NOT_FOUND => {
+        let create = doc! {
+          "create": collection.name(),
+          "validator": validator,
+          "validationLevel": "moderate",
+          "validationAction": "error",
+        };
+        db.database
+          .run_command(create, None)
+          .await
+          .map_err(MongoQueryError)?;
+        Ok(())
+      }
+      _ => Err(MongoQueryError(e)),
+    },
+  }
+}
+
+/// Removes the validator of `collection`, if it exists, so documents can be
+/// rewritten into a shape the installed validator still rejects.
+pub async fn drop_validator(db: &DB, collection: &Collection<Document>) -> Result<()> {
+  let modify = doc! {"collMod": collection.name(), "validator": {}};
+  match db.database.run_command(modify, None).await {
+    Ok(_) => Ok(()),
+    Err(e) => match *e.kind {
+      ErrorKind::Command(ref command) if command.code == NAMESPACE_NOT_FOUND => Ok(()),
+      _ => Err(MongoQueryError(e)),
+    },
+  }
+}
+
+/// Installs the validator of every application collection.
+pub async fn install_validators(db: &DB) -> Result<()> {
+  install::<ClientModel>(db, &db.client_collection).await?;
+  install::<FreelancerModel>(db, &db.freelancer_collection).await?;
+  install::<TaskModel>(db, &db.tasks_collection).await?;
+  install::<ProposalModel>(db, &db.proposals_collection).await?;
+  install::<MilestoneModel>(db, &db.milestones_collection).await?;
+  install::<DealModel>(db, &db.deals_collection).await?;
+  install::<ReviewModel>(db, &db.review_collection).await?;
+  install::<SkillModel>(db, &db.skills_collection).await?;
+  Ok(())
+}
+
+/// The documents of `collection` failing `T`'s validator. The server only
+/// says whether a document matches, so the reason comes from decoding it
+/// into `T` when that fails too.
+async fn scan<T: CollectionSchema + DeserializeOwned>(
+  collection: &Collection<Document>,
+  invalid: &mut Vec<InvalidDocument>,
+) -> Result<()> {
+  let filter = doc! {"$nor": [{"$jsonSchema": T::json_schema()}]};
+  let mut cursor = collection
+    .find(filter, None)
+    .await
+    .map_err(MongoQueryError)?;
+
+  while let Some(document) = cursor.next().await {
+    let document = document.map_err(MongoQueryError)?;
+    let id = match document.get("_id") {
+      Some(Bson::String(id)) => id.to_owned(),
+      Some(id) => id.to_string(),
+      None => String::new(),
+    };
+    let reason = match bson::from_document::<T>(document) {
+      Ok(_) => "does not match the collection schema".to_string(),
+      Err(e) => e.to_string(),
+    };
+    invalid.push(InvalidDocument {
+      collection: collection.name().to_string(),
+      id,
+      reason,
+    });
+  }
+  Ok(())
+}
+
+/// Every stored document, soft-deleted ones included, that its collection's
+/// validator would reject.
+pub async fn invalid_documents(db: &DB) -> Result<Vec<InvalidDocument>> {
+  let mut invalid = Vec::new();
+  scan::<ClientModel>(&db.client_collection, &mut invalid).await?;
+  scan::<FreelancerModel>(&db.freelancer_collection, &mut invalid).await?;
+  scan::<TaskModel>(&db.tasks_collection, &mut invalid).await?;
+  scan::<ProposalModel>(&db.proposals_collection, &mut invalid).await?;
+  scan::<MilestoneModel>(&db.milestones_collection, &mut invalid).await?;
+  scan::<DealModel>(&db.deals_collection, &mut invalid).await?;
+  scan::<ReviewModel>(&db.review_collection, &mut invalid).await?;
+  scan::<SkillModel>(&db.skills_collection, &mut invalid).await?;
+  Ok(invalid)
+}
@@ -0,0 +1,36 @@
+pub mod address;
+pub mod cache;
+pub mod db;
+pub mod dump;
+pub mod error;
+pub mod feed;
+pub mod handler;
+pub mod health;
+pub mod job;
+pub mod json_schema;
+pub mod lifecycle;
+pub mod migration;
+pub mod model;
+pub mod money;
+pub mod recommend;
+pub mod response;
+pub mod schema;
+pub mod seed;
+pub mod skill;
+pub mod storage;
+pub mod utils;
+pub mod web;
+
+use std::sync::Arc;
+
+use db::DB;
+use feed::ChangeFeed;
+use storage::Storage;
+use web::upload::UploadLimits;
+
+pub struct AppState {
+  pub db: DB,
+  pub feed: ChangeFeed,
+  pub storage: Arc<dyn Storage>,
+  pub upload_limits: UploadLimits,
+}
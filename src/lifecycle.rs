@@ -0,0 +1,313 @@
+use std::fmt;
+
+use mongodb::bson::DateTime;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::error::MyError::{self, ForbiddenError, InvalidTransitionError};
+
+/// Which side of a deal may move a document into a status. Admins may make
+/// any allowed move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Party {
+  Client,
+  Freelancer,
+  Either,
+}
+
+/// A status with a fixed set of moves out of each value.
+pub trait Lifecycle:
+  Copy + Eq + fmt::Debug + Serialize + DeserializeOwned + Send + Sync + 'static
+{
+  const ALL: &'static [Self];
+
+  /// The statuses reachable in one move from `self`.
+  fn next(self) -> &'static [Self];
+
+  /// Who may move a document into `self`.
+  fn moved_by(self) -> Party;
+}
+
+/// Checks `to` is one move away from `from`, whoever makes it.
+pub fn check_move<S: Lifecycle>(from: S, to: S) -> Result<(), MyError> {
+  if !from.next().contains(&to) {
+    let allowed: Vec<String> = from.next().iter().map(|s| format!("{:?}", s)).collect();
+    return Err(InvalidTransitionError(format!(
+      "{:?} can't move to {:?}, only to [{}]",
+      from,
+      to,
+      allowed.join(", ")
+    )));
+  }
+  Ok(())
+}
+
+/// Checks the move from `from` to `to` is allowed and made by the right party,
+/// `client` and `freelancer` telling whether the caller is either.
+pub fn transition<S: Lifecycle>(
+  from: S,
+  to: S,
+  client: bool,
+  freelancer: bool,
+  admin: bool,
+) -> Result<S, MyError> {
+  check_move(from, to)?;
+  let permitted = admin
+    || match to.moved_by() {
+      Party::Client => client,
+      Party::Freelancer => freelancer,
+      Party::Either => client || freelancer,
+    };
+  if !permitted {
+    let party = match to.moved_by() {
+      Party::Client => "the client",
+      Party::Freelancer => "the freelancer",
+      Party::Either => "the client or the freelancer",
+    };
+    return Err(ForbiddenError(format!(
+      "only {} can move to {:?}",
+      party, to
+    )));
+  }
+  Ok(to)
+}
+
+/// Open → InProgress once a proposal is approved → Completed or Cancelled
+/// with its deal. An open task can be cancelled, and expires past its
+/// deadline until its deadline is moved later.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TaskStatus {
+  #[default]
+  Open,
+  InProgress,
+  Completed,
+  Cancelled,
+  Expired,
+}
+
+impl Lifecycle for TaskStatus {
+  const ALL: &'static [Self] = &[
+    TaskStatus::Open,
+    TaskStatus::InProgress,
+    TaskStatus::Completed,
+    TaskStatus::Cancelled,
+    TaskStatus::Expired,
+  ];
+
+  fn next(self) -> &'static [Self] {
+    use TaskStatus::*;
+    match self {
+      Open => &[InProgress, Cancelled, Expired],
+      InProgress => &[Completed, Cancelled],
+      Expired => &[Open, Cancelled],
+      Completed | Cancelled => &[],
+    }
+  }
+
+  fn moved_by(self) -> Party {
+    match self {
+      TaskStatus::Cancelled => Party::Either,
+      _ => Party::Client,
+    }
+  }
+}
+
+/// Initialized → Submitted → Approved → Paid. A client can send a submitted
+/// milestone back to Initialized for changes.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MilestoneStatus {
+  Initialized,
+  Submitted,
+  Approved,
+  Paid,
+}
+
+impl Lifecycle for MilestoneStatus {
+  const ALL: &'static [Self] = &[
+    MilestoneStatus::Initialized,
+    MilestoneStatus::Submitted,
+    MilestoneStatus::Approved,
+    MilestoneStatus::Paid,
+  ];
+
+  fn next(self) -> &'static [Self] {
+    use MilestoneStatus::*;
+    match self {
+      Initialized => &[Submitted],
+      Submitted => &[Approved, Initialized],
+      Approved => &[Paid],
+      Paid => &[],
+    }
+  }
+
+  fn moved_by(self) -> Party {
+    match self {
+      MilestoneStatus::Submitted => Party::Freelancer,
+      _ => Party::Client,
+    }
+  }
+}
+
+/// Initialized → Funded → Completed, Cancelled or Disputed. An unfunded deal
+/// can be cancelled, and a dispute settles as Completed or Cancelled.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DealStatus {
+  Initialized,
+  Funded,
+  Completed,
+  Cancelled,
+  Disputed,
+}
+
+impl Lifecycle for DealStatus {
+  const ALL: &'static [Self] = &[
+    DealStatus::Initialized,
+    DealStatus::Funded,
+    DealStatus::Completed,
+    DealStatus::Cancelled,
+    DealStatus::Disputed,
+  ];
+
+  fn next(self) -> &'static [Self] {
+    use DealStatus::*;
+    match self {
+      Initialized => &[Funded, Cancelled],
+      Funded => &[Completed, Cancelled, Disputed],
+      Disputed => &[Completed, Cancelled],
+      Completed | Cancelled => &[],
+    }
+  }
+
+  fn moved_by(self) -> Party {
+    match self {
+      DealStatus::Funded | DealStatus::Completed => Party::Client,
+      DealStatus::Cancelled | DealStatus::Disputed => Party::Either,
+      DealStatus::Initialized => Party::Client,
+    }
+  }
+}
+
+/// Pending → Accepted or Declined by the invited freelancer. The client may
+/// invite a freelancer who declined again.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InvitationStatus {
+  Pending,
+  Accepted,
+  Declined,
+}
+
+impl Lifecycle for InvitationStatus {
+  const ALL: &'static [Self] = &[
+    InvitationStatus::Pending,
+    InvitationStatus::Accepted,
+    InvitationStatus::Declined,
+  ];
+
+  fn next(self) -> &'static [Self] {
+    use InvitationStatus::*;
+    match self {
+      Pending => &[Accepted, Declined],
+      Declined => &[Pending],
+      Accepted => &[],
+    }
+  }
+
+  fn moved_by(self) -> Party {
+    match self {
+      InvitationStatus::Pending => Party::Client,
+      _ => Party::Freelancer,
+    }
+  }
+}
+
+/// One entry of a document's `history`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct StatusChange<S> {
+  pub from: S,
+  pub to: S,
+  pub at: DateTime,
+  pub by: String,
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Every status of `S` with no move out of it.
+  fn terminal<S: Lifecycle>() -> Vec<S> {
+    S::ALL
+      .iter()
+      .copied()
+      .filter(|s| s.next().is_empty())
+      .collect()
+  }
+
+  /// Checks no one, admins included, moves a terminal status of `S`.
+  fn check_terminal_stays<S: Lifecycle>(expected: &[S]) {
+    assert_eq!(terminal::<S>(), expected);
+    for &from in expected {
+      for &to in S::ALL {
+        let error = transition(from, to, true, true, true).unwrap_err();
+        assert!(
+          matches!(error, InvalidTransitionError(_)),
+          "{:?} -> {:?}",
+          from,
+          to
+        );
+      }
+    }
+  }
+
+  #[test]
+  fn terminal_statuses_move_nowhere() {
+    check_terminal_stays(&[TaskStatus::Completed, TaskStatus::Cancelled]);
+    check_terminal_stays(&[MilestoneStatus::Paid]);
+    check_terminal_stays(&[DealStatus::Completed, DealStatus::Cancelled]);
+    check_terminal_stays(&[InvitationStatus::Accepted]);
+  }
+
+  #[test]
+  fn allowed_moves_go_through() {
+    use TaskStatus::*;
+    assert_eq!(
+      transition(Open, InProgress, true, false, false).unwrap(),
+      InProgress
+    );
+    assert_eq!(transition(Expired, Open, true, false, false).unwrap(), Open);
+    assert_eq!(
+      transition(DealStatus::Funded, DealStatus::Disputed, false, true, false).unwrap(),
+      DealStatus::Disputed
+    );
+    assert_eq!(
+      transition(
+        MilestoneStatus::Submitted,
+        MilestoneStatus::Initialized,
+        true,
+        false,
+        false
+      )
+      .unwrap(),
+      MilestoneStatus::Initialized
+    );
+  }
+
+  #[test]
+  fn moves_skipping_a_step_are_invalid() {
+    let error = check_move(TaskStatus::Open, TaskStatus::Completed).unwrap_err();
+    assert!(matches!(error, InvalidTransitionError(_)));
+    let error = check_move(MilestoneStatus::Initialized, MilestoneStatus::Paid).unwrap_err();
+    assert!(matches!(error, InvalidTransitionError(_)));
+  }
+
+  #[test]
+  fn only_the_right_party_moves() {
+    use MilestoneStatus::*;
+    let error = transition(Initialized, Submitted, true, false, false).unwrap_err();
+    assert!(matches!(error, ForbiddenError(_)));
+    assert!(transition(Initialized, Submitted, false, true, false).is_ok());
+    assert!(transition(Initialized, Submitted, false, false, true).is_ok());
+    let error = transition(Submitted, Approved, false, true, false).unwrap_err();
+    assert!(matches!(error, ForbiddenError(_)));
+    assert!(transition(TaskStatus::Open, TaskStatus::Cancelled, false, true, false).is_ok());
+  }
+}
@@ -1,41 +1,53 @@
-mod db;
-mod error;
-mod handler;
-mod model;
-mod response;
-mod schema;
-mod utils;
-mod web;
-
 use std::{net::SocketAddr, sync::Arc};
 
 use axum::http::{
-  header::{ACCEPT, AUTHORIZATION, CONTENT_TYPE},
+  header::{ACCEPT, AUTHORIZATION, CONTENT_TYPE, ETAG, IF_MATCH},
   HeaderValue, Method,
 };
-use db::DB;
 use dotenv::dotenv;
-use error::MyError;
+use tower_http::catch_panic::CatchPanicLayer;
 use tower_http::cors::CorsLayer;
-use web::route::create_router;
-
-pub struct AppState {
-  db: DB,
-}
+use vayamai_axum_mongodb::db::DB;
+use vayamai_axum_mongodb::error::{panic_response, MyError};
+use vayamai_axum_mongodb::feed::ChangeFeed;
+use vayamai_axum_mongodb::web::route::create_router;
+use vayamai_axum_mongodb::web::upload::UploadLimits;
+use vayamai_axum_mongodb::AppState;
+use vayamai_axum_mongodb::{job, json_schema, migration, storage};
 
 #[tokio::main]
 async fn main() -> Result<(), MyError> {
   dotenv().ok();
 
   let db = DB::init().await?;
+  if let Err(e) = migration::run_migrations(&db).await {
+    println!("❌ Migrations failed: {}", e);
+  }
+  if let Err(e) = json_schema::install_validators(&db).await {
+    println!("❌ Installing collection validators failed: {}", e);
+  }
+  if let Err(e) = db.create_indexes().await {
+    println!("❌ Creating indexes failed: {}", e);
+  }
+  let feed = ChangeFeed::start(&db);
+  job::start(&db);
+  let storage = storage::from_env()?;
 
   let cors = CorsLayer::new()
     .allow_origin("http://localhost:3000".parse::<HeaderValue>().unwrap())
     .allow_methods([Method::GET, Method::POST, Method::PATCH, Method::DELETE])
     .allow_credentials(true)
-    .allow_headers([AUTHORIZATION, ACCEPT, CONTENT_TYPE]);
-
-  let app = create_router(Arc::new(AppState { db: db.clone() })).layer(cors);
+    .allow_headers([AUTHORIZATION, ACCEPT, CONTENT_TYPE, IF_MATCH])
+    .expose_headers([ETAG]);
+
+  let app = create_router(Arc::new(AppState {
+    db: db.clone(),
+    feed,
+    storage,
+    upload_limits: UploadLimits::from_env(),
+  }))
+  .layer(cors)
+  .layer(CatchPanicLayer::custom(panic_response));
 
   let addr = SocketAddr::from(([0, 0, 0, 0], 8080));
   println!("✅ Server listening on {addr}\n");
@@ -0,0 +1,411 @@
+use std::future::Future;
+use std::pin::Pin;
+
+use futures::StreamExt;
+use mongodb::bson::{doc, Bson, DateTime, Document};
+use mongodb::Collection;
+
+use crate::address::Address;
+use crate::db::{Result, DB};
+use crate::error::MyError::{MalformedDocumentError, MongoQueryError};
+use crate::json_schema::drop_validator;
+use crate::lifecycle::{DealStatus, TaskStatus};
+use crate::model::TaskVisibility;
+use crate::money::{Money, Token};
+use crate::utils::{parse_datetime, to_document};
+
+type MigrationFuture<'a> = Pin<Box<dyn Future<Output = Result<()>> + Send + 'a>>;
+
+/// A one-off data change, applied once per database and recorded by `name`
+/// in the migrations collection.
+pub struct Migration {
+  pub name: &'static str,
+  pub run: for<'a> fn(&'a DB) -> MigrationFuture<'a>,
+}
+
+/// Every migration, in the order they are applied. Never reorder or rename.
+pub const MIGRATIONS: &[Migration] = &[
+  Migration {
+    name: "0001_back_references",
+    run: repair_back_references,
+  },
+  Migration {
+    name: "0002_datetime_schedules",
+    run: datetime_schedules,
+  },
+  Migration {
+    name: "0003_money_amounts",
+    run: money_amounts,
+  },
+  Migration {
+    name: "0004_addresses",
+    run: addresses,
+  },
+  Migration {
+    name: "0005_task_status",
+    run: task_status,
+  },
+  Migration {
+    name: "0006_task_visibility",
+    run: task_visibility,
+  },
+];
+
+/// The token amounts stored as plain numbers were meant in.
+const LEGACY_TOKEN: Token = Token::Usdc;
+
+/// Clients were created with `tasks_id` instead of `tasks_ids`, and neither
+/// list was maintained before.
+fn repair_back_references(db: &DB) -> MigrationFuture<'_> {
+  Box::pin(async move {
+    db.repair_back_references().await?;
+    Ok(())
+  })
+}
+
+/// Task `start_time`/`deadline` and milestone `deadline` were stored as the
+/// strings clients sent.
+fn datetime_schedules(db: &DB) -> MigrationFuture<'_> {
+  Box::pin(async move {
+    convert_datetimes(db, &db.tasks_collection, &["start_time", "deadline"]).await?;
+    convert_datetimes(db, &db.milestones_collection, &["deadline"]).await
+  })
+}
+
+/// Rewrites the string values of `fields` as BSON datetimes. Documents with
+/// a value `parse_datetime` can't read are left as they are and reported.
+async fn convert_datetimes(
+  db: &DB,
+  collection: &Collection<Document>,
+  fields: &[&str],
+) -> Result<()> {
+  // The validator in place may still want strings; the current one is
+  // installed again once migrations have run.
+  drop_validator(db, collection).await?;
+
+  let string_fields: Vec<Document> = fields
+    .iter()
+    .map(|field| doc! {*field: {"$type": "string"}})
+    .collect();
+  let mut cursor = collection
+    .find(doc! {"$or": string_fields}, None)
+    .await
+    .map_err(MongoQueryError)?;
+
+  let mut unparseable = Vec::new();
+  while let Some(document) = cursor.next().await {
+    let document = document.map_err(MongoQueryError)?;
+    let id = document.get("_id").cloned().unwrap_or(Bson::Null);
+    let mut set = Document::new();
+    for field in fields {
+      if let Ok(value) = document.get_str(field) {
+        match parse_datetime(value) {
+          Some(datetime) => {
+            set.insert(*field, DateTime::from_chrono(datetime));
+          }
+          None => unparseable.push(format!("{} ({} {:?})", id, field, value)),
+        }
+      }
+    }
+    if !set.is_empty() {
+      collection
+        .update_one(doc! {"_id": id}, doc! {"$set": set}, None)
+        .await
+        .map_err(MongoQueryError)?;
+    }
+  }
+
+  if unparseable.is_empty() {
+    Ok(())
+  } else {
+    Err(MalformedDocumentError(format!(
+      "unparseable dates in {}: {}",
+      collection.name(),
+      unparseable.join(", ")
+    )))
+  }
+}
+
+/// Bounties were `u16`s and prices `f64`s, with no token.
+fn money_amounts(db: &DB) -> MigrationFuture<'_> {
+  Box::pin(async move {
+    convert_amounts(db, &db.tasks_collection, "bounty").await?;
+    convert_amounts(db, &db.proposals_collection, "proposal_price").await?;
+    convert_amounts(db, &db.milestones_collection, "price").await?;
+    convert_amounts(db, &db.deals_collection, "price").await
+  })
+}
+
+/// Rewrites the numbers in `field` as `LEGACY_TOKEN` amounts, doubles rounded
+/// to the token's decimals. Negative amounts are left as they are and reported.
+async fn convert_amounts(db: &DB, collection: &Collection<Document>, field: &str) -> Result<()> {
+  drop_validator(db, collection).await?;
+
+  let mut cursor = collection
+    .find(doc! {field: {"$type": "number"}}, None)
+    .await
+    .map_err(MongoQueryError)?;
+
+  let mut invalid = Vec::new();
+  while let Some(document) = cursor.next().await {
+    let document = document.map_err(MongoQueryError)?;
+    let id = document.get("_id").cloned().unwrap_or(Bson::Null);
+    let amount = match document.get(field) {
+      Some(Bson::Int32(amount)) => amount.to_string(),
+      Some(Bson::Int64(amount)) => amount.to_string(),
+      Some(Bson::Double(amount)) => {
+        format!("{:.*}", LEGACY_TOKEN.decimals() as usize, amount)
+      }
+      _ => continue,
+    };
+    match Money::parse(&amount, LEGACY_TOKEN) {
+      Ok(money) => {
+        collection
+          .update_one(
+            doc! {"_id": id},
+            doc! {"$set": {field: to_document(&money)?}},
+            None,
+          )
+          .await
+          .map_err(MongoQueryError)?;
+      }
+      Err(_) => invalid.push(format!("{} ({} {})", id, field, amount)),
+    }
+  }
+
+  if invalid.is_empty() {
+    Ok(())
+  } else {
+    Err(MalformedDocumentError(format!(
+      "invalid amounts in {}: {}",
+      collection.name(),
+      invalid.join(", ")
+    )))
+  }
+}
+
+/// User ids and the references to them were stored in whatever case they
+/// were sent, and deals were created with `0x0` as their address.
+fn addresses(db: &DB) -> MigrationFuture<'_> {
+  Box::pin(async move {
+    let mut skipped = Vec::new();
+    rename_users(&db.client_collection, &mut skipped).await?;
+    rename_users(&db.freelancer_collection, &mut skipped).await?;
+    let references = [
+      (&db.tasks_collection, "client_id"),
+      (&db.proposals_collection, "client_id"),
+      (&db.proposals_collection, "freelancer_id"),
+      (&db.deals_collection, "client_id"),
+      (&db.deals_collection, "freelancer_id"),
+      (&db.review_collection, "client_id"),
+      (&db.review_collection, "freelancer_id"),
+      (&db.deals_collection, "address"),
+    ];
+    for (collection, field) in references {
+      normalize_field(db, collection, field, &mut skipped).await?;
+    }
+    db.deals_collection
+      .update_many(
+        doc! {"address": "0x0"},
+        doc! {"$set": {"address": Bson::Null}},
+        None,
+      )
+      .await
+      .map_err(MongoQueryError)?;
+
+    // Ids that aren't addresses still work, they are only looked up as is.
+    if !skipped.is_empty() {
+      println!(
+        "⚠️  Not addresses, left as they are: {}",
+        skipped.join(", ")
+      );
+    }
+    Ok(())
+  })
+}
+
+/// Moves users whose `_id` is an address with uppercase letters to its
+/// lowercase form.
+async fn rename_users(collection: &Collection<Document>, skipped: &mut Vec<String>) -> Result<()> {
+  let mut cursor = collection.find(None, None).await.map_err(MongoQueryError)?;
+
+  let mut renames = Vec::new();
+  while let Some(document) = cursor.next().await {
+    let document = document.map_err(MongoQueryError)?;
+    let Ok(id) = document.get_str("_id") else {
+      continue;
+    };
+    match Address::parse(id) {
+      Ok(address) if address.as_str() != id => renames.push((id.to_string(), address, document)),
+      Ok(_) => {}
+      Err(_) => skipped.push(format!("{} _id {}", collection.name(), id)),
+    }
+  }
+
+  for (id, address, mut document) in renames {
+    document.insert("_id", address.as_str());
+    match collection.insert_one(&document, None).await {
+      Ok(_) => {
+        collection
+          .delete_one(doc! {"_id": &id}, None)
+          .await
+          .map_err(MongoQueryError)?;
+      }
+      // Another user already has the lowercase id.
+      Err(e) if e.to_string().contains("E11000") => skipped.push(format!(
+        "{} _id {} (taken by {})",
+        collection.name(),
+        id,
+        address
+      )),
+      Err(e) => return Err(MongoQueryError(e)),
+    }
+  }
+  Ok(())
+}
+
+/// Lowercases the addresses in `field`, skipping `0x0`.
+async fn normalize_field(
+  db: &DB,
+  collection: &Collection<Document>,
+  field: &str,
+  skipped: &mut Vec<String>,
+) -> Result<()> {
+  drop_validator(db, collection).await?;
+
+  let mut cursor = collection
+    .find(doc! {field: {"$type": "string", "$ne": "0x0"}}, None)
+    .await
+    .map_err(MongoQueryError)?;
+
+  while let Some(document) = cursor.next().await {
+    let document = document.map_err(MongoQueryError)?;
+    let (Some(id), Ok(value)) = (document.get("_id"), document.get_str(field)) else {
+      continue;
+    };
+    match Address::parse(value) {
+      Ok(address) if address.as_str() != value => {
+        collection
+          .update_one(
+            doc! {"_id": id},
+            doc! {"$set": {field: address.as_str()}},
+            None,
+          )
+          .await
+          .map_err(MongoQueryError)?;
+      }
+      Ok(_) => {}
+      Err(_) => skipped.push(format!("{} {} {} {}", collection.name(), id, field, value)),
+    }
+  }
+  Ok(())
+}
+
+/// Tasks had no `status`: it is derived from their cancellation, their
+/// accepted proposal's deal and their deadline.
+fn task_status(db: &DB) -> MigrationFuture<'_> {
+  Box::pin(async move {
+    drop_validator(db, &db.tasks_collection).await?;
+    let mut cursor = db
+      .tasks_collection
+      .find(doc! {"status": {"$exists": false}}, None)
+      .await
+      .map_err(MongoQueryError)?;
+
+    let now = DateTime::now();
+    while let Some(task) = cursor.next().await {
+      let task = task.map_err(MongoQueryError)?;
+      let Some(id) = task.get("_id").cloned() else {
+        continue;
+      };
+      let deal = db
+        .deals_collection_model
+        .find_one(doc! {"task_id": &id, "deleted_at": null}, None)
+        .await
+        .map_err(MongoQueryError)?;
+      let status = if task.get_datetime("cancelled_at").is_ok() {
+        TaskStatus::Cancelled
+      } else if let Some(deal) = deal {
+        match deal.status {
+          DealStatus::Completed => TaskStatus::Completed,
+          DealStatus::Cancelled => TaskStatus::Cancelled,
+          _ => TaskStatus::InProgress,
+        }
+      } else if task
+        .get_datetime("deadline")
+        .is_ok_and(|deadline| *deadline < now)
+      {
+        TaskStatus::Expired
+      } else {
+        TaskStatus::Open
+      };
+      db.tasks_collection
+        .update_one(
+          doc! {"_id": id},
+          doc! {"$set": {"status": format!("{:?}", status), "history": []}},
+          None,
+        )
+        .await
+        .map_err(MongoQueryError)?;
+    }
+    Ok(())
+  })
+}
+
+/// Tasks were all public, without invitations.
+fn task_visibility(db: &DB) -> MigrationFuture<'_> {
+  Box::pin(async move {
+    drop_validator(db, &db.tasks_collection).await?;
+    db.tasks_collection
+      .update_many(
+        doc! {"visibility": {"$exists": false}},
+        doc! {"$set": {
+          "visibility": format!("{:?}", TaskVisibility::Public),
+          "invitations": [],
+        }},
+        None,
+      )
+      .await
+      .map_err(MongoQueryError)?;
+    Ok(())
+  })
+}
+
+/// Applies the migrations not recorded yet, stopping at the first failure.
+pub async fn run_migrations(db: &DB) -> Result<()> {
+  for migration in pending_migrations(db).await? {
+    (migration.run)(db).await?;
+    db.migrations_collection
+      .insert_one(
+        doc! {"_id": migration.name, "applied_at": DateTime::now()},
+        None,
+      )
+      .await
+      .map_err(MongoQueryError)?;
+    println!("✅ Migration {} applied", migration.name);
+  }
+  Ok(())
+}
+
+pub async fn pending_migrations(db: &DB) -> Result<Vec<&'static Migration>> {
+  let mut cursor = db
+    .migrations_collection
+    .find(None, None)
+    .await
+    .map_err(MongoQueryError)?;
+
+  let mut applied = Vec::new();
+  while let Some(doc) = cursor.next().await {
+    let doc: Document = doc.map_err(MongoQueryError)?;
+    if let Ok(name) = doc.get_str("_id") {
+      applied.push(name.to_string());
+    }
+  }
+
+  Ok(
+    MIGRATIONS
+      .iter()
+      .filter(|migration| !applied.iter().any(|name| name == migration.name))
+      .collect(),
+  )
+}
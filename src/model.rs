@@ -1,5 +1,10 @@
+use mongodb::bson::DateTime;
 use serde::{Deserialize, Serialize};
 
+use crate::job::{JobKind, JobStatus};
+use crate::lifecycle::{DealStatus, InvitationStatus, MilestoneStatus, StatusChange, TaskStatus};
+use crate::money::Money;
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct UserModel {
   #[serde(rename = "_id")]
@@ -7,6 +12,10 @@ pub struct UserModel {
   pub user_name: String,
   pub description: Option<String>,
   pub password: String,
+  #[serde(default)]
+  pub version: i64,
+  pub deleted_at: Option<DateTime>,
+  pub deleted_by: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -28,13 +37,102 @@ pub struct TaskModel {
   pub id: String,
   pub client_id: String,
   pub title: String,
-  pub start_time: String,
-  pub deadline: String,
+  pub start_time: DateTime,
+  pub deadline: DateTime,
   pub description: String,
-  //#[validate(length(max = 5))]
   pub skills: Vec<String>,
-  pub bounty: u16,
+  pub bounty: Money,
   pub proposals_id: Option<Vec<String>>,
+  #[serde(default)]
+  pub version: i64,
+  pub deleted_at: Option<DateTime>,
+  pub deleted_by: Option<String>,
+  pub created_at: Option<DateTime>,
+  pub updated_at: Option<DateTime>,
+  pub created_by: Option<String>,
+  pub cancelled_at: Option<DateTime>,
+  pub cancelled_by: Option<String>,
+  pub cancel_reason: Option<String>,
+  #[serde(default)]
+  pub edits: Vec<TaskEdit>,
+  #[serde(default)]
+  pub status: TaskStatus,
+  #[serde(default)]
+  pub history: Vec<StatusChange<TaskStatus>>,
+  #[serde(default)]
+  pub attachments: Vec<Attachment>,
+  #[serde(default)]
+  pub visibility: TaskVisibility,
+  #[serde(default)]
+  pub invitations: Vec<Invitation>,
+}
+
+/// Who sees a task: everyone, those given its id (it isn't listed), or only
+/// its client and the freelancers they invited, who alone may bid on it.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TaskVisibility {
+  #[default]
+  Public,
+  Unlisted,
+  InviteOnly,
+}
+
+impl TaskVisibility {
+  pub const ALL: &'static [Self] = &[
+    TaskVisibility::Public,
+    TaskVisibility::Unlisted,
+    TaskVisibility::InviteOnly,
+  ];
+}
+
+/// A freelancer invited to a task, and their answer.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Invitation {
+  pub freelancer_id: String,
+  pub status: InvitationStatus,
+  pub invited_by: String,
+  pub invited_at: DateTime,
+  pub answered_at: Option<DateTime>,
+}
+
+/// A file uploaded to a task or delivered for a milestone, stored under `key`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Attachment {
+  pub id: String,
+  pub file_name: String,
+  pub content_type: String,
+  pub size: i64,
+  pub key: String,
+  pub uploaded_by: String,
+  pub uploaded_at: DateTime,
+}
+
+/// The editable fields of a task; an edit only holds those it changed.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct TaskFields {
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub title: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub description: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub start_time: Option<DateTime>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub deadline: Option<DateTime>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub skills: Option<Vec<String>>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub bounty: Option<Money>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub visibility: Option<TaskVisibility>,
+}
+
+/// One entry of a task's `edits`: the changed fields before and after.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TaskEdit {
+  pub at: DateTime,
+  pub by: String,
+  pub before: TaskFields,
+  pub after: TaskFields,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -45,8 +143,19 @@ pub struct ProposalModel {
   pub task_id: String,
   pub freelancer_id: String,
   pub milestones_id: Option<Vec<String>>,
-  pub proposal_price: f64,
+  pub proposal_price: Money,
   pub accepted: bool,
+  #[serde(default)]
+  pub version: i64,
+  pub deleted_at: Option<DateTime>,
+  pub deleted_by: Option<String>,
+  pub created_at: Option<DateTime>,
+  pub updated_at: Option<DateTime>,
+  pub created_by: Option<String>,
+  /// Set when the proposal can no longer be accepted, e.g. because its task
+  /// was cancelled.
+  pub closed_at: Option<DateTime>,
+  pub closed_reason: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -55,10 +164,22 @@ pub struct MilestoneModel {
   pub id: String,
   pub proposal_id: String,
   pub description: String,
-  pub deadline: String,
-  pub price: f64,
+  pub deadline: DateTime,
+  pub price: Money,
   pub link: String,
-  pub status: String,
+  pub status: MilestoneStatus,
+  #[serde(default)]
+  pub history: Vec<StatusChange<MilestoneStatus>>,
+  pub reminded_at: Option<DateTime>,
+  #[serde(default)]
+  pub deliverables: Vec<Attachment>,
+  #[serde(default)]
+  pub version: i64,
+  pub deleted_at: Option<DateTime>,
+  pub deleted_by: Option<String>,
+  pub created_at: Option<DateTime>,
+  pub updated_at: Option<DateTime>,
+  pub created_by: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -69,9 +190,18 @@ pub struct DealModel {
   pub proposal_id: String,
   pub freelancer_id: String,
   pub client_id: String,
-  pub price: f64,
-  pub status: String,
-  pub address: String,
+  pub price: Money,
+  pub status: DealStatus,
+  pub address: Option<String>,
+  #[serde(default)]
+  pub history: Vec<StatusChange<DealStatus>>,
+  #[serde(default)]
+  pub version: i64,
+  pub deleted_at: Option<DateTime>,
+  pub deleted_by: Option<String>,
+  pub created_at: Option<DateTime>,
+  pub updated_at: Option<DateTime>,
+  pub created_by: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -83,4 +213,66 @@ pub struct ReviewModel {
   pub deal_id: String,
   pub review: String,
   pub stars: u16,
+  #[serde(default)]
+  pub version: i64,
+  pub deleted_at: Option<DateTime>,
+  pub deleted_by: Option<String>,
+  pub created_at: Option<DateTime>,
+  pub updated_at: Option<DateTime>,
+  pub created_by: Option<String>,
+}
+
+/// An entry of the skill taxonomy. Skills sent with tasks and freelancers
+/// are stored under the `name` of the skill they match.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SkillModel {
+  #[serde(rename = "_id")]
+  pub id: String,
+  pub name: String,
+  pub category: Option<String>,
+  pub aliases: Vec<String>,
+  /// `skill_key` of the name and of every alias, unique across skills.
+  pub keys: Vec<String>,
+  /// The skills folded into this one, oldest first.
+  #[serde(default)]
+  pub merges: Vec<SkillMerge>,
+  #[serde(default)]
+  pub version: i64,
+  pub created_at: Option<DateTime>,
+  pub updated_at: Option<DateTime>,
+  pub created_by: Option<String>,
+}
+
+/// One merge of other skills into a skill.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SkillMerge {
+  /// The names of the skills merged in.
+  pub skills: Vec<String>,
+  pub by: String,
+  pub at: DateTime,
+}
+
+/// Work the scheduler runs in the background, once or every `every_secs`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct JobModel {
+  #[serde(rename = "_id")]
+  pub id: String,
+  pub kind: JobKind,
+  pub status: JobStatus,
+  pub run_at: DateTime,
+  pub every_secs: Option<i64>,
+  pub attempts: i32,
+  pub max_attempts: i32,
+  pub last_run_at: Option<DateTime>,
+  pub last_result: Option<String>,
+  pub last_error: Option<String>,
+  /// The instance running it, until `lease_until`.
+  pub lease_owner: Option<String>,
+  pub lease_until: Option<DateTime>,
+  /// Set on a recurring job while the configuration disables it.
+  #[serde(default)]
+  pub disabled: bool,
+  pub finished_at: Option<DateTime>,
+  pub created_at: Option<DateTime>,
+  pub updated_at: Option<DateTime>,
 }
@@ -0,0 +1,269 @@
+use std::fmt;
+
+use serde::de::{self, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::error::MyError::{self, InvalidAmountError};
+
+/// The tokens amounts can be denominated in.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Token {
+  Eth,
+  Usdc,
+  Usdt,
+  Dai,
+}
+
+impl Token {
+  pub const ALL: [Token; 4] = [Token::Eth, Token::Usdc, Token::Usdt, Token::Dai];
+
+  /// Digits after the decimal point of the token's smallest unit.
+  pub const fn decimals(self) -> u32 {
+    match self {
+      Token::Eth | Token::Dai => 18,
+      Token::Usdc | Token::Usdt => 6,
+    }
+  }
+
+  pub const fn symbol(self) -> &'static str {
+    match self {
+      Token::Eth => "ETH",
+      Token::Usdc => "USDC",
+      Token::Usdt => "USDT",
+      Token::Dai => "DAI",
+    }
+  }
+}
+
+impl fmt::Display for Token {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(self.symbol())
+  }
+}
+
+/// An exact, non-negative amount of a token, held in its smallest unit.
+///
+/// It reads and writes as `{"amount": "12.5", "token": "USDC"}`, both in JSON
+/// and in MongoDB, so no precision is lost on the way. A whole amount may also
+/// be sent as a JSON integer; fractional ones must be strings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Money {
+  units: u128,
+  token: Token,
+}
+
+impl Money {
+  pub const fn zero(token: Token) -> Self {
+    Self { units: 0, token }
+  }
+
+  /// Parses a decimal amount such as `12.5` or `400`.
+  pub fn parse(amount: &str, token: Token) -> Result<Self, MyError> {
+    let invalid = |reason: &str| InvalidAmountError(format!("{} {}: {}", amount, token, reason));
+    let (whole, fraction) = amount.split_once('.').unwrap_or((amount, ""));
+    let digits_only = whole
+      .bytes()
+      .chain(fraction.bytes())
+      .all(|b| b.is_ascii_digit());
+    if whole.is_empty() || amount.ends_with('.') || !digits_only {
+      return Err(invalid("not a non-negative decimal number"));
+    }
+    let decimals = token.decimals();
+    if fraction.len() > decimals as usize {
+      return Err(invalid(&format!(
+        "{} has at most {} decimals",
+        token, decimals
+      )));
+    }
+    let digits = format!("{}{:0<width$}", whole, fraction, width = decimals as usize);
+    let units = digits.parse().map_err(|_| invalid("too large"))?;
+    Ok(Self { units, token })
+  }
+
+  pub fn token(&self) -> Token {
+    self.token
+  }
+
+  /// The amount in the token's smallest unit, e.g. wei for ETH.
+  pub fn units(&self) -> u128 {
+    self.units
+  }
+
+  /// The sum of two amounts of the same token.
+  pub fn checked_add(self, other: Money) -> Result<Money, MyError> {
+    if self.token != other.token {
+      return Err(InvalidAmountError(format!(
+        "cannot add {} to {}",
+        other.token, self.token
+      )));
+    }
+    let units = self
+      .units
+      .checked_add(other.units)
+      .ok_or_else(|| InvalidAmountError(format!("{} + {} overflows", self, other)))?;
+    Ok(Money { units, ..self })
+  }
+
+  /// The amount without its token, trailing zeros trimmed.
+  pub fn amount(&self) -> String {
+    let decimals = self.token.decimals();
+    let scale = 10u128.pow(decimals);
+    let whole = self.units / scale;
+    let fraction = self.units % scale;
+    if fraction == 0 {
+      return whole.to_string();
+    }
+    let fraction = format!("{:0>width$}", fraction, width = decimals as usize);
+    format!("{}.{}", whole, fraction.trim_end_matches('0'))
+  }
+}
+
+impl fmt::Display for Money {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{} {}", self.amount(), self.token)
+  }
+}
+
+#[derive(Serialize)]
+struct MoneyRef<'a> {
+  amount: &'a str,
+  token: Token,
+}
+
+impl Serialize for Money {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    MoneyRef {
+      amount: &self.amount(),
+      token: self.token,
+    }
+    .serialize(serializer)
+  }
+}
+
+#[derive(Deserialize)]
+struct MoneyRepr {
+  amount: Amount,
+  token: Token,
+}
+
+impl<'de> Deserialize<'de> for Money {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    let repr = MoneyRepr::deserialize(deserializer)?;
+    Money::parse(&repr.amount.0, repr.token).map_err(de::Error::custom)
+  }
+}
+
+/// The decimal text of an amount sent as a string or a JSON integer. Floats are
+/// refused: by the time they get here they may already be off by a few units.
+struct Amount(String);
+
+impl<'de> Deserialize<'de> for Amount {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    struct AmountVisitor;
+
+    impl<'de> Visitor<'de> for AmountVisitor {
+      type Value = Amount;
+
+      fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a decimal amount as a string, or an integer")
+      }
+
+      fn visit_str<E: de::Error>(self, value: &str) -> Result<Amount, E> {
+        Ok(Amount(value.to_string()))
+      }
+
+      fn visit_u64<E: de::Error>(self, value: u64) -> Result<Amount, E> {
+        Ok(Amount(value.to_string()))
+      }
+
+      fn visit_i64<E: de::Error>(self, value: i64) -> Result<Amount, E> {
+        Ok(Amount(value.to_string()))
+      }
+
+      fn visit_f64<E: de::Error>(self, value: f64) -> Result<Amount, E> {
+        Err(E::custom(format!(
+          "amount {} is a floating point number, send it as a string such as \"{}\"",
+          value, value
+        )))
+      }
+    }
+
+    deserializer.deserialize_any(AmountVisitor)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn from_json(json: &str) -> Result<Money, serde_json::Error> {
+    serde_json::from_str(json)
+  }
+
+  #[test]
+  fn parses_decimal_amounts() {
+    let money = Money::parse("12.5", Token::Usdc).unwrap();
+    assert_eq!(money.units(), 12_500_000);
+    assert_eq!(money.amount(), "12.5");
+    assert_eq!(Money::parse("400", Token::Eth).unwrap().amount(), "400");
+  }
+
+  #[test]
+  fn rejects_malformed_amounts() {
+    for amount in ["", ".5", "5.", "-1", "1e3", "1.2.3", " 1"] {
+      assert!(Money::parse(amount, Token::Usdc).is_err(), "{:?}", amount);
+    }
+  }
+
+  #[test]
+  fn rejects_too_many_decimals() {
+    assert!(Money::parse("0.000001", Token::Usdc).is_ok());
+    assert!(Money::parse("0.0000001", Token::Usdc).is_err());
+    assert!(Money::parse("0.000000000000000001", Token::Eth).is_ok());
+    assert!(Money::parse("0.0000000000000000001", Token::Eth).is_err());
+  }
+
+  #[test]
+  fn rejects_amounts_overflowing_u128() {
+    let whole_units = (u128::MAX / 10u128.pow(6)).to_string();
+    assert!(Money::parse(&whole_units, Token::Usdc).is_ok());
+    assert!(Money::parse(&format!("{}1", whole_units), Token::Usdc).is_err());
+    assert!(Money::parse(&u128::MAX.to_string(), Token::Eth).is_err());
+  }
+
+  #[test]
+  fn rejects_overflowing_sums_and_mixed_tokens() {
+    let max = Money {
+      units: u128::MAX,
+      token: Token::Dai,
+    };
+    let one = Money::parse("0.000000000000000001", Token::Dai).unwrap();
+    assert!(max.checked_add(one).is_err());
+    let usdc = Money::parse("1", Token::Usdc).unwrap();
+    assert!(one.checked_add(usdc).is_err());
+  }
+
+  #[test]
+  fn reads_strings_and_integers() {
+    let money = from_json(r#"{"amount": "0.25", "token": "ETH"}"#).unwrap();
+    assert_eq!(money, Money::parse("0.25", Token::Eth).unwrap());
+    let money = from_json(r#"{"amount": 400, "token": "USDC"}"#).unwrap();
+    assert_eq!(money, Money::parse("400", Token::Usdc).unwrap());
+  }
+
+  #[test]
+  fn refuses_floats() {
+    let error = from_json(r#"{"amount": 0.25, "token": "ETH"}"#).unwrap_err();
+    assert!(error.to_string().contains("as a string"), "{}", error);
+  }
+
+  #[test]
+  fn writes_the_amount_as_a_string() {
+    let money = Money::parse("12.50", Token::Usdt).unwrap();
+    assert_eq!(
+      serde_json::to_value(money).unwrap(),
+      serde_json::json!({"amount": "12.5", "token": "USDT"})
+    );
+  }
+}
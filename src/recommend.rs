@@ -0,0 +1,279 @@
+use std::collections::{HashMap, HashSet};
+
+use mongodb::bson::DateTime;
+
+use crate::model::TaskModel;
+use crate::money::Token;
+use crate::skill::skill_key;
+
+/// How much each signal weighs in a task's score, out of 1.
+const SKILLS_WEIGHT: f64 = 0.5;
+const BOUNTY_WEIGHT: f64 = 0.2;
+const DEADLINE_WEIGHT: f64 = 0.15;
+const HISTORY_WEIGHT: f64 = 0.15;
+
+/// Days of work from which a deadline counts as comfortable.
+const COMFORTABLE_DAYS: f64 = 7.0;
+
+const DAY_MILLIS: f64 = 86_400_000.0;
+
+/// What a freelancer's completed deals tell about them.
+#[derive(Debug, Default)]
+pub struct History {
+  /// Completed deals by client.
+  pub deals_by_client: HashMap<String, usize>,
+  /// The keys of the skills required by the tasks of their completed deals.
+  pub proven_skills: HashSet<String>,
+}
+
+/// A task worth suggesting, scored from 0 to 100, with why.
+#[derive(Debug)]
+pub struct Recommendation {
+  pub task: TaskModel,
+  pub score: u32,
+  pub reasons: Vec<String>,
+}
+
+/// The `tasks` suiting a freelancer with `skills` and `history` best first,
+/// leaving out those sharing no skill with them from clients they never
+/// completed a deal for.
+pub fn rank(
+  tasks: Vec<TaskModel>,
+  skills: &[String],
+  history: &History,
+  now: DateTime,
+) -> Vec<Recommendation> {
+  let skills: HashSet<String> = skills.iter().map(|skill| skill_key(skill)).collect();
+  let mut top_bounties: HashMap<Token, u128> = HashMap::new();
+  for task in &tasks {
+    let top = top_bounties.entry(task.bounty.token()).or_default();
+    *top = (*top).max(task.bounty.units());
+  }
+
+  let mut recommendations: Vec<Recommendation> = tasks
+    .into_iter()
+    .filter_map(|task| {
+      let top_bounty = top_bounties[&task.bounty.token()];
+      score(task, &skills, history, top_bounty, now)
+    })
+    .collect();
+  recommendations.sort_by(|a, b| {
+    b.score
+      .cmp(&a.score)
+      .then(a.task.deadline.cmp(&b.task.deadline))
+      .then(a.task.id.cmp(&b.task.id))
+  });
+  recommendations
+}
+
+fn score(
+  task: TaskModel,
+  skills: &HashSet<String>,
+  history: &History,
+  top_bounty: u128,
+  now: DateTime,
+) -> Option<Recommendation> {
+  let required = task.skills.len().max(1) as f64;
+  let matched = matching(&task.skills, skills);
+  let deals = history
+    .deals_by_client
+    .get(&task.client_id)
+    .copied()
+    .unwrap_or_default();
+  if matched.is_empty() && deals == 0 {
+    return None;
+  }
+  let proven = matching(&task.skills, &history.proven_skills);
+
+  let mut reasons = Vec::new();
+  let skills_fit = matched.len() as f64 / required;
+  if !matched.is_empty() {
+    reasons.push(format!(
+      "matches {} of {} required skills: {}",
+      matched.len(),
+      task.skills.len(),
+      matched.join(", ")
+    ));
+  }
+
+  let bounty_fit = match top_bounty {
+    0 => 0.0,
+    top => task.bounty.units() as f64 / top as f64,
+  };
+  if task.bounty.units() == top_bounty {
+    reasons.push(format!(
+      "highest open {} bounty ({})",
+      task.bounty.token(),
+      task.bounty
+    ));
+  } else {
+    reasons.push(format!(
+      "bounty of {}, {:.0}% of the highest open {} bounty",
+      task.bounty,
+      bounty_fit * 100.0,
+      task.bounty.token()
+    ));
+  }
+
+  let starts = task.start_time.max(now);
+  let days = (task.deadline.timestamp_millis() - starts.timestamp_millis()) as f64 / DAY_MILLIS;
+  let deadline_fit = (days / COMFORTABLE_DAYS).clamp(0.0, 1.0);
+  if days < 1.0 {
+    reasons.push("less than a day left to deliver".to_string());
+  } else {
+    reasons.push(format!("{} day(s) to deliver", days.floor()));
+  }
+
+  let history_fit = if deals > 0 {
+    1.0
+  } else {
+    proven.len() as f64 / required
+  };
+  if deals > 0 {
+    reasons.push(format!("you completed {} deal(s) for this client", deals));
+  }
+  if !proven.is_empty() {
+    reasons.push(format!(
+      "you completed deals requiring {}",
+      proven.join(", ")
+    ));
+  }
+
+  let score = SKILLS_WEIGHT * skills_fit
+    + BOUNTY_WEIGHT * bounty_fit
+    + DEADLINE_WEIGHT * deadline_fit
+    + HISTORY_WEIGHT * history_fit;
+  Some(Recommendation {
+    task,
+    score: (score * 100.0).round() as u32,
+    reasons,
+  })
+}
+
+/// The skills of `required` whose keys are in `keys`.
+fn matching(required: &[String], keys: &HashSet<String>) -> Vec<String> {
+  required
+    .iter()
+    .filter(|skill| keys.contains(&skill_key(skill)))
+    .cloned()
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::money::Money;
+
+  const NOW: i64 = 1_700_000_000_000;
+  const DAY: i64 = 86_400_000;
+
+  fn task(id: &str, skills: &[&str], bounty: &str, token: Token, days: i64) -> TaskModel {
+    TaskModel {
+      id: id.to_string(),
+      client_id: "client".to_string(),
+      title: id.to_string(),
+      start_time: DateTime::from_millis(NOW - DAY),
+      deadline: DateTime::from_millis(NOW + days * DAY),
+      description: String::new(),
+      skills: skills.iter().map(|skill| skill.to_string()).collect(),
+      bounty: Money::parse(bounty, token).unwrap(),
+      proposals_id: None,
+      version: 0,
+      deleted_at: None,
+      deleted_by: None,
+      created_at: None,
+      updated_at: None,
+      created_by: None,
+      cancelled_at: None,
+      cancelled_by: None,
+      cancel_reason: None,
+      edits: Vec::new(),
+      status: Default::default(),
+      history: Vec::new(),
+      attachments: Vec::new(),
+      visibility: Default::default(),
+      invitations: Vec::new(),
+    }
+  }
+
+  fn ranked(tasks: Vec<TaskModel>, skills: &[&str], history: &History) -> Vec<(String, u32)> {
+    let skills: Vec<String> = skills.iter().map(|skill| skill.to_string()).collect();
+    rank(tasks, &skills, history, DateTime::from_millis(NOW))
+      .into_iter()
+      .map(|recommendation| (recommendation.task.id, recommendation.score))
+      .collect()
+  }
+
+  #[test]
+  fn weighs_bounties_against_the_top_one_of_their_token() {
+    let recommendations = rank(
+      vec![
+        task("eth", &["rust"], "1", Token::Eth, 10),
+        task("usdc", &["rust"], "100", Token::Usdc, 10),
+        task("usdc-half", &["rust"], "50", Token::Usdc, 10),
+      ],
+      &["Rust".to_string()],
+      &History::default(),
+      DateTime::from_millis(NOW),
+    );
+    let scores: Vec<(&str, u32)> = recommendations
+      .iter()
+      .map(|recommendation| (recommendation.task.id.as_str(), recommendation.score))
+      .collect();
+    // Skills 50, bounty 20 at the top of its token, deadline 15.
+    assert_eq!(scores, [("eth", 85), ("usdc", 85), ("usdc-half", 75)]);
+    assert!(recommendations[0].reasons[1].starts_with("highest open ETH bounty"));
+    assert!(recommendations[2].reasons[1].contains("50% of the highest open USDC bounty"));
+  }
+
+  #[test]
+  fn leaves_out_tasks_without_a_skill_or_a_known_client() {
+    let tasks = || {
+      vec![
+        task("rust", &["rust"], "1", Token::Eth, 10),
+        task("solidity", &["solidity"], "1", Token::Eth, 10),
+      ]
+    };
+    assert_eq!(
+      ranked(tasks(), &["Rust"], &History::default()),
+      [("rust".to_string(), 85)]
+    );
+
+    let mut history = History::default();
+    history.deals_by_client.insert("client".to_string(), 2);
+    // A past client lifts the history part to its full 15.
+    assert_eq!(
+      ranked(tasks(), &["Rust"], &history),
+      [("rust".to_string(), 100), ("solidity".to_string(), 50)]
+    );
+  }
+
+  #[test]
+  fn breaks_ties_by_deadline_then_id() {
+    let tasks = vec![
+      task("b", &["rust"], "1", Token::Eth, 10),
+      task("c", &["rust"], "1", Token::Eth, 8),
+      task("a", &["rust"], "1", Token::Eth, 10),
+    ];
+    let ids: Vec<String> = ranked(tasks, &["rust"], &History::default())
+      .into_iter()
+      .map(|(id, _)| id)
+      .collect();
+    assert_eq!(ids, ["c", "a", "b"]);
+  }
+
+  #[test]
+  fn counts_a_past_deadline_as_no_time_left() {
+    let recommendations = rank(
+      vec![task("late", &["rust"], "1", Token::Eth, -2)],
+      &["rust".to_string()],
+      &History::default(),
+      DateTime::from_millis(NOW),
+    );
+    // Negative days left score no deadline part at all.
+    assert_eq!(recommendations[0].score, 70);
+    assert!(recommendations[0]
+      .reasons
+      .contains(&"less than a day left to deliver".to_string()));
+  }
+}
@@ -1,238 +1,402 @@
+use chrono::{DateTime, Utc};
 use serde::Serialize;
 
-#[derive(Serialize, Debug)]
+use crate::job::{JobKind, JobStatus};
+use crate::lifecycle::{DealStatus, InvitationStatus, MilestoneStatus, TaskStatus};
+use crate::model::TaskVisibility;
+use crate::money::Money;
+
+#[derive(Serialize, Debug, Clone)]
 pub struct UserResponse {
   pub id: String,
   pub role: String,
   pub user_name: String,
   pub description: String,
   pub password: String,
+  pub version: i64,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub deleted_at: Option<DateTime<Utc>>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub deleted_by: Option<String>,
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, Clone)]
 pub struct FreelancerResponse {
   #[serde(flatten)]
   pub user: UserResponse,
   pub skills: Vec<String>,
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, Clone)]
+pub struct ReputationResponse {
+  pub reviews: u64,
+  pub average_stars: Option<f64>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct FreelancerProfileResponse {
+  #[serde(flatten)]
+  pub freelancer: FreelancerResponse,
+  pub reputation: ReputationResponse,
+}
+
+#[derive(Serialize, Debug, Clone)]
 pub struct ClientResponse {
   #[serde(flatten)]
   pub user: UserResponse,
   pub tasks_ids: Vec<String>,
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, Clone)]
 pub struct TaskResponse {
   pub id: String,
   pub client_id: String,
   pub title: String,
-  pub start_time: String,
-  pub deadline: String,
+  pub start_time: DateTime<Utc>,
+  pub deadline: DateTime<Utc>,
   pub description: String,
   pub skills: Vec<String>,
-  pub bounty: u16,
+  pub bounty: Money,
   pub proposals_id: Vec<String>,
+  pub version: i64,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub deleted_at: Option<DateTime<Utc>>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub deleted_by: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub created_at: Option<DateTime<Utc>>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub updated_at: Option<DateTime<Utc>>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub created_by: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub cancelled_at: Option<DateTime<Utc>>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub cancelled_by: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub cancel_reason: Option<String>,
+  #[serde(skip_serializing_if = "Vec::is_empty")]
+  pub edits: Vec<TaskEditResponse>,
+  pub status: TaskStatus,
+  pub history: Vec<StatusChangeResponse<TaskStatus>>,
+  #[serde(skip_serializing_if = "Vec::is_empty")]
+  pub attachments: Vec<AttachmentResponse>,
+  pub visibility: TaskVisibility,
+  #[serde(skip_serializing_if = "Vec::is_empty")]
+  pub invitations: Vec<InvitationResponse>,
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, Clone)]
+pub struct InvitationResponse {
+  pub freelancer_id: String,
+  pub status: InvitationStatus,
+  pub invited_by: String,
+  pub invited_at: DateTime<Utc>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub answered_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct AttachmentResponse {
+  pub id: String,
+  pub file_name: String,
+  pub content_type: String,
+  pub size: i64,
+  pub uploaded_by: String,
+  pub uploaded_at: DateTime<Utc>,
+  /// Where to download it from.
+  pub url: String,
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct TaskFieldsResponse {
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub title: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub description: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub start_time: Option<DateTime<Utc>>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub deadline: Option<DateTime<Utc>>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub skills: Option<Vec<String>>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub bounty: Option<Money>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub visibility: Option<TaskVisibility>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct TaskEditResponse {
+  pub at: DateTime<Utc>,
+  pub by: String,
+  pub before: TaskFieldsResponse,
+  pub after: TaskFieldsResponse,
+}
+
+#[derive(Serialize, Debug, Clone)]
 pub struct MilestoneResponse {
   pub id: String,
   pub proposal_id: String,
   pub description: String,
-  pub deadline: String,
-  pub price: f64,
+  pub deadline: DateTime<Utc>,
+  pub price: Money,
   pub link: String,
-  pub status: String,
-}
-#[derive(Serialize, Debug)]
+  pub status: MilestoneStatus,
+  pub history: Vec<StatusChangeResponse<MilestoneStatus>>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub reminded_at: Option<DateTime<Utc>>,
+  #[serde(skip_serializing_if = "Vec::is_empty")]
+  pub deliverables: Vec<AttachmentResponse>,
+  pub version: i64,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub deleted_at: Option<DateTime<Utc>>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub deleted_by: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub created_at: Option<DateTime<Utc>>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub updated_at: Option<DateTime<Utc>>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub created_by: Option<String>,
+}
+#[derive(Serialize, Debug, Clone)]
 pub struct ProposalResponse {
   pub id: String,
   pub client_id: String,
   pub task_id: String,
   pub freelancer_id: String,
   pub milestones_id: Vec<String>,
-  pub proposal_price: f64,
+  pub proposal_price: Money,
   pub accepted: bool,
+  pub version: i64,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub deleted_at: Option<DateTime<Utc>>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub deleted_by: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub created_at: Option<DateTime<Utc>>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub updated_at: Option<DateTime<Utc>>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub created_by: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub closed_at: Option<DateTime<Utc>>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub closed_reason: Option<String>,
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, Clone)]
 pub struct ProposalDetailedResponse {
   pub id: String,
   pub client_id: String,
   pub task_id: String,
   pub freelancer_id: String,
   pub milestones: Vec<MilestoneResponse>,
-  pub proposal_price: f64,
+  pub proposal_price: Money,
   pub accepted: bool,
+  pub version: i64,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub deleted_at: Option<DateTime<Utc>>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub deleted_by: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub created_at: Option<DateTime<Utc>>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub updated_at: Option<DateTime<Utc>>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub created_by: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub closed_at: Option<DateTime<Utc>>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub closed_reason: Option<String>,
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, Clone)]
 pub struct DealResponse {
   pub id: String,
   pub task_id: String,
   pub proposal_id: String,
   pub freelancer_id: String,
   pub client_id: String,
-  pub price: f64,
-  pub status: String,
-  pub address: String,
+  pub price: Money,
+  pub status: DealStatus,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub address: Option<String>,
+  pub history: Vec<StatusChangeResponse<DealStatus>>,
+  pub version: i64,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub deleted_at: Option<DateTime<Utc>>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub deleted_by: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub created_at: Option<DateTime<Utc>>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub updated_at: Option<DateTime<Utc>>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub created_by: Option<String>,
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, Clone)]
 pub struct PartialDealResponse {
   pub task_id: String,
   pub proposal_id: String,
   pub freelancer_id: String,
   pub client_id: String,
-  pub price: f64,
-  pub status: String,
-  pub address: String,
+  pub price: Money,
+  pub status: DealStatus,
+  pub address: Option<String>,
 }
 
-#[derive(Serialize, Debug)]
-pub struct UserData {
-  pub user: UserResponse,
+#[derive(Serialize, Debug, Clone)]
+pub struct StatusChangeResponse<S> {
+  pub from: S,
+  pub to: S,
+  pub at: DateTime<Utc>,
+  pub by: String,
 }
 
-#[derive(Serialize, Debug)]
-pub struct ClientData {
-  pub client: ClientResponse,
+#[derive(Serialize, Debug, Clone)]
+pub struct UserData {
+  pub user: UserResponse,
 }
 
-#[derive(Serialize, Debug)]
-pub struct FreelancerData {
-  pub freelancer: FreelancerResponse,
+#[derive(Serialize, Debug, Clone)]
+pub struct TaskData {
+  pub task: TaskResponse,
 }
 
-#[derive(Serialize, Debug)]
-pub struct TaskData {
+/// A cancelled task and how many of its pending proposals were closed.
+#[derive(Serialize, Debug, Clone)]
+pub struct TaskCancelData {
   pub task: TaskResponse,
+  pub closed_proposals: u64,
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, Clone)]
 pub struct ProposalData {
   pub proposal: ProposalResponse,
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, Clone)]
 pub struct ProposalDetailedData {
   pub detailed_proposal: ProposalDetailedResponse,
 }
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, Clone)]
 pub struct MilestoneData {
   pub milestone: MilestoneResponse,
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, Clone)]
 pub struct ProposalDealData {
   pub proposal: ProposalResponse,
   pub deal: DealResponse,
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, Clone)]
 pub struct DealData {
   pub deal: DealResponse,
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, Clone)]
 pub struct SingleUserResponse {
   pub status: &'static str,
   pub data: UserData,
 }
 
-#[derive(Serialize, Debug)]
-pub struct SingleClientResponse {
-  pub status: &'static str,
-  pub data: ClientData,
-}
-
-#[derive(Serialize, Debug)]
-pub struct SingleFreelancerResponse {
-  pub status: &'static str,
-  pub data: FreelancerData,
-}
-
-#[derive(Serialize, Debug)]
-pub struct UsersListResponse {
-  pub status: &'static str,
-  pub results: usize,
-  pub users: Vec<UserResponse>,
-}
-
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, Clone)]
 pub struct ClientListResponse {
   pub status: &'static str,
   pub results: usize,
   pub users: Vec<ClientResponse>,
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, Clone)]
 pub struct FreelancerListResponse {
   pub status: &'static str,
   pub results: usize,
   pub users: Vec<FreelancerResponse>,
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, Clone)]
 pub struct SingleTaskResponse {
   pub status: &'static str,
   pub data: TaskData,
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, Clone)]
+pub struct TaskCancelResponse {
+  pub status: &'static str,
+  pub data: TaskCancelData,
+}
+
+#[derive(Serialize, Debug, Clone)]
 pub struct SingleProposalResponse {
   pub status: &'static str,
   pub data: ProposalData,
 }
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, Clone)]
 pub struct SingleProposalDetailedResponse {
   pub status: &'static str,
   pub data: ProposalDetailedData,
 }
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, Clone)]
 pub struct SingleMilestoneResponse {
   pub status: &'static str,
   pub data: MilestoneData,
 }
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, Clone)]
 pub struct SingleProposalDealResponse {
   pub status: &'static str,
   pub data: ProposalDealData,
 }
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, Clone)]
 pub struct SingleDealResponse {
   pub status: &'static str,
   pub data: DealData,
 }
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, Clone)]
 pub struct TaskListResponse {
   pub status: &'static str,
   pub results: usize,
   pub tasks: Vec<TaskResponse>,
 }
 
-#[derive(Serialize, Debug)]
+/// An open task suggested to a freelancer, scored from 0 to 100.
+#[derive(Serialize, Debug, Clone)]
+pub struct RecommendedTaskResponse {
+  pub task: TaskResponse,
+  pub score: u32,
+  pub reasons: Vec<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct RecommendedTaskListResponse {
+  pub status: &'static str,
+  pub results: usize,
+  pub recommendations: Vec<RecommendedTaskResponse>,
+}
+
+#[derive(Serialize, Debug, Clone)]
 pub struct ProposalListResponse {
   pub status: &'static str,
   pub results: usize,
   pub proposals: Vec<ProposalResponse>,
 }
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, Clone)]
 pub struct MilestoneListResponse {
   pub status: &'static str,
   pub results: usize,
   pub milestones: Vec<MilestoneResponse>,
 }
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, Clone)]
 pub struct DealListResponse {
   pub status: &'static str,
   pub results: usize,
   pub deals: Vec<DealResponse>,
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, Clone)]
 pub struct ReviewResponse {
   pub id: String,
   pub client_id: String,
@@ -240,15 +404,228 @@ pub struct ReviewResponse {
   pub freelancer_id: String,
   pub review: String,
   pub stars: u16,
+  pub version: i64,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub deleted_at: Option<DateTime<Utc>>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub deleted_by: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub created_at: Option<DateTime<Utc>>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub updated_at: Option<DateTime<Utc>>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub created_by: Option<String>,
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, Clone)]
 pub struct ReviewData {
   pub review: ReviewResponse,
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, Clone)]
 pub struct SingleReviewResponse {
   pub status: &'static str,
   pub data: ReviewData,
 }
+
+#[derive(Serialize, Debug, Clone)]
+pub struct SkillResponse {
+  pub id: String,
+  pub name: String,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub category: Option<String>,
+  pub aliases: Vec<String>,
+  pub version: i64,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub created_at: Option<DateTime<Utc>>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub updated_at: Option<DateTime<Utc>>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub created_by: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct SkillData {
+  pub skill: SkillResponse,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct SingleSkillResponse {
+  pub status: &'static str,
+  pub data: SkillData,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct SkillListResponse {
+  pub status: &'static str,
+  pub results: usize,
+  pub skills: Vec<SkillResponse>,
+}
+
+/// The skill others were merged into, their names, and how many tasks and
+/// freelancers had their skills rewritten.
+#[derive(Serialize, Debug, Clone)]
+pub struct SkillMergeData {
+  pub skill: SkillResponse,
+  pub merged: Vec<String>,
+  pub tasks: u64,
+  pub freelancers: u64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct SkillMergeResponse {
+  pub status: &'static str,
+  pub data: SkillMergeData,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct HealthResponse {
+  pub status: &'static str,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct DependencyStatus {
+  pub status: &'static str,
+  pub latency_ms: u128,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub error: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ReadinessChecks {
+  pub mongodb: DependencyStatus,
+  pub migrations: DependencyStatus,
+  pub configuration: DependencyStatus,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ReadinessResponse {
+  pub status: &'static str,
+  pub checks: ReadinessChecks,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct FreelancerProfileData {
+  pub freelancer: FreelancerProfileResponse,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct SingleFreelancerProfileResponse {
+  pub status: &'static str,
+  pub data: FreelancerProfileData,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct CacheStatsResponse {
+  pub name: &'static str,
+  pub hits: u64,
+  pub misses: u64,
+  pub entries: usize,
+  pub capacity: usize,
+  pub ttl_secs: u64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct CacheStatsListResponse {
+  pub status: &'static str,
+  pub caches: Vec<CacheStatsResponse>,
+}
+
+/// A task with the relations picked by `expand`; those not expanded are left
+/// out, while `deal` is `null` when expanded but the task has none.
+#[derive(Serialize, Debug, Clone)]
+pub struct TaskDetailResponse {
+  #[serde(flatten)]
+  pub task: TaskResponse,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub proposals: Option<Vec<ProposalResponse>>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub milestones: Option<Vec<MilestoneResponse>>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub deal: Option<Option<DealResponse>>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub reviews: Option<Vec<ReviewResponse>>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct TaskDetailData {
+  pub task: TaskDetailResponse,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct SingleTaskDetailResponse {
+  pub status: &'static str,
+  pub data: TaskDetailData,
+}
+
+/// A deal with the relations picked by `expand`, like `TaskDetailResponse`.
+#[derive(Serialize, Debug, Clone)]
+pub struct DealDetailResponse {
+  #[serde(flatten)]
+  pub deal: DealResponse,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub task: Option<Option<TaskResponse>>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub proposal: Option<Option<ProposalResponse>>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub milestones: Option<Vec<MilestoneResponse>>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub reviews: Option<Vec<ReviewResponse>>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct DealDetailData {
+  pub deal: DealDetailResponse,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct SingleDealDetailResponse {
+  pub status: &'static str,
+  pub data: DealDetailData,
+}
+
+#[derive(Serialize, Debug)]
+pub struct JobResponse {
+  pub id: String,
+  pub kind: JobKind,
+  pub status: JobStatus,
+  pub run_at: DateTime<Utc>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub every_secs: Option<i64>,
+  pub attempts: i32,
+  pub max_attempts: i32,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub last_run_at: Option<DateTime<Utc>>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub last_result: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub last_error: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub lease_owner: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub lease_until: Option<DateTime<Utc>>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub finished_at: Option<DateTime<Utc>>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub created_at: Option<DateTime<Utc>>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct JobData {
+  pub job: JobResponse,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SingleJobResponse {
+  pub status: &'static str,
+  pub data: JobData,
+}
+
+#[derive(Serialize, Debug)]
+pub struct JobListResponse {
+  pub status: &'static str,
+  pub results: usize,
+  pub jobs: Vec<JobResponse>,
+}
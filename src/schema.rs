@@ -1,13 +1,167 @@
+use chrono::{DateTime, Utc};
+use mongodb::bson::serde_helpers::chrono_datetime_as_bson_datetime;
+use mongodb::bson::{doc, Document};
 use serde::{Deserialize, Serialize};
+use validator::{Validate, ValidationError, ValidationErrors};
 
-#[derive(Deserialize, Debug)]
-pub struct ParamOptions {
-  pub id: String,
+use crate::address::Address;
+use crate::error::MyError;
+use crate::job::{JobKind, JobStatus};
+use crate::lifecycle::{Lifecycle, TaskStatus};
+use crate::model::TaskVisibility;
+use crate::money::Money;
+use crate::utils::{deserialize_datetime, deserialize_optional_datetime};
+
+#[derive(Deserialize, Debug, Default)]
+pub struct ListOptions {
+  pub include_deleted: Option<bool>,
+  pub sort: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Deserialize, Debug, Default)]
+pub struct TaskListOptions {
+  pub include_deleted: Option<bool>,
+  pub skill: Option<String>,
+  pub sort: Option<String>,
+  pub status: Option<String>,
+}
+
+/// The fields lists can be sorted by, oldest first, or newest first with a
+/// `-` prefix.
+const SORTABLE: [&str; 2] = ["created_at", "updated_at"];
+
+/// The `sort` document of a `sort` query parameter, if any.
+pub fn sort_order(sort: Option<&str>) -> Result<Option<Document>, MyError> {
+  let Some(sort) = sort else {
+    return Ok(None);
+  };
+  let (field, direction) = match sort.strip_prefix('-') {
+    Some(field) => (field, -1),
+    None => (sort, 1),
+  };
+  if !SORTABLE.contains(&field) {
+    return Err(MyError::InvalidQueryError(format!(
+      "cannot sort by {}, expected one of {} with an optional - prefix",
+      sort,
+      SORTABLE.join(", ")
+    )));
+  }
+  // `_id` breaks ties.
+  Ok(Some(doc! {field: direction, "_id": direction}))
+}
+
+/// The task status a `status` query parameter lists: `Open` when absent,
+/// any status for `all`.
+pub fn task_status_filter(status: Option<&str>) -> Result<Option<TaskStatus>, MyError> {
+  let Some(status) = status else {
+    return Ok(Some(TaskStatus::Open));
+  };
+  if status.eq_ignore_ascii_case("all") {
+    return Ok(None);
+  }
+  match TaskStatus::ALL
+    .iter()
+    .find(|known| format!("{:?}", known).eq_ignore_ascii_case(status))
+  {
+    Some(known) => Ok(Some(*known)),
+    None => {
+      let known: Vec<String> = TaskStatus::ALL.iter().map(|s| format!("{:?}", s)).collect();
+      Err(MyError::InvalidQueryError(format!(
+        "unknown task status {}, expected all or one of {}",
+        status,
+        known.join(", ")
+      )))
+    }
+  }
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct JobListOptions {
+  pub status: Option<JobStatus>,
+  pub kind: Option<JobKind>,
+  pub limit: Option<i64>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct RecommendationOptions {
+  pub limit: Option<usize>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct SkillSearchOptions {
+  pub q: Option<String>,
+  pub limit: Option<i64>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct DetailOptions {
+  pub expand: Option<String>,
+}
+
+impl DetailOptions {
+  /// The comma separated relations of `expand`, each one of `allowed`. All of
+  /// them when there is no `expand`, none for an empty one.
+  pub fn relations(&self, allowed: &[&'static str]) -> Result<Vec<&'static str>, MyError> {
+    let expand = match &self.expand {
+      Some(expand) => expand,
+      None => return Ok(allowed.to_vec()),
+    };
+    expand
+      .split(',')
+      .map(str::trim)
+      .filter(|relation| !relation.is_empty())
+      .map(|relation| {
+        allowed
+          .iter()
+          .find(|allowed| **allowed == relation)
+          .copied()
+          .ok_or_else(|| {
+            MyError::InvalidQueryError(format!(
+              "cannot expand {}, expected one of {}",
+              relation,
+              allowed.join(", ")
+            ))
+          })
+      })
+      .collect()
+  }
+}
+
+/// At most this many skills on a task or a freelancer.
+pub const MAX_SKILLS: usize = 5;
+/// At most this many freelancers invited to a task.
+pub const MAX_INVITATIONS: usize = 50;
+/// At most this many milestones sent at once.
+pub const MAX_MILESTONES: usize = 20;
+
+/// Rejects a zero amount; `Money` can't be negative.
+fn positive(amount: &Money) -> Result<(), ValidationError> {
+  if amount.units() == 0 {
+    let mut error = ValidationError::new("positive");
+    error.message = Some(format!("must be more than 0 {}", amount.token()).into());
+    return Err(error);
+  }
+  Ok(())
+}
+
+/// Rejects blank skills and skills longer than 40 characters.
+fn skill_names(skills: &[String]) -> Result<(), ValidationError> {
+  if skills
+    .iter()
+    .any(|skill| skill.trim().is_empty() || skill.chars().count() > 40)
+  {
+    let mut error = ValidationError::new("skill");
+    error.message = Some("skills must be 1 to 40 characters, not blank".into());
+    return Err(error);
+  }
+  Ok(())
+}
+
+#[derive(Serialize, Deserialize, Debug, Validate)]
 pub struct CredentialUserSchema {
+  #[validate(length(min = 3, max = 32, message = "must be 3 to 32 characters"))]
   pub user_name: String,
+  #[validate(length(min = 8, max = 128, message = "must be 8 to 128 characters"))]
   pub password: String,
 }
 #[derive(Serialize, Deserialize, Debug)]
@@ -17,48 +171,111 @@ pub struct LoginUserSchema {
   pub credentials: CredentialUserSchema,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Validate)]
 pub struct CreateUserSchema {
   #[serde(rename(serialize = "_id"))]
-  pub id: String,
+  pub id: Address,
   #[serde(flatten)]
+  #[validate]
   pub credential: CredentialUserSchema,
   #[serde(skip_serializing_if = "Option::is_none")]
+  #[validate(length(max = 1000, message = "must be at most 1000 characters"))]
   pub description: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Validate)]
 pub struct CreateClientSchema {
   #[serde(flatten)]
+  #[validate]
   pub user: CreateUserSchema,
   pub task_ids: Option<Vec<String>>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Validate)]
 pub struct CreateFreelancerSchema {
   #[serde(flatten)]
+  #[validate]
   pub user: CreateUserSchema,
+  #[validate(
+    length(max = "MAX_SKILLS", message = "must have at most 5 skills"),
+    custom = "skill_names"
+  )]
   pub skills: Option<Vec<String>>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Validate)]
 pub struct CreateTaskSchema {
-  pub client_id: String,
+  pub client_id: Address,
+  #[validate(length(min = 1, max = 120, message = "must be 1 to 120 characters"))]
   pub title: String,
-  pub start_time: String,
-  pub deadline: String,
+  #[serde(
+    deserialize_with = "deserialize_datetime",
+    serialize_with = "chrono_datetime_as_bson_datetime::serialize"
+  )]
+  pub start_time: DateTime<Utc>,
+  #[serde(
+    deserialize_with = "deserialize_datetime",
+    serialize_with = "chrono_datetime_as_bson_datetime::serialize"
+  )]
+  pub deadline: DateTime<Utc>,
+  #[validate(length(min = 1, max = 5000, message = "must be 1 to 5000 characters"))]
   pub description: String,
+  #[validate(
+    length(min = 1, max = "MAX_SKILLS", message = "must have 1 to 5 skills"),
+    custom = "skill_names"
+  )]
   pub skills: Vec<String>,
-  pub bounty: u16,
+  #[validate(custom = "positive")]
+  pub bounty: Money,
   #[serde(skip_serializing_if = "Option::is_none")]
   pub proposals_id: Option<Vec<String>>,
+  #[serde(default)]
+  pub visibility: TaskVisibility,
+}
+
+/// The fields of a task to change; those left out stay as they are.
+#[derive(Deserialize, Debug, Clone, Default, Validate)]
+pub struct UpdateTaskSchema {
+  #[validate(length(min = 1, max = 120, message = "must be 1 to 120 characters"))]
+  pub title: Option<String>,
+  #[validate(length(min = 1, max = 5000, message = "must be 1 to 5000 characters"))]
+  pub description: Option<String>,
+  #[serde(default, deserialize_with = "deserialize_optional_datetime")]
+  pub start_time: Option<DateTime<Utc>>,
+  #[serde(default, deserialize_with = "deserialize_optional_datetime")]
+  pub deadline: Option<DateTime<Utc>>,
+  #[validate(
+    length(min = 1, max = "MAX_SKILLS", message = "must have 1 to 5 skills"),
+    custom = "skill_names"
+  )]
+  pub skills: Option<Vec<String>>,
+  #[validate(custom = "positive")]
+  pub bounty: Option<Money>,
+  pub visibility: Option<TaskVisibility>,
+}
+
+#[derive(Deserialize, Debug, Clone, Validate)]
+pub struct InviteFreelancersSchema {
+  #[validate(length(
+    min = 1,
+    max = "MAX_INVITATIONS",
+    message = "must have 1 to 50 freelancers"
+  ))]
+  pub freelancer_ids: Vec<Address>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Deserialize, Debug, Clone, Default, Validate)]
+pub struct CancelTaskSchema {
+  #[validate(length(min = 1, max = 500, message = "must be 1 to 500 characters"))]
+  pub reason: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Validate)]
 pub struct CreateProposalSchema {
-  pub client_id: String,
+  pub client_id: Address,
+  #[validate(length(min = 1, message = "must not be empty"))]
   pub task_id: String,
-  pub freelancer_id: String,
+  pub freelancer_id: Address,
   #[serde(skip_serializing_if = "Option::is_none")]
   pub milestones_id: Option<Vec<String>>,
   //pub price: u16,
@@ -74,19 +291,151 @@ pub struct CreateProposalSchema {
 //   pub milestones_id: Option<Vec<String>>,
 // }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Validate)]
 pub struct CreateMilestoneSchema {
+  #[validate(length(min = 1, message = "must not be empty"))]
   pub proposal_id: String,
+  #[validate(length(min = 1, max = 1000, message = "must be 1 to 1000 characters"))]
   pub description: String,
-  pub deadline: String,
-  pub price: f64,
+  #[serde(
+    deserialize_with = "deserialize_datetime",
+    serialize_with = "chrono_datetime_as_bson_datetime::serialize"
+  )]
+  pub deadline: DateTime<Utc>,
+  #[validate(custom = "positive")]
+  pub price: Money,
+}
+
+/// The milestones added to a proposal, sent as a bare JSON array. Errors are
+/// reported under `milestones[i]`.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(transparent)]
+pub struct CreateMilestonesSchema(pub Vec<CreateMilestoneSchema>);
+
+impl Validate for CreateMilestonesSchema {
+  fn validate(&self) -> Result<(), ValidationErrors> {
+    if self.0.is_empty() || self.0.len() > MAX_MILESTONES {
+      let mut errors = ValidationErrors::new();
+      let mut error = ValidationError::new("length");
+      error.message = Some(format!("must have 1 to {} milestones", MAX_MILESTONES).into());
+      errors.add("milestones", error);
+      return Err(errors);
+    }
+    let children = self
+      .0
+      .iter()
+      .map(|milestone| ValidationErrors::merge(Ok(()), "milestones", milestone.validate()))
+      .collect();
+    ValidationErrors::merge_all(Ok(()), "milestones", children)
+  }
+}
+
+/// The status a milestone or deal moves to.
+#[derive(Deserialize, Debug, Clone)]
+pub struct TransitionSchema<S> {
+  pub status: S,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Validate)]
 pub struct CreateReviewSchema {
-  pub freelancer_id: String,
-  pub client_id: String,
+  pub freelancer_id: Address,
+  pub client_id: Address,
+  #[validate(length(min = 1, message = "must not be empty"))]
   pub deal_id: String,
+  #[validate(length(min = 1, max = 2000, message = "must be 1 to 2000 characters"))]
   pub review: String,
+  #[validate(range(min = 1, max = 5, message = "must be 1 to 5"))]
   pub stars: u16,
 }
+
+#[derive(Deserialize, Debug, Clone, Validate)]
+pub struct CreateSkillSchema {
+  #[validate(length(min = 1, max = 40, message = "must be 1 to 40 characters"))]
+  pub name: String,
+  #[validate(length(min = 1, max = 40, message = "must be 1 to 40 characters"))]
+  pub category: Option<String>,
+  #[serde(default)]
+  #[validate(custom = "skill_names")]
+  pub aliases: Vec<String>,
+}
+
+/// Folds the skills named in `from` into the one named `into`.
+#[derive(Deserialize, Debug, Clone, Validate)]
+pub struct MergeSkillsSchema {
+  #[validate(
+    length(min = 1, message = "must name at least one skill"),
+    custom = "skill_names"
+  )]
+  pub from: Vec<String>,
+  #[validate(length(min = 1, max = 40, message = "must be 1 to 40 characters"))]
+  pub into: String,
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const RELATIONS: [&str; 3] = ["proposals", "deal", "reviews"];
+
+  fn relations(expand: Option<&str>) -> Result<Vec<&'static str>, MyError> {
+    DetailOptions {
+      expand: expand.map(str::to_string),
+    }
+    .relations(&RELATIONS)
+  }
+
+  #[test]
+  fn expands_the_listed_relations() {
+    assert_eq!(relations(None).unwrap(), RELATIONS);
+    assert_eq!(relations(Some("")).unwrap(), Vec::<&str>::new());
+    assert_eq!(
+      relations(Some(" deal, ,proposals ")).unwrap(),
+      ["deal", "proposals"]
+    );
+  }
+
+  #[test]
+  fn rejects_unknown_relations() {
+    for expand in ["milestones", "deal,Reviews", "deals"] {
+      assert!(
+        matches!(relations(Some(expand)), Err(MyError::InvalidQueryError(_))),
+        "{}",
+        expand
+      );
+    }
+  }
+
+  #[test]
+  fn sorts_by_known_fields_with_id_breaking_ties() {
+    assert_eq!(sort_order(None).unwrap(), None);
+    assert_eq!(
+      sort_order(Some("created_at")).unwrap(),
+      Some(doc! {"created_at": 1, "_id": 1})
+    );
+    assert_eq!(
+      sort_order(Some("-updated_at")).unwrap(),
+      Some(doc! {"updated_at": -1, "_id": -1})
+    );
+    for sort in ["deadline", "--created_at", "created_at-", ""] {
+      assert!(
+        matches!(sort_order(Some(sort)), Err(MyError::InvalidQueryError(_))),
+        "{}",
+        sort
+      );
+    }
+  }
+
+  #[test]
+  fn filters_task_statuses() {
+    assert_eq!(task_status_filter(None).unwrap(), Some(TaskStatus::Open));
+    assert_eq!(task_status_filter(Some("ALL")).unwrap(), None);
+    assert_eq!(
+      task_status_filter(Some("inprogress")).unwrap(),
+      Some(TaskStatus::InProgress)
+    );
+    assert!(matches!(
+      task_status_filter(Some("in_progress")),
+      Err(MyError::InvalidQueryError(_))
+    ));
+  }
+}
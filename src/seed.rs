@@ -0,0 +1,273 @@
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::address::Address;
+use crate::db::{Result, DB};
+use crate::lifecycle::DealStatus;
+use crate::model::TaskVisibility;
+use crate::money::{Money, Token};
+use crate::schema::{
+  CreateClientSchema, CreateFreelancerSchema, CreateMilestoneSchema, CreateProposalSchema,
+  CreateReviewSchema, CreateSkillSchema, CreateTaskSchema, CreateUserSchema, CredentialUserSchema,
+};
+use crate::web::ctx::Ctx;
+
+const FIRST_NAMES: [&str; 12] = [
+  "Ada", "Linus", "Grace", "Satoshi", "Vitalik", "Barbara", "Ken", "Margaret", "Dennis", "Radia",
+  "Guido", "Frances",
+];
+const COMPANIES: [&str; 8] = [
+  "Scroll",
+  "Linea",
+  "Aave",
+  "Uniswap",
+  "Gnosis",
+  "Lido",
+  "Optimism",
+  "Chainlink",
+];
+/// Skills and their category, added to the taxonomy first.
+const SKILLS: [(&str, &str); 10] = [
+  ("Rust", "Language"),
+  ("Solidity", "Language"),
+  ("React", "Front end"),
+  ("TypeScript", "Language"),
+  ("Go", "Language"),
+  ("Python", "Language"),
+  ("Auditing", "Security"),
+  ("Design", "Design"),
+  ("DevOps", "Infrastructure"),
+  ("Zero-knowledge", "Cryptography"),
+];
+const DELIVERABLES: [&str; 8] = [
+  "Back end",
+  "Smart contract",
+  "Landing page",
+  "Indexer",
+  "Security audit",
+  "Dashboard",
+  "Bridge integration",
+  "CI pipeline",
+];
+const REVIEWS: [&str; 5] = [
+  "Would not hire again",
+  "Missed most deadlines",
+  "Did the job",
+  "Great communication",
+  "Outstanding work, delivered early",
+];
+/// Every generated password, so seeded users can log in.
+pub const PASSWORD: &str = "password";
+
+/// How much to generate. The same options against an empty database always
+/// produce the same documents.
+#[derive(Debug)]
+pub struct SeedOptions {
+  pub clients: usize,
+  pub freelancers: usize,
+  pub tasks: usize,
+  pub seed: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct SeedReport {
+  pub skills: usize,
+  pub clients: usize,
+  pub freelancers: usize,
+  pub tasks: usize,
+  pub proposals: usize,
+  pub milestones: usize,
+  pub deals: usize,
+  pub reviews: usize,
+}
+
+/// A random wallet address, like the ids the front end registers with.
+fn address(rng: &mut ChaCha8Rng) -> Address {
+  Address::from_bytes(rng.gen())
+}
+
+/// A midnight UTC up to `max_days` after `from`.
+fn day(rng: &mut ChaCha8Rng, from: DateTime<Utc>, max_days: i64) -> DateTime<Utc> {
+  from + Duration::days(rng.gen_range(0..=max_days))
+}
+
+/// `value` whole stablecoins, or thousandths of an ETH.
+fn money(token: Token, value: u32) -> Result<Money> {
+  match token {
+    Token::Eth => Money::parse(&format!("{}.{:03}", value / 1000, value % 1000), token),
+    _ => Money::parse(&value.to_string(), token),
+  }
+}
+
+/// Fills the database through the same `DB` methods the API uses: skills,
+/// clients, freelancers, tasks, proposals with their milestones, the deals of the
+/// approved proposals and reviews of some of those deals. Meant for an empty
+/// database; user ids and names collide otherwise.
+pub async fn seed(db: &DB, options: &SeedOptions) -> Result<SeedReport> {
+  let mut rng = ChaCha8Rng::seed_from_u64(options.seed);
+  let mut report = SeedReport::default();
+  let epoch = Utc
+    .with_ymd_and_hms(2023, 1, 1, 0, 0, 0)
+    .single()
+    .unwrap_or_default();
+
+  let admin = Ctx::new("seed".to_string());
+  for (name, category) in SKILLS {
+    let body = CreateSkillSchema {
+      name: name.to_string(),
+      category: Some(category.to_string()),
+      aliases: vec![],
+    };
+    db.create_skill(&body, &admin).await?;
+    report.skills += 1;
+  }
+
+  let mut clients = Vec::new();
+  for i in 0..options.clients {
+    let company = COMPANIES[i % COMPANIES.len()];
+    let body = CreateClientSchema {
+      user: CreateUserSchema {
+        id: address(&mut rng),
+        credential: CredentialUserSchema {
+          user_name: format!("{}{}", company, i),
+          password: PASSWORD.to_string(),
+        },
+        description: Some(format!("{} core team", company)),
+      },
+      task_ids: None,
+    };
+    db.add_client(&body).await?;
+    clients.push(body.user.id);
+    report.clients += 1;
+  }
+
+  let mut freelancers = Vec::new();
+  for i in 0..options.freelancers {
+    let count = rng.gen_range(1..=3);
+    let skills: Vec<String> = SKILLS
+      .choose_multiple(&mut rng, count)
+      .map(|(skill, _)| skill.to_string())
+      .collect();
+    let body = CreateFreelancerSchema {
+      user: CreateUserSchema {
+        id: address(&mut rng),
+        credential: CredentialUserSchema {
+          user_name: format!("{}{}", FIRST_NAMES[i % FIRST_NAMES.len()], i),
+          password: PASSWORD.to_string(),
+        },
+        description: Some(format!("{} developer", skills.join(" and "))),
+      },
+      skills: Some(skills),
+    };
+    db.add_freelancer(&body).await?;
+    freelancers.push(body.user.id);
+    report.freelancers += 1;
+  }
+
+  if clients.is_empty() {
+    return Ok(report);
+  }
+
+  for _ in 0..options.tasks {
+    let client_id = clients[rng.gen_range(0..clients.len())].clone();
+    let deliverable = DELIVERABLES[rng.gen_range(0..DELIVERABLES.len())];
+    let count = rng.gen_range(1..=3);
+    let skills: Vec<String> = SKILLS
+      .choose_multiple(&mut rng, count)
+      .map(|(skill, _)| skill.to_string())
+      .collect();
+    let token = Token::ALL[rng.gen_range(0..Token::ALL.len())];
+    let start = day(&mut rng, epoch, 300);
+    let deadline = day(&mut rng, start + Duration::days(7), 90);
+    let body = CreateTaskSchema {
+      client_id: client_id.clone(),
+      title: format!("{} in {}", deliverable, skills[0]),
+      start_time: start,
+      deadline,
+      description: format!(
+        "{} needed, {} experience required",
+        deliverable,
+        skills.join(", ")
+      ),
+      skills,
+      bounty: money(token, rng.gen_range(1..=50) * 100)?,
+      proposals_id: None,
+      visibility: TaskVisibility::Public,
+    };
+    // Everything is created as its author would through the API.
+    let client = Ctx::new(client_id.to_string());
+    let task_id = db.create_task(&body, &client).await?.data.task.id;
+    report.tasks += 1;
+
+    let count = rng.gen_range(0..=3);
+    let bidders: Vec<Address> = freelancers
+      .choose_multiple(&mut rng, count)
+      .cloned()
+      .collect();
+    let mut proposals = Vec::new();
+    for freelancer_id in bidders {
+      let body = CreateProposalSchema {
+        client_id: client_id.clone(),
+        task_id: task_id.clone(),
+        freelancer_id: freelancer_id.clone(),
+        milestones_id: None,
+      };
+      let freelancer = Ctx::new(freelancer_id.to_string());
+      let proposal_id = db
+        .submit_proposal(&body, &freelancer)
+        .await?
+        .data
+        .proposal
+        .id;
+      report.proposals += 1;
+
+      let milestones: Vec<CreateMilestoneSchema> = (1..=rng.gen_range(1..=3))
+        .map(|step| {
+          Ok(CreateMilestoneSchema {
+            proposal_id: proposal_id.clone(),
+            description: format!("Milestone {}", step),
+            deadline: day(&mut rng, start, (deadline - start).num_days()),
+            price: money(token, rng.gen_range(1..=20) * 50)?,
+          })
+        })
+        .collect::<Result<_>>()?;
+      db.add_milestones(&milestones, &freelancer).await?;
+      report.milestones += milestones.len();
+      proposals.push((proposal_id, freelancer_id));
+    }
+
+    // Roughly half the tasks with bids get one approved.
+    if proposals.is_empty() || !rng.gen_bool(0.5) {
+      continue;
+    }
+    let (proposal_id, freelancer_id) = proposals.swap_remove(rng.gen_range(0..proposals.len()));
+    let deal_id = db
+      .approve_proposal(&proposal_id, &client, None)
+      .await?
+      .data
+      .deal
+      .id;
+    report.deals += 1;
+
+    // Reviews are for completed deals.
+    if rng.gen_bool(0.6) {
+      for to in [DealStatus::Funded, DealStatus::Completed] {
+        db.transition_deal(&deal_id, to, &client, None).await?;
+      }
+      let stars = rng.gen_range(1..=5);
+      let body = CreateReviewSchema {
+        freelancer_id,
+        client_id: client_id.clone(),
+        deal_id,
+        review: REVIEWS[usize::from(stars) - 1].to_string(),
+        stars,
+      };
+      db.add_review(&body, &client).await?;
+      report.reviews += 1;
+    }
+  }
+
+  Ok(report)
+}
@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+
+use crate::model::SkillModel;
+
+/// The form skills are matched by: trimmed and lowercase, with runs of
+/// whitespace, `-` and `_` collapsed to one space, so "Zero-knowledge" and
+/// "zero knowledge" match.
+pub fn skill_key(name: &str) -> String {
+  name
+    .split(|c: char| c.is_whitespace() || c == '-' || c == '_')
+    .filter(|word| !word.is_empty())
+    .map(str::to_lowercase)
+    .collect::<Vec<_>>()
+    .join(" ")
+}
+
+/// The `_id` of the skill named `name`: its key with dashes for spaces.
+pub fn skill_id(name: &str) -> String {
+  skill_key(name).replace(' ', "-")
+}
+
+/// A regular expression matching the keys starting with `prefix`.
+pub fn prefix_pattern(prefix: &str) -> String {
+  format!("^{}", escape(prefix))
+}
+
+/// A regular expression matching, case aside, the skill names whose key is
+/// `key`. To be used with the `i` option.
+pub fn key_pattern(key: &str) -> String {
+  let words: Vec<String> = key.split(' ').map(escape).collect();
+  format!("^[\\s_-]*{}[\\s_-]*$", words.join("[\\s_-]+"))
+}
+
+fn escape(text: &str) -> String {
+  text
+    .chars()
+    .map(|c| match c {
+      '\\' | '^' | '$' | '.' | '|' | '?' | '*' | '+' | '(' | ')' | '[' | ']' | '{' | '}' => {
+        format!("\\{}", c)
+      }
+      _ => c.to_string(),
+    })
+    .collect()
+}
+
+/// Canonical skill names by the keys of their names and aliases.
+#[derive(Debug, Default)]
+pub struct Taxonomy(HashMap<String, String>);
+
+impl Taxonomy {
+  pub fn new(skills: &[SkillModel]) -> Self {
+    let mut names = HashMap::new();
+    for skill in skills {
+      for key in &skill.keys {
+        names.insert(key.to_owned(), skill.name.to_owned());
+      }
+    }
+    Taxonomy(names)
+  }
+
+  /// The canonical name of `skill`, or `skill` with its whitespace collapsed
+  /// when the taxonomy doesn't know it.
+  pub fn canonical(&self, skill: &str) -> String {
+    match self.0.get(&skill_key(skill)) {
+      Some(name) => name.to_owned(),
+      None => skill.split_whitespace().collect::<Vec<_>>().join(" "),
+    }
+  }
+
+  /// `skills` by their canonical names, without duplicates, in order.
+  pub fn canonical_all(&self, skills: &[String]) -> Vec<String> {
+    let mut canonical: Vec<String> = Vec::with_capacity(skills.len());
+    for skill in skills {
+      let name = self.canonical(skill);
+      if !canonical
+        .iter()
+        .any(|seen| skill_key(seen) == skill_key(&name))
+      {
+        canonical.push(name);
+      }
+    }
+    canonical
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn skill(name: &str, aliases: &[&str]) -> SkillModel {
+    let aliases: Vec<String> = aliases.iter().map(|alias| alias.to_string()).collect();
+    let keys = std::iter::once(name)
+      .chain(aliases.iter().map(String::as_str))
+      .map(skill_key)
+      .collect();
+    SkillModel {
+      id: skill_id(name),
+      name: name.to_string(),
+      category: None,
+      aliases,
+      keys,
+      merges: Vec::new(),
+      version: 0,
+      created_at: None,
+      updated_at: None,
+      created_by: None,
+    }
+  }
+
+  fn names(skills: &[&str]) -> Vec<String> {
+    skills.iter().map(|skill| skill.to_string()).collect()
+  }
+
+  #[test]
+  fn keys_collapse_case_and_separators() {
+    assert_eq!(
+      skill_key("  Zero-Knowledge__Proofs "),
+      "zero knowledge proofs"
+    );
+    assert_eq!(skill_key("zero knowledge\tproofs"), "zero knowledge proofs");
+    assert_eq!(skill_id("Zero knowledge"), "zero-knowledge");
+    assert_eq!(skill_key(" - _ "), "");
+  }
+
+  #[test]
+  fn escapes_regex_metacharacters() {
+    assert_eq!(escape("c++"), "c\\+\\+");
+    assert_eq!(escape("node.js (v18)"), "node\\.js \\(v18\\)");
+    assert_eq!(escape("a\\b|^$?*[]{}"), "a\\\\b\\|\\^\\$\\?\\*\\[\\]\\{\\}");
+    assert_eq!(escape("solidity-0_8"), "solidity-0_8");
+    assert_eq!(prefix_pattern("c#."), "^c#\\.");
+  }
+
+  #[test]
+  fn key_patterns_allow_any_separators_between_words() {
+    assert_eq!(key_pattern("rust"), "^[\\s_-]*rust[\\s_-]*$");
+    assert_eq!(
+      key_pattern("node.js dev"),
+      "^[\\s_-]*node\\.js[\\s_-]+dev[\\s_-]*$"
+    );
+  }
+
+  #[test]
+  fn canonicalizes_names_and_aliases() {
+    let taxonomy = Taxonomy::new(&[
+      skill("Zero-knowledge", &["ZK", "zkp"]),
+      skill("Solidity", &[]),
+    ]);
+    assert_eq!(taxonomy.canonical("zero knowledge"), "Zero-knowledge");
+    assert_eq!(taxonomy.canonical(" zk "), "Zero-knowledge");
+    assert_eq!(taxonomy.canonical("SOLIDITY"), "Solidity");
+    assert_eq!(taxonomy.canonical("  Move   lang "), "Move lang");
+  }
+
+  #[test]
+  fn dedups_keeping_the_first_occurrence_in_order() {
+    let taxonomy = Taxonomy::new(&[skill("Zero-knowledge", &["zkp"])]);
+    assert_eq!(
+      taxonomy.canonical_all(&names(&[
+        "Rust",
+        "zkp",
+        "rust",
+        "Move",
+        "zero_knowledge",
+        "move"
+      ])),
+      names(&["Rust", "Zero-knowledge", "Move"])
+    );
+    assert!(Taxonomy::default().canonical_all(&[]).is_empty());
+  }
+}
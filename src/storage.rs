@@ -0,0 +1,134 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use axum::async_trait;
+use axum::body::Bytes;
+use object_store::aws::{AmazonS3, AmazonS3Builder};
+use object_store::path::Path;
+use object_store::ObjectStore;
+
+use crate::db::Result;
+use crate::error::MyError::{self, NotFoundError, StorageError};
+
+/// Where uploaded files are kept, by a key the server picks.
+#[async_trait]
+pub trait Storage: Send + Sync {
+  async fn put(&self, key: &str, bytes: Bytes) -> Result<()>;
+  async fn get(&self, key: &str) -> Result<Bytes>;
+  /// Succeeds when nothing is stored under `key`.
+  async fn delete(&self, key: &str) -> Result<()>;
+}
+
+/// The backend named by `STORAGE_BACKEND`: `local` (the default) keeps files
+/// under `STORAGE_DIR` (`uploads` by default), `s3` in the `S3_BUCKET` of
+/// any S3-compatible service configured by the usual `AWS_*` variables.
+pub fn from_env() -> Result<Arc<dyn Storage>> {
+  let backend = std::env::var("STORAGE_BACKEND").unwrap_or_else(|_| "local".to_string());
+  match backend.as_str() {
+    "local" => {
+      let root = std::env::var("STORAGE_DIR").unwrap_or_else(|_| "uploads".to_string());
+      Ok(Arc::new(LocalStorage::new(root)))
+    }
+    "s3" => {
+      let bucket = std::env::var("S3_BUCKET")
+        .map_err(|_| StorageError("S3_BUCKET must be set for the s3 backend".to_string()))?;
+      Ok(Arc::new(S3Storage::new(&bucket)?))
+    }
+    other => Err(StorageError(format!(
+      "unknown STORAGE_BACKEND {}, expected local or s3",
+      other
+    ))),
+  }
+}
+
+/// Files in a directory of the server's filesystem, one per key.
+pub struct LocalStorage {
+  root: PathBuf,
+}
+
+impl LocalStorage {
+  pub fn new(root: impl Into<PathBuf>) -> Self {
+    Self { root: root.into() }
+  }
+
+  fn path(&self, key: &str) -> Result<PathBuf> {
+    if key.starts_with('/') || key.split('/').any(|part| part.is_empty() || part == "..") {
+      return Err(StorageError(format!("invalid key {}", key)));
+    }
+    Ok(self.root.join(key))
+  }
+}
+
+#[async_trait]
+impl Storage for LocalStorage {
+  async fn put(&self, key: &str, bytes: Bytes) -> Result<()> {
+    let path = self.path(key)?;
+    if let Some(parent) = path.parent() {
+      tokio::fs::create_dir_all(parent).await?;
+    }
+    tokio::fs::write(path, bytes).await?;
+    Ok(())
+  }
+
+  async fn get(&self, key: &str) -> Result<Bytes> {
+    match tokio::fs::read(self.path(key)?).await {
+      Ok(bytes) => Ok(Bytes::from(bytes)),
+      Err(e) if e.kind() == std::io::ErrorKind::NotFound => Err(NotFoundError(key.to_string())),
+      Err(e) => Err(e.into()),
+    }
+  }
+
+  async fn delete(&self, key: &str) -> Result<()> {
+    match tokio::fs::remove_file(self.path(key)?).await {
+      Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+      _ => Ok(()),
+    }
+  }
+}
+
+/// Objects in a bucket of S3 or a compatible service (MinIO, R2...), set
+/// with `AWS_ENDPOINT` and `AWS_ALLOW_HTTP` for the latter.
+pub struct S3Storage {
+  store: AmazonS3,
+}
+
+impl S3Storage {
+  pub fn new(bucket: &str) -> Result<Self> {
+    let store = AmazonS3Builder::from_env()
+      .with_bucket_name(bucket)
+      .build()
+      .map_err(storage_error)?;
+    Ok(Self { store })
+  }
+}
+
+fn storage_error(e: object_store::Error) -> MyError {
+  StorageError(e.to_string())
+}
+
+#[async_trait]
+impl Storage for S3Storage {
+  async fn put(&self, key: &str, bytes: Bytes) -> Result<()> {
+    self
+      .store
+      .put(&Path::from(key), bytes)
+      .await
+      .map_err(storage_error)?;
+    Ok(())
+  }
+
+  async fn get(&self, key: &str) -> Result<Bytes> {
+    match self.store.get(&Path::from(key)).await {
+      Ok(object) => object.bytes().await.map_err(storage_error),
+      Err(object_store::Error::NotFound { .. }) => Err(NotFoundError(key.to_string())),
+      Err(e) => Err(storage_error(e)),
+    }
+  }
+
+  async fn delete(&self, key: &str) -> Result<()> {
+    match self.store.delete(&Path::from(key)).await {
+      Err(object_store::Error::NotFound { .. }) => Ok(()),
+      result => result.map_err(storage_error),
+    }
+  }
+}
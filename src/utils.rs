@@ -1,24 +1,141 @@
 use crate::db::Result;
-use crate::error::MyError::MongoSerializeBsonError;
+use crate::error::MyError::{MalformedDocumentError, MongoSerializeBsonError};
+use crate::lifecycle::{DealStatus, MilestoneStatus, StatusChange, TaskStatus};
 use crate::model::{
-  ClientModel, DealModel, FreelancerModel, MilestoneModel, ProposalModel, ReviewModel, TaskModel,
+  Attachment, ClientModel, DealModel, FreelancerModel, JobModel, MilestoneModel, ProposalModel,
+  ReviewModel, SkillModel, TaskFields, TaskModel,
 };
+use crate::money::Money;
 use crate::response::{
-  ClientResponse, DealResponse, FreelancerResponse, MilestoneResponse, PartialDealResponse,
-  ProposalDetailedResponse, ProposalResponse, ReviewResponse, TaskResponse,
+  AttachmentResponse, ClientResponse, DealResponse, FreelancerResponse, InvitationResponse,
+  JobResponse, MilestoneResponse, PartialDealResponse, ProposalDetailedResponse, ProposalResponse,
+  ReviewResponse, SkillResponse, StatusChangeResponse, TaskEditResponse, TaskFieldsResponse,
+  TaskResponse,
 };
 use crate::schema::{
-  CreateClientSchema, CreateMilestoneSchema, CreateProposalSchema, CreateReviewSchema,
+  CreateMilestoneSchema, CreateProposalSchema, CreateReviewSchema, CreateSkillSchema,
   CreateTaskSchema,
 };
+use crate::skill::{skill_id, skill_key};
 use crate::{model::UserModel, response::UserResponse, schema::CreateUserSchema};
-use mongodb::bson::{self, doc, Document};
+use chrono::{DateTime, NaiveDate, Utc};
+use mongodb::bson::{self, doc, to_bson, Bson, DateTime as BsonDateTime, Document};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer, Serialize};
+
+/// Day-only formats sent before schedules were datetimes, read as midnight UTC.
+const LEGACY_DAY_FORMATS: [&str; 3] = ["%d/%m/%Y", "%d-%m-%Y", "%Y-%m-%d"];
+
+/// An RFC 3339 datetime, or a day in one of the legacy formats.
+pub fn parse_datetime(value: &str) -> Option<DateTime<Utc>> {
+  if let Ok(datetime) = DateTime::parse_from_rfc3339(value) {
+    return Some(datetime.with_timezone(&Utc));
+  }
+  LEGACY_DAY_FORMATS
+    .iter()
+    .find_map(|format| NaiveDate::parse_from_str(value, format).ok())
+    .and_then(|day| day.and_hms_opt(0, 0, 0))
+    .map(|midnight| DateTime::from_naive_utc_and_offset(midnight, Utc))
+}
+
+/// Serde `deserialize_with` counterpart of `parse_datetime`.
+pub fn deserialize_datetime<'de, D: Deserializer<'de>>(
+  deserializer: D,
+) -> std::result::Result<DateTime<Utc>, D::Error> {
+  let value = String::deserialize(deserializer)?;
+  parse_datetime(&value).ok_or_else(|| {
+    serde::de::Error::custom(format!(
+      "invalid datetime {:?}, expected RFC 3339, dd/mm/yyyy, dd-mm-yyyy or yyyy-mm-dd",
+      value
+    ))
+  })
+}
+
+/// `deserialize_datetime` for an optional field, to be used with
+/// `#[serde(default)]`.
+pub fn deserialize_optional_datetime<'de, D: Deserializer<'de>>(
+  deserializer: D,
+) -> std::result::Result<Option<DateTime<Utc>>, D::Error> {
+  deserialize_datetime(deserializer).map(Some)
+}
+
+/// Filter excluding soft-deleted documents, unless `include_deleted` is set.
+pub fn active_filter(include_deleted: bool) -> Document {
+  if include_deleted {
+    doc! {}
+  } else {
+    doc! {"deleted_at": null}
+  }
+}
+
+/// Adds the `If-Match` version, if any, to an update filter. Documents written
+/// before versioning have no `version` field and count as version 0.
+pub fn with_version(filter: &Document, expected_version: Option<i64>) -> Document {
+  let mut filter = filter.clone();
+  if let Some(version) = expected_version {
+    let version: Bson = if version == 0 {
+      doc! {"$exists": false}.into()
+    } else {
+      version.into()
+    };
+    filter.insert("version", version);
+  }
+  filter
+}
+
+/// A `$lookup` stage embedding, as `as_field`, the live documents of `from`
+/// whose `foreign_field` equals `local`, or is one of the ids in `local` when
+/// `local` holds an array.
+pub fn lookup(from: &str, local: &str, foreign_field: &str, as_field: &str) -> Document {
+  let matches = doc! {"$in": [
+    format!("${}", foreign_field),
+    {"$cond": [{"$isArray": "$$local"}, "$$local", ["$$local"]]},
+  ]};
+  doc! {"$lookup": {
+    "from": from,
+    "let": {"local": format!("${}", local)},
+    "pipeline": [{"$match": {"deleted_at": null, "$expr": matches}}],
+    "as": as_field,
+  }}
+}
+
+/// Decodes an aggregation result, or one of the documents embedded in it.
+pub fn from_document<T: DeserializeOwned>(document: &Document) -> Result<T> {
+  bson::from_document(document.clone()).map_err(|e| MalformedDocumentError(e.to_string()))
+}
+
+/// Decodes the documents a `lookup` embedded as `field`.
+pub fn embedded<T: DeserializeOwned>(document: &Document, field: &str) -> Result<Vec<T>> {
+  match document.get_array(field) {
+    Ok(items) => items
+      .iter()
+      .map(|item| match item {
+        Bson::Document(item) => from_document(item),
+        _ => Err(MalformedDocumentError(format!(
+          "{} holds something else than documents",
+          field
+        ))),
+      })
+      .collect(),
+    Err(_) => Ok(Vec::new()),
+  }
+}
+
+/// Serializes a request body or model into the document stored for it.
+pub fn to_document<T: Serialize>(value: &T) -> Result<Document> {
+  match bson::to_bson(value).map_err(MongoSerializeBsonError)? {
+    Bson::Document(document) => Ok(document),
+    other => Err(MalformedDocumentError(format!(
+      "expected a document, got {:?}",
+      other.element_type()
+    ))),
+  }
+}
 
 fn build_user_document(body: &CreateUserSchema, description: String) -> Result<bson::Document> {
-  let serialized_data = bson::to_bson(body).map_err(MongoSerializeBsonError)?;
-  let document = serialized_data.as_document().unwrap();
-  let mut doc_with_description = doc! {"description": description};
-  doc_with_description.extend(document.clone());
+  let document = to_document(body)?;
+  let mut doc_with_description = doc! {"description": description, "version": 1};
+  doc_with_description.extend(document);
 
   Ok(doc_with_description)
 }
@@ -26,11 +143,11 @@ fn build_user_document(body: &CreateUserSchema, description: String) -> Result<b
 pub fn build_client_document(
   body: &CreateUserSchema,
   description: String,
-  taks_ids: Vec<String>,
+  tasks_ids: Vec<String>,
 ) -> Result<bson::Document> {
-  let document = build_user_document(body, description);
-  let mut doc_with_tasks = doc! {"tasks_id": taks_ids};
-  doc_with_tasks.extend(document.unwrap());
+  let document = build_user_document(body, description)?;
+  let mut doc_with_tasks = doc! {"tasks_ids": tasks_ids};
+  doc_with_tasks.extend(document);
 
   Ok(doc_with_tasks)
 }
@@ -40,9 +157,9 @@ pub fn build_freelancer_document(
   description: String,
   skills: Vec<String>,
 ) -> Result<bson::Document> {
-  let document = build_user_document(body, description);
+  let document = build_user_document(body, description)?;
   let mut doc_with_skills = doc! {"skills": skills};
-  doc_with_skills.extend(document.unwrap());
+  doc_with_skills.extend(document);
 
   Ok(doc_with_skills)
 }
@@ -51,8 +168,11 @@ pub fn doc_to_user_response(user: &UserModel, role: &String) -> Result<UserRespo
     role: role.to_owned(),
     id: user.id.to_owned(),
     user_name: user.user_name.to_owned(),
-    description: user.description.to_owned().unwrap(),
+    description: user.description.to_owned().unwrap_or_default(),
     password: user.password.to_owned(),
+    version: user.version,
+    deleted_at: user.deleted_at.map(|d| d.to_chrono()),
+    deleted_by: user.deleted_by.to_owned(),
   };
 
   Ok(user_response)
@@ -60,7 +180,7 @@ pub fn doc_to_user_response(user: &UserModel, role: &String) -> Result<UserRespo
 pub fn doc_to_client_response(client: &ClientModel) -> Result<ClientResponse> {
   let tasks_ids = client.tasks_ids.to_owned().unwrap_or_default();
   let role = String::from("client");
-  let user_response = doc_to_user_response(&client.user, &role).unwrap();
+  let user_response = doc_to_user_response(&client.user, &role)?;
   let client_response = ClientResponse {
     user: user_response,
     tasks_ids,
@@ -69,7 +189,7 @@ pub fn doc_to_client_response(client: &ClientModel) -> Result<ClientResponse> {
 }
 
 pub fn doc_to_freelancer_response(freelancer: &FreelancerModel) -> Result<FreelancerResponse> {
-  let user_response = doc_to_user_response(&freelancer.user, &String::from("freelancer")).unwrap();
+  let user_response = doc_to_user_response(&freelancer.user, &String::from("freelancer"))?;
   let skills = freelancer.skills.to_owned().unwrap_or_default();
   let freelancer_response = FreelancerResponse {
     user: user_response,
@@ -79,29 +199,58 @@ pub fn doc_to_freelancer_response(freelancer: &FreelancerModel) -> Result<Freela
   Ok(freelancer_response)
 }
 
-pub fn build_task_document(body: &CreateTaskSchema, _id: String) -> Result<bson::Document> {
-  let serialized_data = bson::to_bson(body).map_err(MongoSerializeBsonError)?;
-  let document = serialized_data.as_document().unwrap();
-  let mut doc_with_id = doc! {"_id": _id};
-  doc_with_id.extend(document.clone());
+/// `created_at`, `updated_at` and `created_by` of a document inserted now.
+pub fn created_by(actor: &str) -> Document {
+  let now = BsonDateTime::now();
+  doc! {"created_at": now, "updated_at": now, "created_by": actor}
+}
+
+pub fn build_task_document(
+  body: &CreateTaskSchema,
+  _id: String,
+  actor: &str,
+) -> Result<bson::Document> {
+  let document = to_document(body)?;
+  let mut doc_with_id = doc! {"_id": _id, "version": 1};
+  doc_with_id.extend(document);
+  doc_with_id.extend(created_by(actor));
+  // Maintained by `submit_proposal`, whatever the client sent.
+  doc_with_id.insert("proposals_id", Vec::<String>::new());
+  doc_with_id.insert("status", to_bson(&TaskStatus::Open)?);
+  doc_with_id.insert("history", Vec::<bson::Document>::new());
+  doc_with_id.insert("invitations", Vec::<bson::Document>::new());
   Ok(doc_with_id)
 }
 
-pub fn build_proposal_document(body: &CreateProposalSchema, _id: String) -> Result<Document> {
-  let serialized_data = bson::to_bson(&body).map_err(MongoSerializeBsonError)?;
-  let document = serialized_data.as_document().unwrap();
-  let mut doc_with_extras = doc! {"_id": _id, "accepted": false, "proposal_price": 0};
-  doc_with_extras.extend(document.clone());
+pub fn build_proposal_document(
+  body: &CreateProposalSchema,
+  _id: String,
+  proposal_price: &Money,
+  actor: &str,
+) -> Result<Document> {
+  let document = to_document(body)?;
+  let mut doc_with_extras = doc! {
+    "_id": _id,
+    "accepted": false,
+    "proposal_price": to_document(proposal_price)?,
+    "version": 1,
+  };
+  doc_with_extras.extend(document);
+  doc_with_extras.extend(created_by(actor));
 
   Ok(doc_with_extras)
 }
 
-pub fn build_deal_document(_id: String, partial_deal: &PartialDealResponse) -> Result<Document> {
-  let serialized_data = bson::to_bson(&partial_deal).map_err(MongoSerializeBsonError)?;
-  let document = serialized_data.as_document().unwrap();
+pub fn build_deal_document(
+  _id: String,
+  partial_deal: &PartialDealResponse,
+  actor: &str,
+) -> Result<Document> {
+  let document = to_document(partial_deal)?;
 
-  let mut doc_with_id = doc! {"_id": _id};
-  doc_with_id.extend(document.clone());
+  let mut doc_with_id = doc! {"_id": _id, "version": 1};
+  doc_with_id.extend(document);
+  doc_with_id.extend(created_by(actor));
   Ok(doc_with_id)
 }
 
@@ -117,30 +266,22 @@ pub fn build_deal_document(_id: String, partial_deal: &PartialDealResponse) -> R
 //   (proposal_body, milestones)
 // }
 
-pub fn build_milestone_document(body: &CreateMilestoneSchema, id: u64) -> Result<bson::Document> {
-  let serialized_data = bson::to_bson(body).map_err(MongoSerializeBsonError)?;
-  let document = serialized_data.as_document().unwrap();
-  let status = "Initialized";
-  let link = "";
-  let mut doc_with_status = doc! {"_id": id.to_string(), "status": status, "link": link};
-  doc_with_status.extend(document.clone());
-  Ok(doc_with_status)
-}
-
 pub fn build_milestones_document(
-  body: &Vec<CreateMilestoneSchema>,
+  body: &[CreateMilestoneSchema],
   mut id: u64,
+  actor: &str,
 ) -> Result<Vec<bson::Document>> {
   let docs = body
     .iter()
     .map(|milestone| {
-      let serialized_data = bson::to_bson(milestone).map_err(MongoSerializeBsonError)?;
-      let document = serialized_data.as_document().unwrap();
-      let status = "Initialized";
+      let document = to_document(milestone)?;
+      let status = bson::to_bson(&MilestoneStatus::Initialized).map_err(MongoSerializeBsonError)?;
       let link = "";
       id += 1;
-      let mut doc_with_status = doc! {"_id": id.to_string(), "status": status, "link": link};
-      doc_with_status.extend(document.clone());
+      let mut doc_with_status =
+        doc! {"_id": id.to_string(), "status": status, "link": link, "version": 1};
+      doc_with_status.extend(document);
+      doc_with_status.extend(created_by(actor));
       Ok(doc_with_status)
     })
     .collect::<Result<Vec<bson::Document>>>()?;
@@ -148,24 +289,79 @@ pub fn build_milestones_document(
 }
 
 pub fn doc_to_task_response(task: &TaskModel) -> Result<TaskResponse> {
-  let proposals_id = task.proposals_id.to_owned().unwrap_or_else(Vec::new);
+  let proposals_id = task.proposals_id.to_owned().unwrap_or_default();
 
   let task_response = TaskResponse {
     id: task.id.to_owned(),
     client_id: task.client_id.to_owned(),
     title: task.title.to_owned(),
-    start_time: task.start_time.to_owned(),
-    deadline: task.deadline.to_owned(),
+    start_time: task.start_time.to_chrono(),
+    deadline: task.deadline.to_chrono(),
     description: task.description.to_owned(),
     skills: task.skills.to_owned(),
     bounty: task.bounty,
     proposals_id,
+    version: task.version,
+    deleted_at: task.deleted_at.map(|d| d.to_chrono()),
+    deleted_by: task.deleted_by.to_owned(),
+    created_at: task.created_at.map(|d| d.to_chrono()),
+    updated_at: task.updated_at.map(|d| d.to_chrono()),
+    created_by: task.created_by.to_owned(),
+    cancelled_at: task.cancelled_at.map(|d| d.to_chrono()),
+    cancelled_by: task.cancelled_by.to_owned(),
+    cancel_reason: task.cancel_reason.to_owned(),
+    edits: task
+      .edits
+      .iter()
+      .map(|edit| TaskEditResponse {
+        at: edit.at.to_chrono(),
+        by: edit.by.to_owned(),
+        before: task_fields_response(&edit.before),
+        after: task_fields_response(&edit.after),
+      })
+      .collect(),
+    status: task.status,
+    history: history_response(&task.history),
+    attachments: task
+      .attachments
+      .iter()
+      .map(|attachment| {
+        attachment_response(
+          attachment,
+          format!("/api/task/{}/attachments/{}", task.id, attachment.id),
+        )
+      })
+      .collect(),
+    visibility: task.visibility,
+    invitations: task
+      .invitations
+      .iter()
+      .map(|invitation| InvitationResponse {
+        freelancer_id: invitation.freelancer_id.to_owned(),
+        status: invitation.status,
+        invited_by: invitation.invited_by.to_owned(),
+        invited_at: invitation.invited_at.to_chrono(),
+        answered_at: invitation.answered_at.map(|d| d.to_chrono()),
+      })
+      .collect(),
   };
   Ok(task_response)
 }
 
+fn task_fields_response(fields: &TaskFields) -> TaskFieldsResponse {
+  TaskFieldsResponse {
+    title: fields.title.to_owned(),
+    description: fields.description.to_owned(),
+    start_time: fields.start_time.map(|d| d.to_chrono()),
+    deadline: fields.deadline.map(|d| d.to_chrono()),
+    skills: fields.skills.to_owned(),
+    bounty: fields.bounty,
+    visibility: fields.visibility,
+  }
+}
+
 pub fn doc_to_proposal_response(proposal: &ProposalModel) -> Result<ProposalResponse> {
-  let milestones_id = proposal.milestones_id.to_owned().unwrap_or_else(Vec::new);
+  let milestones_id = proposal.milestones_id.to_owned().unwrap_or_default();
   let proposal_response = ProposalResponse {
     id: proposal.id.to_owned(),
     task_id: proposal.task_id.to_owned(),
@@ -174,6 +370,14 @@ pub fn doc_to_proposal_response(proposal: &ProposalModel) -> Result<ProposalResp
     milestones_id,
     proposal_price: proposal.proposal_price,
     accepted: proposal.accepted,
+    version: proposal.version,
+    deleted_at: proposal.deleted_at.map(|d| d.to_chrono()),
+    deleted_by: proposal.deleted_by.to_owned(),
+    created_at: proposal.created_at.map(|d| d.to_chrono()),
+    updated_at: proposal.updated_at.map(|d| d.to_chrono()),
+    created_by: proposal.created_by.to_owned(),
+    closed_at: proposal.closed_at.map(|d| d.to_chrono()),
+    closed_reason: proposal.closed_reason.to_owned(),
   };
   Ok(proposal_response)
 }
@@ -182,7 +386,6 @@ pub fn doc_to_detailed_proposal_response(
   proposal: &ProposalModel,
   milestones: Vec<MilestoneResponse>,
 ) -> Result<ProposalDetailedResponse> {
-  let milestones_id = proposal.milestones_id.to_owned().unwrap_or_else(Vec::new);
   let proposal_response = ProposalDetailedResponse {
     id: proposal.id.to_owned(),
     task_id: proposal.task_id.to_owned(),
@@ -191,6 +394,14 @@ pub fn doc_to_detailed_proposal_response(
     milestones,
     proposal_price: proposal.proposal_price,
     accepted: proposal.accepted,
+    version: proposal.version,
+    deleted_at: proposal.deleted_at.map(|d| d.to_chrono()),
+    deleted_by: proposal.deleted_by.to_owned(),
+    created_at: proposal.created_at.map(|d| d.to_chrono()),
+    updated_at: proposal.updated_at.map(|d| d.to_chrono()),
+    created_by: proposal.created_by.to_owned(),
+    closed_at: proposal.closed_at.map(|d| d.to_chrono()),
+    closed_reason: proposal.closed_reason.to_owned(),
   };
   Ok(proposal_response)
 }
@@ -203,8 +414,15 @@ pub fn doc_to_deal_response(deal: &DealModel) -> Result<DealResponse> {
     freelancer_id: deal.freelancer_id.to_owned(),
     client_id: deal.client_id.to_owned(),
     price: deal.price,
-    status: deal.status.to_owned(),
+    status: deal.status,
     address: deal.address.to_owned(),
+    history: history_response(&deal.history),
+    version: deal.version,
+    deleted_at: deal.deleted_at.map(|d| d.to_chrono()),
+    deleted_by: deal.deleted_by.to_owned(),
+    created_at: deal.created_at.map(|d| d.to_chrono()),
+    updated_at: deal.updated_at.map(|d| d.to_chrono()),
+    created_by: deal.created_by.to_owned(),
   };
   Ok(deal_response)
 }
@@ -218,9 +436,17 @@ pub fn doc_to_proposal_and_deal_response(
     client_id: proposal.client_id.to_owned(),
     task_id: proposal.task_id.to_owned(),
     freelancer_id: proposal.freelancer_id.to_owned(),
-    milestones_id: proposal.milestones_id.to_owned().unwrap_or_else(Vec::new),
+    milestones_id: proposal.milestones_id.to_owned().unwrap_or_default(),
     proposal_price: proposal.proposal_price,
     accepted: proposal.accepted,
+    version: proposal.version,
+    deleted_at: proposal.deleted_at.map(|d| d.to_chrono()),
+    deleted_by: proposal.deleted_by.to_owned(),
+    created_at: proposal.created_at.map(|d| d.to_chrono()),
+    updated_at: proposal.updated_at.map(|d| d.to_chrono()),
+    created_by: proposal.created_by.to_owned(),
+    closed_at: proposal.closed_at.map(|d| d.to_chrono()),
+    closed_reason: proposal.closed_reason.to_owned(),
   };
 
   let partial_deal_response = PartialDealResponse {
@@ -229,8 +455,8 @@ pub fn doc_to_proposal_and_deal_response(
     freelancer_id: proposal.freelancer_id.to_owned(),
     client_id: proposal.client_id.to_owned(),
     price: proposal.proposal_price,
-    status: "Initialized".to_string(),
-    address: "0x0".to_string(),
+    status: DealStatus::Initialized,
+    address: None,
   };
   Ok((proposal_response, partial_deal_response))
 }
@@ -246,8 +472,15 @@ pub fn docs_to_deal_response(
     freelancer_id: partial_deal.freelancer_id.to_owned(),
     client_id: partial_deal.client_id.to_owned(),
     price: partial_deal.price,
-    status: partial_deal.status.to_owned(),
+    status: partial_deal.status,
     address: partial_deal.address.to_owned(),
+    history: history_response(&deal.history),
+    version: deal.version,
+    deleted_at: deal.deleted_at.map(|d| d.to_chrono()),
+    deleted_by: deal.deleted_by.to_owned(),
+    created_at: deal.created_at.map(|d| d.to_chrono()),
+    updated_at: deal.updated_at.map(|d| d.to_chrono()),
+    created_by: deal.created_by.to_owned(),
   };
   Ok(deal_response)
 }
@@ -265,24 +498,73 @@ pub fn docs_to_deal_response(
 //   (vec, price)
 // }
 
+pub fn history_response<S: Copy>(history: &[StatusChange<S>]) -> Vec<StatusChangeResponse<S>> {
+  history
+    .iter()
+    .map(|change| StatusChangeResponse {
+      from: change.from,
+      to: change.to,
+      at: change.at.to_chrono(),
+      by: change.by.to_owned(),
+    })
+    .collect()
+}
+
+fn attachment_response(attachment: &Attachment, url: String) -> AttachmentResponse {
+  AttachmentResponse {
+    id: attachment.id.to_owned(),
+    file_name: attachment.file_name.to_owned(),
+    content_type: attachment.content_type.to_owned(),
+    size: attachment.size,
+    uploaded_by: attachment.uploaded_by.to_owned(),
+    uploaded_at: attachment.uploaded_at.to_chrono(),
+    url,
+  }
+}
+
 pub fn doc_to_milestone_response(milestone: &MilestoneModel) -> Result<MilestoneResponse> {
   let milestone_response = MilestoneResponse {
     id: milestone.id.to_owned(),
     proposal_id: milestone.proposal_id.to_owned(),
     description: milestone.description.to_owned(),
-    deadline: milestone.deadline.to_owned(),
+    deadline: milestone.deadline.to_chrono(),
     price: milestone.price,
     link: milestone.link.to_owned(),
-    status: milestone.status.to_owned(),
+    status: milestone.status,
+    history: history_response(&milestone.history),
+    reminded_at: milestone.reminded_at.map(|d| d.to_chrono()),
+    deliverables: milestone
+      .deliverables
+      .iter()
+      .map(|deliverable| {
+        attachment_response(
+          deliverable,
+          format!(
+            "/api/milestone/{}/{}/deliverables/{}",
+            milestone.proposal_id, milestone.id, deliverable.id
+          ),
+        )
+      })
+      .collect(),
+    version: milestone.version,
+    deleted_at: milestone.deleted_at.map(|d| d.to_chrono()),
+    deleted_by: milestone.deleted_by.to_owned(),
+    created_at: milestone.created_at.map(|d| d.to_chrono()),
+    updated_at: milestone.updated_at.map(|d| d.to_chrono()),
+    created_by: milestone.created_by.to_owned(),
   };
   Ok(milestone_response)
 }
 
-pub fn build_review_document(body: &CreateReviewSchema, _id: String) -> Result<bson::Document> {
-  let serialized_data = bson::to_bson(body).map_err(MongoSerializeBsonError)?;
-  let document = serialized_data.as_document().unwrap();
-  let mut doc_with_id = doc! {"_id": _id};
-  doc_with_id.extend(document.clone());
+pub fn build_review_document(
+  body: &CreateReviewSchema,
+  _id: String,
+  actor: &str,
+) -> Result<bson::Document> {
+  let document = to_document(body)?;
+  let mut doc_with_id = doc! {"_id": _id, "version": 1};
+  doc_with_id.extend(document);
+  doc_with_id.extend(created_by(actor));
   Ok(doc_with_id)
 }
 
@@ -294,6 +576,69 @@ pub fn doc_to_review_response(review: &ReviewModel) -> Result<ReviewResponse> {
     deal_id: review.deal_id.to_owned(),
     stars: review.stars.to_owned(),
     review: review.review.to_owned(),
+    version: review.version,
+    deleted_at: review.deleted_at.map(|d| d.to_chrono()),
+    deleted_by: review.deleted_by.to_owned(),
+    created_at: review.created_at.map(|d| d.to_chrono()),
+    updated_at: review.updated_at.map(|d| d.to_chrono()),
+    created_by: review.created_by.to_owned(),
   };
   Ok(review_response)
 }
+
+pub fn build_skill_document(body: &CreateSkillSchema, actor: &str) -> Result<bson::Document> {
+  let name = body.name.split_whitespace().collect::<Vec<_>>().join(" ");
+  let mut keys = vec![skill_key(&name)];
+  let mut aliases = Vec::new();
+  for alias in &body.aliases {
+    let key = skill_key(alias);
+    if !keys.contains(&key) {
+      keys.push(key);
+      aliases.push(alias.trim().to_owned());
+    }
+  }
+  let mut document = doc! {
+    "_id": skill_id(&name),
+    "name": name,
+    "category": body.category.as_deref().map(str::trim),
+    "aliases": aliases,
+    "keys": keys,
+    "version": 1,
+  };
+  document.extend(created_by(actor));
+  Ok(document)
+}
+
+pub fn doc_to_skill_response(skill: &SkillModel) -> Result<SkillResponse> {
+  let skill_response = SkillResponse {
+    id: skill.id.to_owned(),
+    name: skill.name.to_owned(),
+    category: skill.category.to_owned(),
+    aliases: skill.aliases.to_owned(),
+    version: skill.version,
+    created_at: skill.created_at.map(|d| d.to_chrono()),
+    updated_at: skill.updated_at.map(|d| d.to_chrono()),
+    created_by: skill.created_by.to_owned(),
+  };
+  Ok(skill_response)
+}
+
+pub fn doc_to_job_response(job: &JobModel) -> JobResponse {
+  JobResponse {
+    id: job.id.to_owned(),
+    kind: job.kind,
+    status: job.status,
+    run_at: job.run_at.to_chrono(),
+    every_secs: job.every_secs,
+    attempts: job.attempts,
+    max_attempts: job.max_attempts,
+    last_run_at: job.last_run_at.map(|d| d.to_chrono()),
+    last_result: job.last_result.to_owned(),
+    last_error: job.last_error.to_owned(),
+    lease_owner: job.lease_owner.to_owned(),
+    lease_until: job.lease_until.map(|d| d.to_chrono()),
+    finished_at: job.finished_at.map(|d| d.to_chrono()),
+    created_at: job.created_at.map(|d| d.to_chrono()),
+    updated_at: job.updated_at.map(|d| d.to_chrono()),
+  }
+}
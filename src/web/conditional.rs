@@ -0,0 +1,84 @@
+use crate::error::MyError;
+use axum::async_trait;
+use axum::extract::FromRequestParts;
+use axum::http::header::IF_MATCH;
+use axum::http::request::Parts;
+
+/// Version the caller last saw, taken from the `If-Match` header
+/// (`"3"` or `W/"3"`). A missing header or `*` sets no precondition.
+pub struct IfMatch(pub Option<i64>);
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for IfMatch {
+  type Rejection = MyError;
+
+  async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+    let Some(value) = parts.headers.get(IF_MATCH) else {
+      return Ok(IfMatch(None));
+    };
+    let value = value
+      .to_str()
+      .map_err(|_| MyError::PreconditionFailedError("unreadable If-Match header".to_string()))?
+      .trim();
+    if value == "*" {
+      return Ok(IfMatch(None));
+    }
+
+    // An entity tag we never issued can't match the current version.
+    value
+      .trim_start_matches("W/")
+      .trim_matches('"')
+      .parse()
+      .map(|version| IfMatch(Some(version)))
+      .map_err(|_| MyError::PreconditionFailedError(format!("unknown entity tag {}", value)))
+  }
+}
+
+/// Entity tag sent back with single-resource responses.
+pub fn etag(version: i64) -> String {
+  format!("\"{}\"", version)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use axum::http::Request;
+
+  async fn if_match(value: Option<&str>) -> Result<Option<i64>, MyError> {
+    let mut request = Request::builder();
+    if let Some(value) = value {
+      request = request.header(IF_MATCH, value);
+    }
+    let (mut parts, _) = request.body(()).unwrap().into_parts();
+    IfMatch::from_request_parts(&mut parts, &())
+      .await
+      .map(|IfMatch(version)| version)
+  }
+
+  #[tokio::test]
+  async fn reads_strong_and_weak_tags() {
+    assert_eq!(if_match(Some("\"3\"")).await.unwrap(), Some(3));
+    assert_eq!(if_match(Some(" W/\"12\" ")).await.unwrap(), Some(12));
+    assert_eq!(if_match(Some(&etag(7))).await.unwrap(), Some(7));
+  }
+
+  #[tokio::test]
+  async fn sets_no_precondition_without_a_version() {
+    assert_eq!(if_match(None).await.unwrap(), None);
+    assert_eq!(if_match(Some("*")).await.unwrap(), None);
+  }
+
+  #[tokio::test]
+  async fn rejects_tags_it_never_issued() {
+    for value in ["\"abc\"", "W/", "\"3\", \"4\"", ""] {
+      assert!(
+        matches!(
+          if_match(Some(value)).await,
+          Err(MyError::PreconditionFailedError(_))
+        ),
+        "{}",
+        value
+      );
+    }
+  }
+}
@@ -0,0 +1,79 @@
+use crate::address::Address;
+use crate::error::MyError;
+use axum::async_trait;
+use axum::extract::FromRequestParts;
+use axum::http::request::Parts;
+
+/// Identity of the authenticated caller, resolved by `mw_require_auth`
+/// from the `sub` claim of the auth cookie. Address ids are held lowercase,
+/// as they are stored, so they compare as plain strings.
+#[derive(Clone, Debug)]
+pub struct Ctx {
+  user_id: String,
+}
+
+impl Ctx {
+  pub fn new(user_id: String) -> Self {
+    Self {
+      user_id: Address::normalize(&user_id),
+    }
+  }
+
+  pub fn user_id(&self) -> &str {
+    &self.user_id
+  }
+
+  /// Admins are listed (comma separated user ids, in any case) in the
+  /// `ADMIN_IDS` env var.
+  pub fn is_admin(&self) -> bool {
+    is_listed(
+      &std::env::var("ADMIN_IDS").unwrap_or_default(),
+      &self.user_id,
+    )
+  }
+}
+
+/// Whether `user_id` is among the comma separated `ids`.
+fn is_listed(ids: &str, user_id: &str) -> bool {
+  ids
+    .split(',')
+    .map(str::trim)
+    .any(|id| !id.is_empty() && Address::normalize(id) == user_id)
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for Ctx {
+  type Rejection = MyError;
+
+  async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+    parts
+      .extensions
+      .get::<Ctx>()
+      .cloned()
+      .ok_or(MyError::AuthFailNoAuthTokenCookie)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const CHECKSUMMED: &str = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed";
+
+  #[test]
+  fn holds_addresses_lowercase() {
+    let ctx = Ctx::new(CHECKSUMMED.to_string());
+    assert_eq!(ctx.user_id(), CHECKSUMMED.to_ascii_lowercase());
+    assert_eq!(Ctx::new("seed".to_string()).user_id(), "seed");
+  }
+
+  #[test]
+  fn matches_admins_whatever_their_case() {
+    let ids = format!("adm, {}", CHECKSUMMED);
+    let admin = Ctx::new(CHECKSUMMED.to_string());
+    assert!(is_listed(&ids, admin.user_id()));
+    assert!(is_listed(&ids, "adm"));
+    assert!(!is_listed(&ids, "other"));
+    assert!(!is_listed("", ""));
+  }
+}
@@ -1,6 +1,10 @@
+pub mod conditional;
+pub mod ctx;
 pub mod mw_auth;
 pub mod route;
 pub mod token;
+pub mod upload;
+pub mod validated;
 
 pub const AUTH_TOKEN: &str = "auth-token";
 pub const SECRET: &str = "secret_key_123";
\ No newline at end of file
@@ -1,26 +1,20 @@
-use crate::error::{MyError, MyError::AuthFailNoAuthTokenCookie};
-use crate::response;
+use crate::web::ctx::Ctx;
 use crate::web::token::Claims;
 use crate::web::{AUTH_TOKEN, SECRET};
-use axum::body::{Body, BoxBody, Bytes, HttpBody};
-use axum::extract::{Extension, FromRequestParts, State};
-use axum::http::request::Parts;
+use axum::body::BoxBody;
 use axum::http::Request;
 use axum::middleware::Next;
-use axum::response::{IntoResponse, Response};
-use axum::BoxError;
+use axum::response::Response;
 use jsonwebtoken::{decode, DecodingKey, Validation};
-use std::collections::HashSet;
-use std::convert::Infallible;
-use tower_cookies::{Cookie, Cookies};
+use tower_cookies::Cookies;
 
-pub async fn mw_require_auth<B>(cookies: Cookies, req: Request<B>, next: Next<B>) -> Response {
+pub async fn mw_require_auth<B>(cookies: Cookies, mut req: Request<B>, next: Next<B>) -> Response {
   //next.run(req).await
   if let Some(auth_cookie) = cookies.get(AUTH_TOKEN) {
     let mut validation = Validation::default();
     validation.set_audience(
       std::env::var("AUTH_AUDIENCE")
-        .unwrap_or(String::default())
+        .unwrap_or_default()
         .split(",")
         .map(String::from)
         .collect::<Vec<String>>()
@@ -33,14 +27,20 @@ pub async fn mw_require_auth<B>(cookies: Cookies, req: Request<B>, next: Next<B>
       &validation,
     );
 
-    if token.is_err() {
-      println!("{:?}", token.err().unwrap());
-      return Response::builder()
-        .status(401)
-        .body(BoxBody::default())
-        .unwrap();
-    }
-
+    let token = match token {
+      Ok(token) => token,
+      Err(e) => {
+        println!("{:?}", e);
+        return Response::builder()
+          .status(401)
+          .body(BoxBody::default())
+          .unwrap();
+      }
+    };
+
+    req
+      .extensions_mut()
+      .insert(Ctx::new(token.claims.sub().to_owned()));
     next.run(req).await
   } else {
     Response::builder()
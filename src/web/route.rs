@@ -1,58 +1,165 @@
 use crate::handler::{
-  add_milestones_handler, get_proposal_handler, list_milestone_handler, submit_milestone_handler,
+  accept_invitation_handler, add_deliverables_handler, add_milestones_handler,
+  add_task_attachments_handler, cache_stats_handler, cancel_job_handler, cancel_task_handler,
+  change_feed_handler, create_skill_handler, decline_invitation_handler, delete_client_handler,
+  delete_deal_handler, delete_deliverable_handler, delete_freelancer_handler,
+  delete_milestone_handler, delete_proposal_handler, delete_review_handler,
+  delete_task_attachment_handler, delete_task_handler, download_deliverable_handler,
+  download_task_attachment_handler, get_deal_handler, get_freelancer_handler, get_proposal_handler,
+  healthz_handler, invite_freelancers_handler, list_jobs_handler, list_milestone_handler,
+  list_skills_handler, merge_skills_handler, readyz_handler, recommended_tasks_handler,
+  restore_client_handler, restore_deal_handler, restore_freelancer_handler,
+  restore_milestone_handler, restore_proposal_handler, restore_review_handler,
+  restore_task_handler, retry_job_handler, revoke_invitation_handler, submit_milestone_handler,
+  transition_deal_handler, transition_milestone_handler, update_task_handler,
+};
+use crate::web::mw_auth::mw_require_auth;
+use crate::{
+  handler::{
+    add_client_handler, add_freelancer_handler, add_review_handler, api_login_handler,
+    approve_proposal_handler, create_task_handler, get_task_handler, list_clients_handler,
+    list_deals_handler, list_freelancers_handler, list_proposal_handler, list_tasks_handler,
+    submit_proposal_handler, update_deal_handler,
+  },
+  AppState,
 };
-use crate::web::mw_auth::{mw_require_auth};
-use crate::{handler::{
-  add_client_handler, add_freelancer_handler, add_review_handler, api_login_handler,
-  approve_proposal_handler, create_task_handler, get_task_handler, list_clients_handler,
-  list_deals_handler, list_freelancers_handler, list_proposal_handler, list_tasks_handler,
-  submit_proposal_handler, update_deal_handler
-}, AppState, web};
 use axum::response::Response;
 use axum::{
+  extract::DefaultBodyLimit,
   middleware,
-  routing::{get, patch, post},
+  routing::{delete, get, patch, post},
   Router,
 };
 use std::sync::Arc;
 use tower_cookies::CookieManagerLayer;
 
 pub fn create_router(app_state: Arc<AppState>) -> Router {
+  // Multipart bodies carry whole files, past the default 2 MB.
+  let upload_limit = DefaultBodyLimit::max(app_state.upload_limits.max_body());
   Router::new()
     .route(
       "/api/task",
       post(create_task_handler).get(list_tasks_handler),
     )
-    .route("/api/task/:skill", get(get_task_handler))
+    .route(
+      "/api/task/:id",
+      get(get_task_handler)
+        .patch(update_task_handler)
+        .delete(delete_task_handler),
+    )
+    .route("/api/task/:id/restore", patch(restore_task_handler))
+    .route("/api/task/:id/cancel", patch(cancel_task_handler))
+    .route(
+      "/api/task/:id/invitations",
+      post(invite_freelancers_handler),
+    )
+    .route(
+      "/api/task/:id/invitations/:freelancer_id",
+      delete(revoke_invitation_handler),
+    )
+    .route(
+      "/api/task/:id/invitation/accept",
+      patch(accept_invitation_handler),
+    )
+    .route(
+      "/api/task/:id/invitation/decline",
+      patch(decline_invitation_handler),
+    )
+    .route(
+      "/api/task/:id/attachments",
+      post(add_task_attachments_handler).layer(upload_limit.clone()),
+    )
+    .route(
+      "/api/task/:id/attachments/:attachment_id",
+      get(download_task_attachment_handler).delete(delete_task_attachment_handler),
+    )
     .route(
       "/api/proposal",
       post(submit_proposal_handler).get(list_proposal_handler),
     )
     .route(
       "/api/proposal/:proposal_id",
-      get(get_proposal_handler).patch(approve_proposal_handler),
+      get(get_proposal_handler)
+        .patch(approve_proposal_handler)
+        .delete(delete_proposal_handler),
+    )
+    .route(
+      "/api/proposal/:proposal_id/restore",
+      patch(restore_proposal_handler),
     )
     .route(
       "/api/milestone",
       post(add_milestones_handler).get(list_milestone_handler),
     )
+    .route(
+      "/api/milestone/:proposal_id/:milestone_id",
+      delete(delete_milestone_handler),
+    )
+    .route(
+      "/api/milestone/:proposal_id/:milestone_id/restore",
+      patch(restore_milestone_handler),
+    )
+    .route(
+      "/api/milestone/:proposal_id/:milestone_id/status",
+      patch(transition_milestone_handler),
+    )
+    .route(
+      "/api/milestone/:proposal_id/:milestone_id/deliverables",
+      post(add_deliverables_handler).layer(upload_limit),
+    )
+    .route(
+      "/api/milestone/:proposal_id/:milestone_id/deliverables/:attachment_id",
+      get(download_deliverable_handler).delete(delete_deliverable_handler),
+    )
     .route(
       "/api/milestone/:proposal_id/:milestone_id/:link",
       patch(submit_milestone_handler),
     )
     .route("/api/deal", get(list_deals_handler))
+    .route(
+      "/api/deal/:deal_id",
+      get(get_deal_handler).delete(delete_deal_handler),
+    )
+    .route("/api/deal/:deal_id/restore", patch(restore_deal_handler))
+    .route("/api/deal/:deal_id/status", patch(transition_deal_handler))
     .route(
       "/api/deal/:deal_id/:transacion_id",
       patch(update_deal_handler),
     )
-    .route("/api/freelancer", get(list_freelancers_handler), )
+    .route("/api/freelancer", get(list_freelancers_handler))
+    .route(
+      "/api/freelancer/me/recommended-tasks",
+      get(recommended_tasks_handler),
+    )
+    .route(
+      "/api/freelancer/:id",
+      get(get_freelancer_handler).delete(delete_freelancer_handler),
+    )
+    .route(
+      "/api/freelancer/:id/restore",
+      patch(restore_freelancer_handler),
+    )
     .route("/api/review", post(add_review_handler))
+    .route("/api/review/:id", delete(delete_review_handler))
+    .route("/api/review/:id/restore", patch(restore_review_handler))
     .route("/api/client", get(list_clients_handler)) //provider, employee
+    .route("/api/client/:id", delete(delete_client_handler))
+    .route("/api/client/:id/restore", patch(restore_client_handler))
+    .route("/api/feed", get(change_feed_handler))
+    .route("/api/admin/cache", get(cache_stats_handler))
+    .route("/api/admin/skills", post(create_skill_handler))
+    .route("/api/admin/skills/merge", post(merge_skills_handler))
+    .route("/api/admin/jobs", get(list_jobs_handler))
+    .route("/api/admin/jobs/:id/retry", patch(retry_job_handler))
+    .route("/api/admin/jobs/:id/cancel", patch(cancel_job_handler))
     .layer(middleware::map_response(main_response_mapper))
     .layer(middleware::from_fn(mw_require_auth))
     .route("/api/client", post(add_client_handler))
     .route("/api/freelancer", post(add_freelancer_handler))
     .route("/api/login", post(api_login_handler))
+    .route("/api/skills", get(list_skills_handler))
+    .route("/healthz", get(healthz_handler))
+    .route("/readyz", get(readyz_handler))
     .layer(CookieManagerLayer::new())
     .with_state(app_state)
 }
@@ -24,6 +24,10 @@ impl Claims {
             sub,
         }
     }
+
+    pub fn sub(&self) -> &str {
+        &self.sub
+    }
 }
 
 pub fn generate_token(user_id: String, exp: Option<u64>) -> String {
@@ -0,0 +1,161 @@
+use axum::body::Bytes;
+use axum::extract::Multipart;
+
+use crate::error::MyError::{
+  self, InvalidUploadError, PayloadTooLargeError, UnsupportedMediaTypeError,
+};
+
+/// Files kept per task, and per milestone.
+pub const MAX_ATTACHMENTS: usize = 10;
+
+/// The multipart field files are sent in.
+const FILE_FIELD: &str = "file";
+
+/// Longest file name kept, in characters.
+const MAX_FILE_NAME: usize = 200;
+
+const DEFAULT_TYPES: &str =
+  "application/pdf,application/zip,image/gif,image/jpeg,image/png,image/webp,text/markdown,text/plain";
+
+/// What uploads may be: at most `ATTACHMENT_MAX_BYTES` per file (10 MiB by
+/// default) of one of the `ATTACHMENT_TYPES` (comma-separated content types).
+#[derive(Debug, Clone)]
+pub struct UploadLimits {
+  pub max_bytes: usize,
+  pub types: Vec<String>,
+}
+
+impl UploadLimits {
+  pub fn from_env() -> Self {
+    let max_bytes = std::env::var("ATTACHMENT_MAX_BYTES")
+      .ok()
+      .and_then(|bytes| bytes.parse().ok())
+      .unwrap_or(10 * 1024 * 1024);
+    let types = std::env::var("ATTACHMENT_TYPES")
+      .unwrap_or_else(|_| DEFAULT_TYPES.to_string())
+      .split(',')
+      .map(|content_type| content_type.trim().to_lowercase())
+      .filter(|content_type| !content_type.is_empty())
+      .collect();
+    Self { max_bytes, types }
+  }
+
+  /// The largest request body carrying a full batch of files.
+  pub fn max_body(&self) -> usize {
+    self.max_bytes * MAX_ATTACHMENTS + 64 * 1024
+  }
+}
+
+/// A file received in a multipart request.
+#[derive(Debug)]
+pub struct Upload {
+  pub file_name: String,
+  pub content_type: String,
+  pub bytes: Bytes,
+}
+
+/// The files sent as `file` fields, checked against `limits`. Each is read
+/// only up to the size limit.
+pub async fn read_uploads(
+  mut multipart: Multipart,
+  limits: &UploadLimits,
+) -> Result<Vec<Upload>, MyError> {
+  let mut uploads = Vec::new();
+  while let Some(mut field) = multipart
+    .next_field()
+    .await
+    .map_err(|e| InvalidUploadError(e.to_string()))?
+  {
+    if field.name() != Some(FILE_FIELD) {
+      return Err(InvalidUploadError(format!(
+        "unexpected field {}, send files as {}",
+        field.name().unwrap_or_default(),
+        FILE_FIELD
+      )));
+    }
+    if uploads.len() == MAX_ATTACHMENTS {
+      return Err(InvalidUploadError(format!(
+        "at most {} files at once",
+        MAX_ATTACHMENTS
+      )));
+    }
+    let file_name = file_name(field.file_name().unwrap_or_default());
+    let content_type = field
+      .content_type()
+      .and_then(|content_type| content_type.split(';').next())
+      .map(|content_type| content_type.trim().to_lowercase())
+      .unwrap_or_default();
+    if !limits.types.contains(&content_type) {
+      return Err(UnsupportedMediaTypeError(format!(
+        "{} is {}, expected one of {}",
+        file_name,
+        if content_type.is_empty() {
+          "untyped"
+        } else {
+          &content_type
+        },
+        limits.types.join(", ")
+      )));
+    }
+
+    let mut bytes = Vec::new();
+    while let Some(chunk) = field
+      .chunk()
+      .await
+      .map_err(|e| InvalidUploadError(e.to_string()))?
+    {
+      if bytes.len() + chunk.len() > limits.max_bytes {
+        return Err(PayloadTooLargeError(format!(
+          "{} is over {} bytes",
+          file_name, limits.max_bytes
+        )));
+      }
+      bytes.extend_from_slice(&chunk);
+    }
+    if bytes.is_empty() {
+      return Err(InvalidUploadError(format!("{} is empty", file_name)));
+    }
+    uploads.push(Upload {
+      file_name,
+      content_type,
+      bytes: Bytes::from(bytes),
+    });
+  }
+  if uploads.is_empty() {
+    return Err(InvalidUploadError(format!(
+      "no file sent, send files as {}",
+      FILE_FIELD
+    )));
+  }
+  Ok(uploads)
+}
+
+/// The last component of a client's file name, without control characters.
+fn file_name(sent: &str) -> String {
+  let name: String = sent
+    .rsplit(['/', '\\'])
+    .next()
+    .unwrap_or_default()
+    .chars()
+    .filter(|c| !c.is_control())
+    .take(MAX_FILE_NAME)
+    .collect();
+  match name.trim() {
+    "" => "file".to_string(),
+    name => name.to_string(),
+  }
+}
+
+/// A `Content-Disposition` downloading `file_name` under an ASCII-safe name.
+pub fn content_disposition(file_name: &str) -> String {
+  let safe: String = file_name
+    .chars()
+    .map(|c| match c {
+      ' ' => ' ',
+      '"' | '\\' => '_',
+      c if c.is_ascii_graphic() => c,
+      _ => '_',
+    })
+    .collect();
+  format!("attachment; filename=\"{}\"", safe)
+}
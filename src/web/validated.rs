@@ -0,0 +1,36 @@
+use crate::error::MyError;
+use axum::async_trait;
+use axum::body::HttpBody;
+use axum::extract::FromRequest;
+use axum::http::Request;
+use axum::response::{IntoResponse, Response};
+use axum::{BoxError, Json};
+use serde::de::DeserializeOwned;
+use validator::Validate;
+
+/// A JSON body that passed its schema's validation rules. A body that isn't
+/// JSON of the schema's shape is rejected as by `Json`, one breaking a rule
+/// with a 422 listing the failed rules per field.
+pub struct ValidatedJson<T>(pub T);
+
+#[async_trait]
+impl<T, S, B> FromRequest<S, B> for ValidatedJson<T>
+where
+  T: DeserializeOwned + Validate,
+  S: Send + Sync,
+  B: HttpBody + Send + 'static,
+  B::Data: Send,
+  B::Error: Into<BoxError>,
+{
+  type Rejection = Response;
+
+  async fn from_request(req: Request<B>, state: &S) -> Result<Self, Self::Rejection> {
+    let Json(value) = Json::<T>::from_request(req, state)
+      .await
+      .map_err(IntoResponse::into_response)?;
+    value
+      .validate()
+      .map_err(|errors| MyError::InvalidInputError(errors).into_response())?;
+    Ok(ValidatedJson(value))
+  }
+}